@@ -1,11 +1,30 @@
-use std::pin::Pin;
+use std::{
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use futures::{stream::FuturesUnordered, Stream, StreamExt};
 use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+};
+use tracing::{debug, Instrument};
 
 use crate::{
+    cache::{BlockhashCache, SwapCache},
     error::SwapError,
-    types::{Provider, QuoteRequest, QuoteResponse, SwapConfig, SwapResult},
+    metrics::MetricsCollector,
+    preflight::PreflightReport,
+    send::{is_slippage_exceeded, send_with_blockhash_refresh},
+    simulate::{simulate, SimSummary},
+    token::{is_token_program, transfer_fee, NATIVE_MINT},
+    types::{
+        AcceptableOutput, Capability, Provider, QuoteContext, QuoteGuard, QuoteRequest,
+        QuoteResponse, SlippageEscalation, SwapConfig, SwapMode, SwapResult,
+    },
 };
 
 type QuoteFuture<'a> =
@@ -18,8 +37,27 @@ use crate::jupiter::JupiterProvider;
 #[cfg(feature = "titan")]
 use crate::titan::TitanProvider;
 
+const DEFAULT_SOL_FEE_RESERVE_LAMPORTS: u64 = 2_000_000;
+const MAX_SLIPPAGE_BPS: u16 = 10_000;
+const MAX_BATCH_CONCURRENCY: usize = 8;
+const DEFAULT_BLOCKHASH_REFRESH_RETRIES: u32 = 2;
+const ROUTE_PROBE_AMOUNT: u64 = 1_000;
+const MAX_BOUND_PROBES: u32 = 64;
+const RATE_LIMIT_RETRIES: u32 = 3;
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_millis(250);
+
 pub struct SwapAggregator {
     pub default_slippage_bps: u16,
+    pub quote_guard: Option<QuoteGuard>,
+    pub swap_cache: Option<SwapCache>,
+    pub reserve_sol_for_fees: u64,
+    pub blockhash_refresh_retries: u32,
+    pub blockhash_cache: Option<BlockhashCache>,
+    pub slippage_escalation: Option<SlippageEscalation>,
+    pub rank_by_net_output: bool,
+    pub metrics: MetricsCollector,
+    // Reads input mints for `input_is_fee_on_transfer` requests; see `with_rpc_client`.
+    pub rpc_client: Option<Arc<RpcClient>>,
     #[cfg(feature = "jupiter")]
     pub jupiter: Option<JupiterProvider>,
     #[cfg(feature = "titan")]
@@ -29,46 +67,212 @@ pub struct SwapAggregator {
 }
 
 impl SwapAggregator {
+    #[expect(
+        clippy::panic,
+        reason = "new is the panicking convenience over try_new"
+    )]
     pub fn new(config: SwapConfig) -> Self {
+        Self::try_new(config).unwrap_or_else(|e| panic!("invalid swap config: {e}"))
+    }
+
+    pub fn try_new(config: SwapConfig) -> Result<Self, SwapError> {
+        validate_config(&config)?;
+        let aggregator = Self::build(config)?;
+        aggregator.validate()?;
+        Ok(aggregator)
+    }
+
+    fn build(config: SwapConfig) -> Result<Self, SwapError> {
         let _ = rustls::crypto::ring::default_provider().install_default();
 
         let SwapConfig {
             default_slippage_bps,
             jupiter_api_url,
             jupiter_api_key,
+            jupiter_tier,
+            jupiter_use_token_ledger,
+            jupiter_dynamic_cu_limit,
             titan_ws_url,
             titan_token,
             dflow_api_url,
             dflow_api_key,
             dflow_max_route_length,
+            dflow_priority_fee_lamports,
+            dflow_jito_tip_lamports,
+            quote_guard,
+            swap_cache_ttl,
+            reserve_sol_for_fees,
+            blockhash_refresh_retries,
+            blockhash_cache_ttl,
+            slippage_escalation,
+            record_dir,
+            rank_by_net_output,
+            max_response_bytes,
+            jupiter_request_signer,
+            dflow_request_signer,
         } = config;
 
         #[cfg(not(feature = "jupiter"))]
-        let _ = (jupiter_api_url, jupiter_api_key);
+        let _ = (
+            jupiter_api_url,
+            jupiter_api_key,
+            jupiter_tier,
+            jupiter_use_token_ledger,
+            jupiter_dynamic_cu_limit,
+            jupiter_request_signer,
+        );
         #[cfg(not(feature = "titan"))]
         let _ = (titan_ws_url, titan_token);
+        #[cfg(not(any(feature = "jupiter", feature = "dflow")))]
+        let _ = (record_dir, max_response_bytes);
         #[cfg(not(feature = "dflow"))]
-        let _ = (dflow_api_url, dflow_api_key, dflow_max_route_length);
+        let _ = (
+            dflow_api_url,
+            dflow_api_key,
+            dflow_max_route_length,
+            dflow_priority_fee_lamports,
+            dflow_jito_tip_lamports,
+            dflow_request_signer,
+        );
 
-        Self {
+        Ok(Self {
             default_slippage_bps,
+            quote_guard,
+            swap_cache: swap_cache_ttl.map(SwapCache::new),
+            reserve_sol_for_fees: reserve_sol_for_fees.unwrap_or(DEFAULT_SOL_FEE_RESERVE_LAMPORTS),
+            blockhash_refresh_retries: blockhash_refresh_retries
+                .unwrap_or(DEFAULT_BLOCKHASH_REFRESH_RETRIES),
+            blockhash_cache: blockhash_cache_ttl.map(BlockhashCache::new),
+            slippage_escalation,
+            rank_by_net_output: rank_by_net_output.unwrap_or(false),
+            metrics: MetricsCollector::default(),
+            rpc_client: None,
             #[cfg(feature = "jupiter")]
-            jupiter: Some(JupiterProvider::new(jupiter_api_url, jupiter_api_key)),
+            jupiter: Some(
+                JupiterProvider::new(
+                    jupiter_api_url,
+                    jupiter_api_key,
+                    jupiter_tier,
+                    jupiter_use_token_ledger,
+                    jupiter_dynamic_cu_limit,
+                )?
+                .with_record_dir(record_dir.clone())
+                .with_max_response_bytes(max_response_bytes)
+                .with_request_signer(jupiter_request_signer),
+            ),
             #[cfg(feature = "titan")]
             titan: Some(TitanProvider::new(titan_ws_url, titan_token)),
             #[cfg(feature = "dflow")]
-            dflow: Some(DflowProvider::new(
-                dflow_api_url,
-                dflow_api_key,
-                dflow_max_route_length,
-            )),
+            dflow: Some(
+                DflowProvider::new(
+                    dflow_api_url,
+                    dflow_api_key,
+                    dflow_max_route_length,
+                    dflow_priority_fee_lamports,
+                    dflow_jito_tip_lamports,
+                )?
+                .with_record_dir(record_dir)
+                .with_max_response_bytes(max_response_bytes)
+                .with_request_signer(dflow_request_signer),
+            ),
+        })
+    }
+
+    fn validate(&self) -> Result<(), SwapError> {
+        if !Provider::ALL.iter().any(|p| self.is_configured(*p)) {
+            return Err(SwapError::InvalidConfig(
+                "no swap provider is enabled".to_string(),
+            ));
+        }
+        #[cfg(feature = "jupiter")]
+        if let Some(p) = &self.jupiter {
+            validate_url("jupiter base url", &p.base_url, &["http", "https"])?;
         }
+        #[cfg(feature = "titan")]
+        if let Some(p) = &self.titan {
+            validate_url("titan ws url", &p.ws_url, &["ws", "wss"])?;
+        }
+        #[cfg(feature = "dflow")]
+        if let Some(p) = &self.dflow {
+            validate_url("dflow api url", &p.base_url, &["http", "https"])?;
+        }
+        Ok(())
+    }
+
+    // Lets `quote` net an `input_is_fee_on_transfer` request's amount of the mint's transfer fee.
+    pub fn with_rpc_client(mut self, rpc_client: Arc<RpcClient>) -> Self {
+        self.rpc_client = Some(rpc_client);
+        self
+    }
+
+    // Replaces the HTTP client of the REST providers, e.g. with a middleware stack.
+    #[cfg(any(feature = "jupiter", feature = "dflow"))]
+    pub fn with_http_client(mut self, client: crate::http::HttpClient) -> Self {
+        #[cfg(feature = "jupiter")]
+        if let Some(p) = &mut self.jupiter {
+            p.client = client.clone();
+        }
+        #[cfg(feature = "dflow")]
+        if let Some(p) = &mut self.dflow {
+            p.client = client;
+        }
+        self
     }
 
     pub async fn quote(
         &self,
         provider: Provider,
         request: &QuoteRequest,
+    ) -> Result<QuoteResponse, SwapError> {
+        self.quote_with_context(provider, request, &QuoteContext::default())
+            .await
+    }
+
+    // Like `quote`, with per-call API keys taking precedence over the configured ones.
+    pub async fn quote_with_context(
+        &self,
+        provider: Provider,
+        request: &QuoteRequest,
+        context: &QuoteContext,
+    ) -> Result<QuoteResponse, SwapError> {
+        let quote = self.quote_unguarded(provider, request, context).await?;
+        self.check_guard(request, quote)
+    }
+
+    async fn quote_unguarded(
+        &self,
+        provider: Provider,
+        request: &QuoteRequest,
+        context: &QuoteContext,
+    ) -> Result<QuoteResponse, SwapError> {
+        let adjusted;
+        let request = if needs_transfer_fee(request) {
+            let rpc_client = self.rpc_client.as_deref().ok_or_else(|| {
+                SwapError::InvalidConfig(
+                    "input_is_fee_on_transfer needs an RPC client (with_rpc_client)".to_string(),
+                )
+            })?;
+            adjusted = self.apply_transfer_fee(request, rpc_client).await?;
+            &adjusted
+        } else {
+            request
+        };
+
+        let started = Instant::now();
+        let result = self.dispatch_quote(provider, request, context).await;
+        self.metrics
+            .record_quote(provider, result.is_ok(), started.elapsed());
+        if let Ok(quote) = &result {
+            debug!(swap_id = %quote.swap_id, %provider, output = quote.output_amount, "quote received");
+        }
+        result
+    }
+
+    async fn dispatch_quote(
+        &self,
+        provider: Provider,
+        request: &QuoteRequest,
+        _context: &QuoteContext,
     ) -> Result<QuoteResponse, SwapError> {
         match provider {
             Provider::Jupiter => {
@@ -78,7 +282,8 @@ impl SwapAggregator {
                         .jupiter
                         .as_ref()
                         .ok_or(SwapError::ProviderNotConfigured(Provider::Jupiter))?;
-                    p.quote(request, self.default_slippage_bps).await
+                    p.quote_with_context(request, self.default_slippage_bps, _context)
+                        .await
                 }
                 #[cfg(not(feature = "jupiter"))]
                 {
@@ -106,7 +311,8 @@ impl SwapAggregator {
                         .dflow
                         .as_ref()
                         .ok_or(SwapError::ProviderNotConfigured(Provider::Dflow))?;
-                    p.quote(request, self.default_slippage_bps).await
+                    p.quote_with_context(request, self.default_slippage_bps, _context)
+                        .await
                 }
                 #[cfg(not(feature = "dflow"))]
                 {
@@ -116,32 +322,411 @@ impl SwapAggregator {
         }
     }
 
-    pub async fn quote_all(&self, request: &QuoteRequest) -> Vec<Result<QuoteResponse, SwapError>> {
-        let mut futures: Vec<QuoteFuture<'_>> = Vec::new();
+    // Probes the pair with a tiny amount.
+    pub async fn has_route(
+        &self,
+        provider: Provider,
+        input: Pubkey,
+        output: Pubkey,
+    ) -> Result<bool, SwapError> {
+        match self
+            .probe_quote(provider, input, output, ROUTE_PROBE_AMOUNT)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(SwapError::NoRouteFound) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
 
-        #[cfg(feature = "jupiter")]
-        if let Some(ref p) = self.jupiter {
-            futures.push(Box::pin(p.quote(request, self.default_slippage_bps)));
+    // Finds the smallest and largest amounts up to `max_amount` that route, assuming the routable
+    // amounts form one range: doubles from 1 until an amount routes, then until one stops routing,
+    // and bisects each boundary. A `None` min means nothing routes up to `max_amount`; a `None`
+    // max means `max_amount` still routes. At most `MAX_BOUND_PROBES` quotes are sent; once they
+    // are spent, the tightest amounts known to route are returned. Rate-limited probes are retried
+    // after a backoff.
+    pub async fn amount_bounds(
+        &self,
+        provider: Provider,
+        input: Pubkey,
+        output: Pubkey,
+        max_amount: u64,
+    ) -> Result<(Option<u64>, Option<u64>), SwapError> {
+        let mut probe = RouteProbe {
+            aggregator: self,
+            provider,
+            input,
+            output,
+            remaining: MAX_BOUND_PROBES,
+        };
+        let mut below = 0;
+        let mut amount = 1.min(max_amount);
+        loop {
+            if amount == below {
+                return Ok((None, None));
+            }
+            match probe.routes(amount).await? {
+                Some(true) => break,
+                Some(false) => {}
+                None => return Ok((None, None)),
+            }
+            below = amount;
+            amount = amount.saturating_mul(2).min(max_amount);
         }
+        let min = probe.bisect(below, amount, false).await?;
 
-        #[cfg(feature = "titan")]
-        if let Some(ref p) = self.titan {
-            futures.push(Box::pin(p.quote(request, self.default_slippage_bps)));
+        let mut routed = amount;
+        while routed < max_amount {
+            let next = routed.saturating_mul(2).min(max_amount);
+            match probe.routes(next).await? {
+                Some(true) => routed = next,
+                Some(false) => {
+                    let max = probe.bisect(routed, next, true).await?;
+                    return Ok((Some(min), Some(max)));
+                }
+                None => return Ok((Some(min), Some(routed))),
+            }
         }
+        Ok((Some(min), None))
+    }
 
-        #[cfg(feature = "dflow")]
-        if let Some(ref p) = self.dflow {
-            futures.push(Box::pin(p.quote(request, self.default_slippage_bps)));
+    // The guard is skipped since it judges real sizes, not probes.
+    async fn probe_quote(
+        &self,
+        provider: Provider,
+        input: Pubkey,
+        output: Pubkey,
+        amount: u64,
+    ) -> Result<QuoteResponse, SwapError> {
+        let probe = QuoteRequest {
+            input_mint: input,
+            output_mint: output,
+            amount,
+            slippage_bps: None,
+            only_direct_routes: None,
+            taker: None,
+            restrict_intermediate_tokens: None,
+            as_legacy_transaction: None,
+            swap_mode: None,
+            dexes: None,
+            exclude_dexes: None,
+            input_is_fee_on_transfer: false,
+            fee_account: None,
+            platform_fee_bps: None,
+            input_token_program: None,
+            output_token_program: None,
+            provider_params: None,
+            reference_price: None,
+            providers: None,
+            preferred_intermediate_mints: None,
+        };
+        self.quote_unguarded(provider, &probe, &QuoteContext::default())
+            .await
+    }
+
+    // Native SOL pays rent and fees from the same balance being swapped, so a full
+    // balance swap would fail; leave `reserve_sol_for_fees` lamports behind.
+    pub fn swappable_amount(&self, input_mint: &Pubkey, amount: u64) -> u64 {
+        if *input_mint == NATIVE_MINT {
+            amount.saturating_sub(self.reserve_sol_for_fees)
+        } else {
+            amount
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn metrics_prometheus(&self) -> String {
+        self.metrics.render_prometheus()
+    }
+
+    pub fn is_configured(&self, provider: Provider) -> bool {
+        match provider {
+            Provider::Jupiter => {
+                #[cfg(feature = "jupiter")]
+                {
+                    self.jupiter.is_some()
+                }
+                #[cfg(not(feature = "jupiter"))]
+                {
+                    false
+                }
+            }
+            Provider::Titan => {
+                #[cfg(feature = "titan")]
+                {
+                    self.titan.is_some()
+                }
+                #[cfg(not(feature = "titan"))]
+                {
+                    false
+                }
+            }
+            Provider::Dflow => {
+                #[cfg(feature = "dflow")]
+                {
+                    self.dflow.is_some()
+                }
+                #[cfg(not(feature = "dflow"))]
+                {
+                    false
+                }
+            }
+        }
+    }
+
+    // Configured providers whose integration supports `capability`, in `Provider::ALL` order.
+    pub fn providers_supporting(&self, capability: Capability) -> Vec<Provider> {
+        Provider::ALL
+            .iter()
+            .copied()
+            .filter(|p| self.is_configured(*p) && p.capabilities().supports(capability))
+            .collect()
+    }
+
+    pub async fn quote_all(&self, request: &QuoteRequest) -> Vec<Result<QuoteResponse, SwapError>> {
+        futures::future::join_all(self.quote_futures(request)).await
+    }
+
+    // Like `quote_all`, but providers still pending at `deadline` are dropped and reported as
+    // `Timeout`, so a slow provider cannot hold up the others' results.
+    pub async fn quote_all_within(
+        &self,
+        request: &QuoteRequest,
+        deadline: Duration,
+    ) -> Vec<Result<QuoteResponse, SwapError>> {
+        all_within(self.quote_futures(request), deadline).await
+    }
+
+    // Results are in `requests` order; at most MAX_BATCH_CONCURRENCY quotes are in flight at once.
+    pub async fn quote_batch(
+        &self,
+        provider: Provider,
+        requests: &[QuoteRequest],
+    ) -> Vec<Result<QuoteResponse, SwapError>> {
+        futures::stream::iter(requests)
+            .map(|request| self.quote(provider, request))
+            .buffered(MAX_BATCH_CONCURRENCY)
+            .collect()
+            .await
+    }
+
+    // Configured providers, narrowed to the request's own allowlist when it has one.
+    fn providers_for<'a>(
+        &'a self,
+        request: &'a QuoteRequest,
+    ) -> impl Iterator<Item = Provider> + 'a {
+        Provider::ALL.iter().copied().filter(|p| {
+            self.is_configured(*p)
+                && request
+                    .providers
+                    .as_ref()
+                    .is_none_or(|allowed| allowed.contains(p))
+        })
+    }
+
+    fn quote_futures<'a>(&'a self, request: &'a QuoteRequest) -> Vec<QuoteFuture<'a>> {
+        self.providers_for(request)
+            .map(|p| {
+                Box::pin(async move {
+                    let quote = self
+                        .quote_unguarded(p, request, &QuoteContext::default())
+                        .await?;
+                    self.check_guard(request, quote)
+                }) as QuoteFuture<'a>
+            })
+            .collect()
+    }
+
+    // Re-quotes `quote`'s provider for the same trade, e.g. to keep a displayed quote fresh. The
+    // request is rebuilt from the quote, fees, DEX filters and taker included.
+    pub async fn refresh(&self, quote: &QuoteResponse) -> Result<QuoteResponse, SwapError> {
+        self.quote(quote.provider, &QuoteRequest::from(quote)).await
+    }
+
+    // Tries `preferred` in order, moving on when a provider has no route or fails transiently;
+    // any other error (bad request, malformed response) is returned immediately.
+    pub async fn quote_with_fallback(
+        &self,
+        preferred: &[Provider],
+        request: &QuoteRequest,
+    ) -> Result<QuoteResponse, SwapError> {
+        let mut last_error = None;
+        for &provider in preferred {
+            match self.quote(provider, request).await {
+                Ok(quote) => return Ok(quote),
+                Err(e) if should_fall_back(&e) => {
+                    debug!(%provider, "quote failed, falling back: {e}");
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.unwrap_or(SwapError::NoRouteFound))
+    }
+
+    // Returns the first quote meeting `target` and drops (cancels) the providers still in
+    // flight; if none qualifies, waits for all and returns the best by `is_better_than`. The
+    // target is an output, which every ExactOut quote already fixes, so ExactOut is rejected.
+    pub async fn first_acceptable(
+        &self,
+        request: &QuoteRequest,
+        target: AcceptableOutput,
+    ) -> Result<QuoteResponse, SwapError> {
+        if request.swap_mode == Some(SwapMode::ExactOut) {
+            return Err(SwapError::InvalidRequest(
+                "first_acceptable targets an output amount, which ExactOut quotes all share; \
+                 use best_quote"
+                    .to_string(),
+            ));
         }
+        first_acceptable_of(self.quote_futures(request), target.min_output()).await
+    }
+
+    pub async fn best_quote(&self, request: &QuoteRequest) -> Result<QuoteResponse, SwapError> {
+        rank_quotes(self.quote_all(request).await, self.rank_by_net_output)
+    }
+
+    // Runs `best_quote` every `interval` and yields each round's winner, whose `provider` names
+    // the current best. A failed round is yielded as an error and polling continues.
+    pub fn best_quote_stream(
+        &self,
+        request: QuoteRequest,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<QuoteResponse, SwapError>> + '_ {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        futures::stream::unfold((ticker, request), move |(mut ticker, request)| async move {
+            ticker.tick().await;
+            let best = self.best_quote(&request).await;
+            Some((best, (ticker, request)))
+        })
+    }
+
+    pub async fn best_quote_by(
+        &self,
+        request: &QuoteRequest,
+        score: impl Fn(&QuoteResponse) -> i128,
+    ) -> Result<QuoteResponse, SwapError> {
+        select_best(self.quote_all(request).await, score)
+    }
 
-        futures::future::join_all(futures).await
+    fn check_guard(
+        &self,
+        request: &QuoteRequest,
+        quote: QuoteResponse,
+    ) -> Result<QuoteResponse, SwapError> {
+        if let Some(guard) = &self.quote_guard {
+            guard.check(&quote)?;
+        }
+        if let Some(reference) = &request.reference_price {
+            reference.check(&quote)?;
+        }
+        Ok(quote)
     }
 
     pub async fn swap(
+        &self,
+        quote: &QuoteResponse,
+        user_pubkey: &Pubkey,
+        rpc_client: &RpcClient,
+    ) -> Result<SwapResult, SwapError> {
+        self.swap_at_slot(quote, user_pubkey, rpc_client, None)
+            .await
+    }
+
+    // Like `swap`, but RPC reads (Jupiter's lookup tables) fail with
+    // `MinContextSlotNotReached` on a node behind `min_context_slot`.
+    pub async fn swap_at_slot(
+        &self,
+        quote: &QuoteResponse,
+        user_pubkey: &Pubkey,
+        rpc_client: &RpcClient,
+        min_context_slot: Option<u64>,
+    ) -> Result<SwapResult, SwapError> {
+        let span =
+            tracing::info_span!("swap", swap_id = %quote.swap_id, provider = %quote.provider);
+        self.swap_cached(
+            quote,
+            user_pubkey,
+            rpc_client,
+            min_context_slot,
+            &QuoteContext::default(),
+        )
+        .instrument(span)
+        .await
+    }
+
+    // Like `swap`, with per-call API keys taking precedence over the configured ones.
+    pub async fn swap_with_context(
+        &self,
+        quote: &QuoteResponse,
+        user_pubkey: &Pubkey,
+        rpc_client: &RpcClient,
+        context: &QuoteContext,
+    ) -> Result<SwapResult, SwapError> {
+        let span =
+            tracing::info_span!("swap", swap_id = %quote.swap_id, provider = %quote.provider);
+        self.swap_cached(quote, user_pubkey, rpc_client, None, context)
+            .instrument(span)
+            .await
+    }
+
+    // Bounds the whole swap build (instruction fetch and every lookup-table RPC round trip) by
+    // `deadline`. On expiry the in-flight requests are dropped and `Timeout` is returned.
+    pub async fn swap_within(
+        &self,
+        quote: &QuoteResponse,
+        user_pubkey: &Pubkey,
+        rpc_client: &RpcClient,
+        deadline: Duration,
+    ) -> Result<SwapResult, SwapError> {
+        tokio::time::timeout(deadline, self.swap(quote, user_pubkey, rpc_client))
+            .await
+            .unwrap_or(Err(SwapError::Timeout))
+    }
+
+    async fn swap_cached(
+        &self,
+        quote: &QuoteResponse,
+        user_pubkey: &Pubkey,
+        rpc_client: &RpcClient,
+        min_context_slot: Option<u64>,
+        context: &QuoteContext,
+    ) -> Result<SwapResult, SwapError> {
+        if quote.is_noop() {
+            return Err(SwapError::InvalidRequest(format!(
+                "no-op swap: {} {} -> {} {}",
+                quote.input_amount, quote.input_mint, quote.output_amount, quote.output_mint
+            )));
+        }
+
+        if let Some(hit) = self
+            .swap_cache
+            .as_ref()
+            .and_then(|cache| cache.get(quote, user_pubkey))
+        {
+            debug!("swap cache hit for {} quote", quote.provider);
+            return Ok(hit.with_swap_id(quote.swap_id));
+        }
+
+        let result = self
+            .swap_uncached(quote, user_pubkey, rpc_client, min_context_slot, context)
+            .await?;
+
+        if let Some(cache) = &self.swap_cache {
+            cache.insert(quote, user_pubkey, &result);
+        }
+        Ok(result)
+    }
+
+    async fn swap_uncached(
         &self,
         quote: &QuoteResponse,
         user_pubkey: &Pubkey,
         _rpc_client: &RpcClient,
+        _min_context_slot: Option<u64>,
+        _context: &QuoteContext,
     ) -> Result<SwapResult, SwapError> {
         match quote.provider {
             Provider::Jupiter => {
@@ -151,7 +736,14 @@ impl SwapAggregator {
                         .jupiter
                         .as_ref()
                         .ok_or(SwapError::ProviderNotConfigured(Provider::Jupiter))?;
-                    p.swap(quote, user_pubkey, _rpc_client).await
+                    p.swap_with_context(
+                        quote,
+                        user_pubkey,
+                        _rpc_client,
+                        _min_context_slot,
+                        _context,
+                    )
+                    .await
                 }
                 #[cfg(not(feature = "jupiter"))]
                 {
@@ -179,7 +771,7 @@ impl SwapAggregator {
                         .dflow
                         .as_ref()
                         .ok_or(SwapError::ProviderNotConfigured(Provider::Dflow))?;
-                    p.swap(quote, user_pubkey).await
+                    p.swap_with_context(quote, user_pubkey, _context).await
                 }
                 #[cfg(not(feature = "dflow"))]
                 {
@@ -188,4 +780,1984 @@ impl SwapAggregator {
             }
         }
     }
+
+    pub async fn apply_transfer_fee(
+        &self,
+        request: &QuoteRequest,
+        rpc_client: &RpcClient,
+    ) -> Result<QuoteRequest, SwapError> {
+        let mut adjusted = request.clone();
+        if !needs_transfer_fee(request) {
+            return Ok(adjusted);
+        }
+
+        let mint = rpc_client
+            .get_account(&request.input_mint)
+            .await
+            .map_err(|e| SwapError::Solana(e.to_string()))?;
+        let epoch = rpc_client
+            .get_epoch_info()
+            .await
+            .map_err(|e| SwapError::Solana(e.to_string()))?
+            .epoch;
+
+        if let Some(fee) = transfer_fee(&mint.data, epoch) {
+            adjusted.amount = fee.amount_after_fee(request.amount);
+            debug!(
+                "transfer fee {} bps on {}: quoting {} instead of {}",
+                fee.basis_points, request.input_mint, adjusted.amount, request.amount
+            );
+        }
+        // the amount is already net of the fee; applying again would deduct it twice
+        adjusted.input_is_fee_on_transfer = false;
+        Ok(adjusted)
+    }
+
+    // Fills unset token programs from the mints' owners, so token-2022 mints are not assumed to
+    // be legacy SPL by providers that take the program. Explicitly set programs are kept.
+    pub async fn detect_token_programs(
+        &self,
+        request: &QuoteRequest,
+        rpc_client: &RpcClient,
+    ) -> Result<QuoteRequest, SwapError> {
+        let mut detected = request.clone();
+        if request.input_token_program.is_some() && request.output_token_program.is_some() {
+            return Ok(detected);
+        }
+
+        let accounts = rpc_client
+            .get_multiple_accounts(&[request.input_mint, request.output_mint])
+            .await
+            .map_err(|e| SwapError::Solana(e.to_string()))?;
+        let owner = |index: usize| {
+            accounts
+                .get(index)
+                .and_then(Option::as_ref)
+                .map(|account| account.owner)
+                .filter(is_token_program)
+        };
+        detected.input_token_program = request.input_token_program.or_else(|| owner(0));
+        detected.output_token_program = request.output_token_program.or_else(|| owner(1));
+        Ok(detected)
+    }
+
+    pub async fn swap_and_send(
+        &self,
+        quote: &QuoteResponse,
+        signer: &Keypair,
+        rpc_client: &RpcClient,
+    ) -> Result<Signature, SwapError> {
+        self.send_escalating(None, quote.clone(), signer, rpc_client)
+            .await
+    }
+
+    // Swaps and sends the best quote for `request`. With `slippage_escalation`, a slippage failure
+    // re-quotes the same provider from `request` with only the slippage raised, and tries again.
+    pub async fn best_swap(
+        &self,
+        request: &QuoteRequest,
+        signer: &Keypair,
+        rpc_client: &RpcClient,
+    ) -> Result<Signature, SwapError> {
+        let quote = self.best_quote(request).await?;
+        self.send_escalating(Some(request), quote, signer, rpc_client)
+            .await
+    }
+
+    async fn send_escalating(
+        &self,
+        request: Option<&QuoteRequest>,
+        mut quote: QuoteResponse,
+        signer: &Keypair,
+        rpc_client: &RpcClient,
+    ) -> Result<Signature, SwapError> {
+        loop {
+            let result = self.swap(&quote, &signer.pubkey(), rpc_client).await?;
+            let sent = send_with_blockhash_refresh(
+                &result,
+                signer,
+                rpc_client,
+                self.blockhash_refresh_retries,
+                None,
+                self.blockhash_cache.as_ref(),
+            )
+            .instrument(tracing::info_span!("send", swap_id = %quote.swap_id))
+            .await;
+
+            let escalation = match (&sent, request) {
+                (Err(e), Some(request)) if is_slippage_exceeded(quote.provider, e) => self
+                    .slippage_escalation
+                    .and_then(|escalation| escalation.next(quote.slippage_bps))
+                    .map(|slippage_bps| (request, slippage_bps)),
+                _ => None,
+            };
+            let Some((request, slippage_bps)) = escalation else {
+                return sent;
+            };
+            debug!(
+                swap_id = %quote.swap_id,
+                "slippage exceeded at {} bps, re-quoting at {slippage_bps} bps",
+                quote.slippage_bps
+            );
+            let request = QuoteRequest {
+                slippage_bps: Some(slippage_bps),
+                ..request.clone()
+            };
+            quote = self.quote(quote.provider, &request).await?;
+        }
+    }
+
+    // Quotes every configured provider (or the request's `providers`), builds each swap for `user`
+    // and simulates it; one provider's failure at any stage does not affect the others.
+    pub async fn quote_and_simulate_all(
+        &self,
+        request: &QuoteRequest,
+        user: &Pubkey,
+        rpc_client: &RpcClient,
+    ) -> Vec<(Provider, Result<(QuoteResponse, SimSummary), SwapError>)> {
+        let runs = self.providers_for(request).map(|provider| async move {
+            let outcome = async {
+                let quote = self.quote(provider, request).await?;
+                let result = self.swap(&quote, user, rpc_client).await?;
+                let summary = simulate(&result, &quote, user, rpc_client).await?;
+                Ok((quote, summary))
+            }
+            .await;
+            (provider, outcome)
+        });
+        futures::future::join_all(runs).await
+    }
+
+    // Closes Titan's WebSocket; call before dropping the aggregator. The REST providers hold
+    // nothing that needs closing.
+    pub async fn close(&self) -> Result<(), SwapError> {
+        #[cfg(feature = "titan")]
+        if let Some(p) = &self.titan {
+            p.close().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn preflight(
+        &self,
+        quote: &QuoteResponse,
+        user: &Pubkey,
+        rpc_client: &RpcClient,
+    ) -> Result<PreflightReport, SwapError> {
+        crate::preflight::preflight(quote, user, rpc_client).await
+    }
+}
+
+// Budgeted route probing for `amount_bounds`.
+struct RouteProbe<'a> {
+    aggregator: &'a SwapAggregator,
+    provider: Provider,
+    input: Pubkey,
+    output: Pubkey,
+    remaining: u32,
+}
+
+impl RouteProbe<'_> {
+    // `None` once the probe budget is spent. An amount the pools cannot fill does not route.
+    async fn routes(&mut self, amount: u64) -> Result<Option<bool>, SwapError> {
+        let Some(remaining) = self.remaining.checked_sub(1) else {
+            return Ok(None);
+        };
+        self.remaining = remaining;
+        let mut backoff = RATE_LIMIT_BACKOFF;
+        let mut retries = 0;
+        loop {
+            match self
+                .aggregator
+                .probe_quote(self.provider, self.input, self.output, amount)
+                .await
+            {
+                Ok(_) => return Ok(Some(true)),
+                Err(SwapError::NoRouteFound | SwapError::InsufficientLiquidity) => {
+                    return Ok(Some(false))
+                }
+                Err(SwapError::Api { status: 429, .. }) if retries < RATE_LIMIT_RETRIES => {
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    retries += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // Narrows `lo..=hi`, whose ends disagree on routing, and returns the end that routes; stops
+    // early, with that end as it stands, when the budget runs out.
+    async fn bisect(
+        &mut self,
+        mut lo: u64,
+        mut hi: u64,
+        lo_routes: bool,
+    ) -> Result<u64, SwapError> {
+        while hi - lo > 1 {
+            let mid = lo + (hi - lo) / 2;
+            let Some(routes) = self.routes(mid).await? else {
+                break;
+            };
+            if routes == lo_routes {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        Ok(if lo_routes { lo } else { hi })
+    }
+}
+
+async fn all_within(
+    futures: Vec<QuoteFuture<'_>>,
+    deadline: Duration,
+) -> Vec<Result<QuoteResponse, SwapError>> {
+    let until = tokio::time::Instant::now() + deadline;
+    futures::future::join_all(futures.into_iter().map(|future| async move {
+        tokio::time::timeout_at(until, future)
+            .await
+            .unwrap_or(Err(SwapError::Timeout))
+    }))
+    .await
+}
+
+async fn first_acceptable_of(
+    futures: Vec<QuoteFuture<'_>>,
+    min_output: u64,
+) -> Result<QuoteResponse, SwapError> {
+    let mut pending: FuturesUnordered<_> = futures.into_iter().collect();
+    let mut results = Vec::new();
+
+    while let Some(result) = pending.next().await {
+        if let Ok(quote) = &result {
+            if quote.output_amount >= min_output {
+                debug!(
+                    "{} quote {} meets target {min_output}, skipping {} pending",
+                    quote.provider,
+                    quote.output_amount,
+                    pending.len()
+                );
+                return result;
+            }
+        }
+        results.push(result);
+    }
+
+    select_best_by(results, QuoteResponse::is_better_than)
+}
+
+// The fee comes off what the pool receives, which only changes the quoted amount for ExactIn.
+fn needs_transfer_fee(request: &QuoteRequest) -> bool {
+    request.input_is_fee_on_transfer && request.swap_mode != Some(SwapMode::ExactOut)
+}
+
+fn should_fall_back(error: &SwapError) -> bool {
+    match error {
+        SwapError::NoRouteFound
+        | SwapError::InsufficientLiquidity
+        | SwapError::Timeout
+        | SwapError::Network(_)
+        | SwapError::ProviderNotConfigured(_)
+        | SwapError::UnsupportedSwapMode { .. }
+        | SwapError::PriceDeviationTooHigh { .. }
+        | SwapError::RouteTooLong { .. } => true,
+        // a rejected request (4xx) fails the same way at the next provider
+        SwapError::Api { status, .. } => *status >= 500 || *status == 429,
+        #[cfg(feature = "titan")]
+        SwapError::Titan(_) => true,
+        _ => false,
+    }
+}
+
+// `is_better_than` (highest output for ExactIn, lowest input for ExactOut), or with
+// `rank_by_net_output` the ExactIn output left after the provider-reported fee, so a provider
+// taking a larger platform fee does not win on its gross number. ExactOut quotes share their
+// output, so they are always ranked by input.
+fn rank_quotes(
+    results: Vec<Result<QuoteResponse, SwapError>>,
+    net_of_fees: bool,
+) -> Result<QuoteResponse, SwapError> {
+    select_best_by(results, |a, b| {
+        let exact_out = a.provider_data["swapMode"] == "ExactOut";
+        if net_of_fees && !exact_out {
+            a.net_output() > b.net_output()
+        } else {
+            a.is_better_than(b)
+        }
+    })
+}
+
+fn select_best(
+    results: Vec<Result<QuoteResponse, SwapError>>,
+    score: impl Fn(&QuoteResponse) -> i128,
+) -> Result<QuoteResponse, SwapError> {
+    select_best_by(results, |a, b| score(a) > score(b))
+}
+
+// Keeps the first quote no later one beats.
+fn select_best_by(
+    results: Vec<Result<QuoteResponse, SwapError>>,
+    better: impl Fn(&QuoteResponse, &QuoteResponse) -> bool,
+) -> Result<QuoteResponse, SwapError> {
+    let mut best: Option<QuoteResponse> = None;
+    let mut last_error = None;
+
+    for result in results {
+        match result {
+            Ok(quote) => {
+                if best.as_ref().is_none_or(|b| better(&quote, b)) {
+                    best = Some(quote);
+                }
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    best.ok_or_else(|| last_error.unwrap_or(SwapError::NoRouteFound))
+}
+
+fn validate_config(config: &SwapConfig) -> Result<(), SwapError> {
+    if config.default_slippage_bps > MAX_SLIPPAGE_BPS {
+        return Err(SwapError::InvalidConfig(format!(
+            "default_slippage_bps {} exceeds {MAX_SLIPPAGE_BPS}",
+            config.default_slippage_bps
+        )));
+    }
+    if config
+        .titan_token
+        .as_deref()
+        .is_some_and(|t| t.trim().is_empty())
+    {
+        return Err(SwapError::InvalidConfig("titan_token is empty".to_string()));
+    }
+    if let Some(escalation) = config.slippage_escalation {
+        if escalation.step_bps == 0 || escalation.max_bps > MAX_SLIPPAGE_BPS {
+            return Err(SwapError::InvalidConfig(format!(
+                "slippage_escalation needs step_bps > 0 and max_bps <= {MAX_SLIPPAGE_BPS}"
+            )));
+        }
+    }
+    if let Some(dir) = config.record_dir.as_deref().filter(|dir| !dir.is_dir()) {
+        return Err(SwapError::InvalidConfig(format!(
+            "record_dir {} is not a directory",
+            dir.display()
+        )));
+    }
+    Ok(())
+}
+
+fn validate_url(name: &str, url: &str, schemes: &[&str]) -> Result<(), SwapError> {
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|e| SwapError::InvalidConfig(format!("{name} {url:?}: {e}")))?;
+    if !schemes.contains(&parsed.scheme()) || parsed.host_str().is_none() {
+        return Err(SwapError::InvalidConfig(format!(
+            "{name} {url:?} must use one of: {}",
+            schemes.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "jupiter"))]
+#[expect(clippy::expect_used, reason = "test code")]
+pub(crate) mod tests {
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+    use uuid::Uuid;
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::types::QuoteMeta;
+
+    pub(crate) fn test_config() -> SwapConfig {
+        SwapConfig {
+            default_slippage_bps: 50,
+            jupiter_api_url: None,
+            jupiter_api_key: None,
+            jupiter_tier: None,
+            jupiter_use_token_ledger: None,
+            jupiter_dynamic_cu_limit: None,
+            titan_ws_url: None,
+            titan_token: None,
+            dflow_api_url: None,
+            dflow_api_key: None,
+            dflow_max_route_length: None,
+            dflow_priority_fee_lamports: None,
+            dflow_jito_tip_lamports: None,
+            quote_guard: None,
+            swap_cache_ttl: None,
+            reserve_sol_for_fees: None,
+            blockhash_refresh_retries: None,
+            blockhash_cache_ttl: None,
+            slippage_escalation: None,
+            record_dir: None,
+            rank_by_net_output: None,
+            max_response_bytes: None,
+            jupiter_request_signer: None,
+            dflow_request_signer: None,
+        }
+    }
+
+    fn jupiter_quote() -> QuoteResponse {
+        QuoteResponse {
+            provider: Provider::Jupiter,
+            input_mint: Pubkey::new_unique(),
+            output_mint: Pubkey::new_unique(),
+            input_amount: 1_000,
+            output_amount: 900,
+            price_impact_bps: None,
+            slippage_bps: 50,
+            slippage_adjusted: false,
+            provider_data: serde_json::json!({"inAmount": "1000", "outAmount": "900"}),
+            meta: QuoteMeta::default(),
+            swap_id: Uuid::new_v4(),
+        }
+    }
+
+    pub(crate) fn quote_request(amount: u64) -> QuoteRequest {
+        QuoteRequest {
+            input_mint: Pubkey::new_unique(),
+            output_mint: Pubkey::new_unique(),
+            amount,
+            slippage_bps: None,
+            only_direct_routes: None,
+            taker: None,
+            restrict_intermediate_tokens: None,
+            as_legacy_transaction: None,
+            swap_mode: None,
+            dexes: None,
+            exclude_dexes: None,
+            input_is_fee_on_transfer: false,
+            fee_account: None,
+            platform_fee_bps: None,
+            input_token_program: None,
+            output_token_program: None,
+            provider_params: None,
+            reference_price: None,
+            providers: None,
+            preferred_intermediate_mints: None,
+        }
+    }
+
+    fn quote_body(amount: u64) -> serde_json::Value {
+        serde_json::json!({
+            "inputMint": Pubkey::new_unique().to_string(),
+            "outputMint": Pubkey::new_unique().to_string(),
+            "inAmount": amount.to_string(),
+            "outAmount": (amount / 2).to_string(),
+            "slippageBps": 50,
+        })
+    }
+
+    fn swap_instructions_body() -> serde_json::Value {
+        serde_json::json!({
+            "swapInstruction": {
+                "programId": Pubkey::new_unique().to_string(),
+                "accounts": [],
+                "data": BASE64.encode([1, 2, 3]),
+            },
+            "computeUnitLimit": 200_000,
+        })
+    }
+
+    #[test]
+    fn try_new_explicit_url_wins_over_tier() {
+        let aggregator = SwapAggregator::try_new(SwapConfig {
+            jupiter_api_url: Some("https://self.hosted/".to_string()),
+            jupiter_tier: Some(crate::types::JupiterTier::Pro),
+            ..test_config()
+        })
+        .expect("valid config");
+
+        let jupiter = aggregator.jupiter.expect("jupiter configured");
+        assert_eq!(jupiter.base_url, "https://self.hosted/swap/v1");
+    }
+
+    #[test]
+    fn try_new_rejects_invalid_jupiter_url() {
+        let result = SwapAggregator::try_new(SwapConfig {
+            jupiter_api_url: Some("ftp://jup.example".to_string()),
+            ..test_config()
+        });
+
+        assert!(matches!(result, Err(SwapError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn try_new_rejects_excessive_slippage() {
+        let result = SwapAggregator::try_new(SwapConfig {
+            default_slippage_bps: 10_001,
+            ..test_config()
+        });
+
+        let Err(SwapError::InvalidConfig(message)) = result else {
+            unreachable!("expected InvalidConfig");
+        };
+        assert!(message.contains("default_slippage_bps"), "{message}");
+    }
+
+    #[test]
+    fn try_new_rejects_empty_titan_token() {
+        let result = SwapAggregator::try_new(SwapConfig {
+            titan_token: Some("  ".to_string()),
+            ..test_config()
+        });
+
+        let Err(SwapError::InvalidConfig(message)) = result else {
+            unreachable!("expected InvalidConfig");
+        };
+        assert!(message.contains("titan_token"), "{message}");
+    }
+
+    #[test]
+    fn try_new_rejects_malformed_urls() {
+        for config in [
+            SwapConfig {
+                jupiter_api_url: Some("not a url".to_string()),
+                ..test_config()
+            },
+            SwapConfig {
+                titan_ws_url: Some("https://titan.example".to_string()),
+                ..test_config()
+            },
+            SwapConfig {
+                dflow_api_url: Some("ws://dflow.example".to_string()),
+                ..test_config()
+            },
+        ] {
+            let result = SwapAggregator::try_new(config);
+            assert!(
+                matches!(result, Err(SwapError::InvalidConfig(_))),
+                "{:?}",
+                result.err()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn swap_short_circuits_equal_mint_quote() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/swap/v1/swap-instructions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(swap_instructions_body()))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(server.uri()),
+            ..test_config()
+        });
+        let mut quote = jupiter_quote();
+        quote.output_mint = quote.input_mint;
+        let rpc = RpcClient::new("http://127.0.0.1:1".to_string());
+
+        let result = aggregator.swap(&quote, &Pubkey::new_unique(), &rpc).await;
+
+        assert!(matches!(result, Err(SwapError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn select_best_applies_custom_score() {
+        let high_impact = QuoteResponse {
+            output_amount: 1_000,
+            price_impact_bps: Some(300),
+            ..jupiter_quote()
+        };
+        let low_impact = QuoteResponse {
+            provider: Provider::Dflow,
+            output_amount: 990,
+            price_impact_bps: Some(10),
+            ..jupiter_quote()
+        };
+        let results = || vec![Ok(high_impact.clone()), Ok(low_impact.clone())];
+
+        let by_output = select_best(results(), |q| i128::from(q.output_amount)).expect("best");
+        assert_eq!(by_output.provider, Provider::Jupiter);
+
+        let penalize_impact = |q: &QuoteResponse| {
+            i128::from(q.output_amount) - i128::from(q.price_impact_bps.unwrap_or(0)) / 10 * 2
+        };
+        let by_score = select_best(results(), penalize_impact).expect("best");
+        assert_eq!(by_score.provider, Provider::Dflow);
+        assert_eq!(by_score.output_amount, 990);
+    }
+
+    #[tokio::test]
+    async fn first_acceptable_returns_fast_quote_without_waiting() {
+        let fast = QuoteResponse {
+            output_amount: 990,
+            ..jupiter_quote()
+        };
+        let slow = QuoteResponse {
+            provider: Provider::Titan,
+            output_amount: 1_000,
+            ..jupiter_quote()
+        };
+        let futures: Vec<QuoteFuture<'_>> = vec![
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                Ok(slow)
+            }),
+            Box::pin(async move { Ok(fast) }),
+        ];
+
+        let started = std::time::Instant::now();
+        let target = AcceptableOutput::BpsOf {
+            reference: 1_000,
+            bps: 9_900,
+        };
+        let quote = first_acceptable_of(futures, target.min_output())
+            .await
+            .expect("acceptable quote");
+
+        assert_eq!(quote.provider, Provider::Jupiter);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn all_within_reports_slow_provider_as_timed_out() {
+        let slow = QuoteResponse {
+            provider: Provider::Titan,
+            ..jupiter_quote()
+        };
+        let futures: Vec<QuoteFuture<'_>> = vec![
+            Box::pin(async move { Ok(jupiter_quote()) }),
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                Ok(slow)
+            }),
+        ];
+
+        let started = std::time::Instant::now();
+        let results = all_within(futures, Duration::from_millis(50)).await;
+
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(results.len(), 2);
+        assert!(
+            matches!(&results[0], Ok(q) if q.provider == Provider::Jupiter),
+            "got {:?}",
+            results[0]
+        );
+        assert!(
+            matches!(results[1], Err(SwapError::Timeout)),
+            "got {:?}",
+            results[1]
+        );
+    }
+
+    #[tokio::test]
+    async fn first_acceptable_rejects_exact_out() {
+        let aggregator = SwapAggregator::new(test_config());
+        let request = QuoteRequest {
+            swap_mode: Some(SwapMode::ExactOut),
+            ..quote_request(1_000)
+        };
+
+        let err = aggregator
+            .first_acceptable(&request, AcceptableOutput::AtLeast(1_000))
+            .await
+            .expect_err("output target with ExactOut");
+
+        assert!(matches!(err, SwapError::InvalidRequest(_)), "got {err:?}");
+    }
+
+    #[tokio::test]
+    async fn first_acceptable_falls_back_to_best_when_none_qualifies() {
+        let base = jupiter_quote();
+        let futures: Vec<QuoteFuture<'_>> = [900, 950]
+            .into_iter()
+            .map(|output_amount| {
+                let quote = QuoteResponse {
+                    output_amount,
+                    ..base.clone()
+                };
+                Box::pin(async move { Ok(quote) }) as QuoteFuture<'_>
+            })
+            .collect();
+
+        let quote = first_acceptable_of(futures, AcceptableOutput::AtLeast(1_000).min_output())
+            .await
+            .expect("best quote");
+
+        assert_eq!(quote.output_amount, 950);
+    }
+
+    #[test]
+    fn net_output_score_prefers_lower_fee_quote() {
+        let high_fee = QuoteResponse {
+            provider: Provider::Dflow,
+            output_amount: 1_000,
+            provider_data: serde_json::json!({"platformFee": {"amount": "60", "feeBps": 60}}),
+            ..jupiter_quote()
+        };
+        // Jupiter's outAmount is already net of its platform fee
+        let low_fee = QuoteResponse {
+            output_amount: 980,
+            provider_data: serde_json::json!({"platformFee": {"amount": "10", "feeBps": 10}}),
+            ..jupiter_quote()
+        };
+        let results = || vec![Ok(high_fee.clone()), Ok(low_fee.clone())];
+
+        let gross = rank_quotes(results(), false).expect("best");
+        assert_eq!(gross.provider, Provider::Dflow);
+
+        let net = rank_quotes(results(), true).expect("best");
+        assert_eq!(net.provider, Provider::Jupiter);
+        assert_eq!(net.net_output(), 980);
+    }
+
+    #[test]
+    fn net_output_ranking_leaves_exact_out_ranked_by_input() {
+        let base = QuoteResponse {
+            provider_data: serde_json::json!({"swapMode": "ExactOut"}),
+            ..jupiter_quote()
+        };
+        let cheaper = QuoteResponse {
+            provider: Provider::Dflow,
+            input_amount: 990,
+            provider_data: serde_json::json!({
+                "swapMode": "ExactOut",
+                "platformFee": {"amount": "60", "feeBps": 60},
+            }),
+            ..base.clone()
+        };
+        let dearer = QuoteResponse {
+            input_amount: 1_000,
+            ..base
+        };
+
+        for results in [
+            vec![Ok(dearer.clone()), Ok(cheaper.clone())],
+            vec![Ok(cheaper.clone()), Ok(dearer.clone())],
+        ] {
+            let best = rank_quotes(results, true).expect("best");
+            assert_eq!(best.provider, Provider::Dflow);
+            assert_eq!(best.input_amount, 990);
+        }
+    }
+
+    #[test]
+    fn select_best_returns_last_error_without_quotes() {
+        let result = select_best(
+            vec![Err(SwapError::NoRouteFound), Err(SwapError::Timeout)],
+            |q| i128::from(q.output_amount),
+        );
+
+        assert!(matches!(result, Err(SwapError::Timeout)));
+    }
+
+    fn rpc_result(result: serde_json::Value) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": result,
+        }))
+    }
+
+    #[tokio::test]
+    async fn best_swap_escalates_slippage_from_the_original_request() {
+        use solana_sdk::{hash::Hash, transaction::VersionedTransaction};
+        use wiremock::{matchers::body_partial_json, Request};
+
+        let taker = Pubkey::new_unique();
+        let request = QuoteRequest {
+            slippage_bps: Some(100),
+            taker: Some(taker),
+            dexes: Some("Orca".into()),
+            ..quote_request(1_000)
+        };
+        let quote_body = |slippage_bps: u16, out_amount: &str| {
+            serde_json::json!({
+                "inputMint": request.input_mint.to_string(),
+                "outputMint": request.output_mint.to_string(),
+                "inAmount": "1000",
+                "outAmount": out_amount,
+                "slippageBps": slippage_bps,
+            })
+        };
+        let api = MockServer::start().await;
+        for (slippage_bps, out_amount) in [(100, "900"), (200, "880")] {
+            Mock::given(method("GET"))
+                .and(path("/swap/v1/quote"))
+                .and(query_param("slippageBps", slippage_bps.to_string()))
+                .and(query_param("dexes", "Orca"))
+                .and(query_param("taker", taker.to_string()))
+                .respond_with(
+                    ResponseTemplate::new(200).set_body_json(quote_body(slippage_bps, out_amount)),
+                )
+                .expect(1)
+                .mount(&api)
+                .await;
+        }
+        Mock::given(method("POST"))
+            .and(path("/swap/v1/swap-instructions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(swap_instructions_body()))
+            .expect(2)
+            .mount(&api)
+            .await;
+
+        let rpc_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"method": "getLatestBlockhash"}),
+            ))
+            .respond_with(rpc_result(serde_json::json!({
+                "context": {"slot": 1},
+                "value": {
+                    "blockhash": Hash::new_unique().to_string(),
+                    "lastValidBlockHeight": 100,
+                },
+            })))
+            .mount(&rpc_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({"method": "sendTransaction"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "error": {
+                    "code": -32002,
+                    "message": "Transaction simulation failed: Error processing Instruction 2: custom program error: 0x1771",
+                },
+            })))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&rpc_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"method": "sendTransaction"}),
+            ))
+            .respond_with(|request: &Request| {
+                let body: serde_json::Value =
+                    serde_json::from_slice(&request.body).expect("json-rpc body");
+                let encoded = body["params"][0].as_str().expect("encoded transaction");
+                let bytes = BASE64.decode(encoded).expect("base64 transaction");
+                let tx: VersionedTransaction = bincode::deserialize(&bytes).expect("transaction");
+                rpc_result(serde_json::json!(tx.signatures[0].to_string()))
+            })
+            .mount(&rpc_server)
+            .await;
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(api.uri()),
+            slippage_escalation: Some(SlippageEscalation {
+                step_bps: 100,
+                max_bps: 300,
+            }),
+            ..test_config()
+        });
+        let rpc = RpcClient::new(rpc_server.uri());
+
+        aggregator
+            .best_swap(&request, &Keypair::new(), &rpc)
+            .await
+            .expect("sent after escalation");
+
+        api.verify().await;
+        let swaps: Vec<serde_json::Value> = api
+            .received_requests()
+            .await
+            .expect("recorded requests")
+            .iter()
+            .filter(|r| r.url.path().ends_with("/swap-instructions"))
+            .map(|r| serde_json::from_slice(&r.body).expect("swap body"))
+            .collect();
+        assert_eq!(swaps.len(), 2);
+        assert_eq!(swaps[1]["quoteResponse"]["slippageBps"], 200);
+    }
+
+    #[tokio::test]
+    async fn swaps_within_blockhash_ttl_fetch_blockhash_once() {
+        use solana_sdk::{hash::Hash, transaction::VersionedTransaction};
+        use wiremock::{matchers::body_partial_json, Request};
+
+        // distinct instructions per swap, so the two transactions differ
+        let api = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/swap/v1/swap-instructions"))
+            .respond_with(|_: &Request| {
+                ResponseTemplate::new(200).set_body_json(swap_instructions_body())
+            })
+            .expect(2)
+            .mount(&api)
+            .await;
+
+        let rpc_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"method": "getLatestBlockhash"}),
+            ))
+            .respond_with(rpc_result(serde_json::json!({
+                "context": {"slot": 1},
+                "value": {
+                    "blockhash": Hash::new_unique().to_string(),
+                    "lastValidBlockHeight": 100,
+                },
+            })))
+            .expect(1)
+            .mount(&rpc_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"method": "sendTransaction"}),
+            ))
+            .respond_with(|request: &Request| {
+                let body: serde_json::Value =
+                    serde_json::from_slice(&request.body).expect("json-rpc body");
+                let encoded = body["params"][0].as_str().expect("encoded transaction");
+                let bytes = BASE64.decode(encoded).expect("base64 transaction");
+                let tx: VersionedTransaction = bincode::deserialize(&bytes).expect("transaction");
+                rpc_result(serde_json::json!(tx.signatures[0].to_string()))
+            })
+            .expect(2)
+            .mount(&rpc_server)
+            .await;
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(api.uri()),
+            blockhash_cache_ttl: Some(Duration::from_secs(2)),
+            ..test_config()
+        });
+        let rpc = RpcClient::new(rpc_server.uri());
+        let signer = Keypair::new();
+
+        for _ in 0..2 {
+            aggregator
+                .swap_and_send(&jupiter_quote(), &signer, &rpc)
+                .await
+                .expect("sent");
+        }
+
+        rpc_server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn identical_swap_within_blockhash_ttl_gets_a_fresh_blockhash() {
+        use solana_sdk::{hash::Hash, transaction::VersionedTransaction};
+        use std::sync::Mutex;
+        use wiremock::{matchers::body_partial_json, Request};
+
+        let api = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/swap/v1/swap-instructions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(swap_instructions_body()))
+            .expect(3)
+            .mount(&api)
+            .await;
+
+        // the first two fetches land in the same slot, the third after it
+        let first = Hash::new_unique();
+        let blockhashes = Mutex::new(vec![Hash::new_unique(), first, first]);
+        let rpc_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"method": "getLatestBlockhash"}),
+            ))
+            .respond_with(move |_: &Request| {
+                let blockhash = blockhashes
+                    .lock()
+                    .expect("blockhashes")
+                    .pop()
+                    .expect("a blockhash per fetch");
+                rpc_result(serde_json::json!({
+                    "context": {"slot": 1},
+                    "value": {"blockhash": blockhash.to_string(), "lastValidBlockHeight": 100},
+                }))
+            })
+            .expect(3)
+            .mount(&rpc_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"method": "sendTransaction"}),
+            ))
+            .respond_with(|request: &Request| {
+                let body: serde_json::Value =
+                    serde_json::from_slice(&request.body).expect("json-rpc body");
+                let encoded = body["params"][0].as_str().expect("encoded transaction");
+                let bytes = BASE64.decode(encoded).expect("base64 transaction");
+                let tx: VersionedTransaction = bincode::deserialize(&bytes).expect("transaction");
+                rpc_result(serde_json::json!(tx.signatures[0].to_string()))
+            })
+            .expect(2)
+            .mount(&rpc_server)
+            .await;
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(api.uri()),
+            blockhash_cache_ttl: Some(Duration::from_secs(30)),
+            ..test_config()
+        });
+        let rpc = RpcClient::new(rpc_server.uri());
+        let signer = Keypair::new();
+        let quote = jupiter_quote();
+
+        let sent = aggregator
+            .swap_and_send(&quote, &signer, &rpc)
+            .await
+            .expect("sent");
+        let err = aggregator
+            .swap_and_send(&quote, &signer, &rpc)
+            .await
+            .expect_err("the blockhash has not moved on");
+        assert!(matches!(err, SwapError::InvalidRequest(_)), "got {err:?}");
+        let resent = aggregator
+            .swap_and_send(&quote, &signer, &rpc)
+            .await
+            .expect("sent under a new blockhash");
+
+        assert_ne!(sent, resent);
+        rpc_server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn quote_and_simulate_all_reports_each_provider() {
+        use wiremock::matchers::body_partial_json;
+
+        // The swap is built from this quote, so its stored mints must match the request's.
+        let request = quote_request(1_000);
+        let mut body = quote_body(1_000);
+        body["inputMint"] = serde_json::json!(request.input_mint.to_string());
+        body["outputMint"] = serde_json::json!(request.output_mint.to_string());
+        let api = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .expect(1)
+            .mount(&api)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/swap/v1/swap-instructions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(swap_instructions_body()))
+            .expect(1)
+            .mount(&api)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/order"))
+            .respond_with(ResponseTemplate::new(400).set_body_string("No route found"))
+            .mount(&api)
+            .await;
+
+        let rpc_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"method": "getMultipleAccounts"}),
+            ))
+            .respond_with(rpc_result(
+                serde_json::json!({"context": {"slot": 1}, "value": [null]}),
+            ))
+            .mount(&rpc_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"method": "simulateTransaction"}),
+            ))
+            .respond_with(rpc_result(serde_json::json!({
+                "context": {"slot": 1},
+                "value": {"err": null, "logs": [], "unitsConsumed": 75_000},
+            })))
+            .expect(1)
+            .mount(&rpc_server)
+            .await;
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(api.uri()),
+            dflow_api_url: Some(api.uri()),
+            ..test_config()
+        });
+        let rpc = RpcClient::new(rpc_server.uri());
+
+        let results = aggregator
+            .quote_and_simulate_all(&request, &Pubkey::new_unique(), &rpc)
+            .await;
+
+        assert_eq!(
+            results.iter().map(|(p, _)| *p).collect::<Vec<_>>(),
+            Provider::ALL
+                .iter()
+                .copied()
+                .filter(|p| aggregator.is_configured(*p))
+                .collect::<Vec<_>>()
+        );
+        for (provider, outcome) in &results {
+            match provider {
+                Provider::Jupiter => {
+                    let (quote, summary) = outcome.as_ref().expect("jupiter simulated");
+                    assert_eq!(quote.provider, Provider::Jupiter);
+                    assert_eq!(summary.consumed_cu, Some(75_000));
+                }
+                _ => assert!(outcome.is_err(), "{provider} should fail"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn has_route_is_false_when_probe_finds_no_route() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .and(query_param("amount", ROUTE_PROBE_AMOUNT.to_string()))
+            .respond_with(ResponseTemplate::new(400).set_body_string("No route found"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(server.uri()),
+            ..test_config()
+        });
+
+        let reachable = aggregator
+            .has_route(
+                Provider::Jupiter,
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+            )
+            .await;
+        assert!(matches!(reachable, Ok(false)), "got {reachable:?}");
+    }
+
+    #[tokio::test]
+    async fn amount_bounds_discovers_pair_minimum_and_liquidity_cap() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .respond_with(|request: &wiremock::Request| {
+                let amount: u64 = request
+                    .url
+                    .query_pairs()
+                    .find(|(key, _)| key == "amount")
+                    .and_then(|(_, value)| value.parse().ok())
+                    .unwrap_or_default();
+                if (5_000..=1_000_000).contains(&amount) {
+                    ResponseTemplate::new(200).set_body_json(quote_body(amount))
+                } else {
+                    ResponseTemplate::new(400).set_body_string("No route found")
+                }
+            })
+            .mount(&server)
+            .await;
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(server.uri()),
+            ..test_config()
+        });
+
+        let bounds = aggregator
+            .amount_bounds(
+                Provider::Jupiter,
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                1 << 40,
+            )
+            .await
+            .expect("bounds");
+        assert_eq!(bounds, (Some(5_000), Some(1_000_000)));
+    }
+
+    // Routes every amount from `min` up, but answers the first request with a 429.
+    async fn routes_from(min: u64) -> MockServer {
+        let server = MockServer::start().await;
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .respond_with(move |request: &wiremock::Request| {
+                if calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 0 {
+                    return ResponseTemplate::new(429).set_body_string("rate limited");
+                }
+                let amount: u64 = request
+                    .url
+                    .query_pairs()
+                    .find(|(key, _)| key == "amount")
+                    .and_then(|(_, value)| value.parse().ok())
+                    .unwrap_or_default();
+                if amount >= min {
+                    ResponseTemplate::new(200).set_body_json(quote_body(amount))
+                } else {
+                    ResponseTemplate::new(400).set_body_string("No route found")
+                }
+            })
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn amount_bounds_stops_at_max_amount_after_a_rate_limit() {
+        let server = routes_from(5_000).await;
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(server.uri()),
+            ..test_config()
+        });
+
+        let bounds = aggregator
+            .amount_bounds(
+                Provider::Jupiter,
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                1_000_000,
+            )
+            .await
+            .expect("bounds");
+        assert_eq!(bounds, (Some(5_000), None));
+    }
+
+    #[tokio::test]
+    async fn amount_bounds_keeps_to_the_probe_budget() {
+        let min = 1 << 55;
+        let server = routes_from(min).await;
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(server.uri()),
+            ..test_config()
+        });
+
+        let (found, max) = aggregator
+            .amount_bounds(
+                Provider::Jupiter,
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                u64::MAX,
+            )
+            .await
+            .expect("bounds");
+
+        let requests = server.received_requests().await.expect("recorded").len();
+        // one extra request for the retried 429
+        assert!(
+            requests <= MAX_BOUND_PROBES as usize + 1,
+            "{requests} requests"
+        );
+        assert!(found.is_some_and(|amount| amount >= min), "got {found:?}");
+        // the budget ran out before the upper edge, so max is the largest amount seen routing
+        assert_eq!(max, Some(min));
+    }
+
+    #[tokio::test]
+    async fn per_call_api_key_overrides_configured_key() {
+        use wiremock::matchers::header;
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .and(header("x-api-key", "tenant-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(quote_body(1_000)))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .and(header("x-api-key", "config-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(quote_body(2_000)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(server.uri()),
+            jupiter_api_key: Some("config-key".to_string()),
+            ..test_config()
+        });
+        let context = QuoteContext {
+            jupiter_api_key: Some("tenant-key".to_string()),
+            ..QuoteContext::default()
+        };
+
+        let tenant = aggregator
+            .quote_with_context(Provider::Jupiter, &quote_request(1_000), &context)
+            .await
+            .expect("tenant quote");
+        let default = aggregator
+            .quote(Provider::Jupiter, &quote_request(2_000))
+            .await
+            .expect("config quote");
+
+        assert_eq!(tenant.input_amount, 1_000);
+        assert_eq!(default.input_amount, 2_000);
+    }
+
+    #[tokio::test]
+    async fn quote_nets_fee_on_transfer_input_of_the_transfer_fee() {
+        use crate::token::{tests::mint_with_transfer_fee, TransferFee, TOKEN_2022_PROGRAM};
+        use wiremock::matchers::body_partial_json;
+
+        let fee = TransferFee {
+            epoch: 0,
+            maximum_fee: u64::MAX,
+            basis_points: 100,
+        };
+        let mint = mint_with_transfer_fee(fee, fee);
+        let rpc_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"method": "getAccountInfo"}),
+            ))
+            .respond_with(rpc_result(serde_json::json!({
+                "context": {"slot": 1},
+                "value": {
+                    "data": [BASE64.encode(&mint), "base64"],
+                    "executable": false,
+                    "lamports": 1_000_000,
+                    "owner": TOKEN_2022_PROGRAM.to_string(),
+                    "rentEpoch": 0,
+                    "space": mint.len(),
+                },
+            })))
+            .mount(&rpc_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"method": "getEpochInfo"}),
+            ))
+            .respond_with(rpc_result(serde_json::json!({
+                "absoluteSlot": 1,
+                "blockHeight": 1,
+                "epoch": 5,
+                "slotIndex": 1,
+                "slotsInEpoch": 432_000,
+            })))
+            .mount(&rpc_server)
+            .await;
+        let api = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .and(query_param("amount", "990000"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(quote_body(990_000)))
+            .expect(1)
+            .mount(&api)
+            .await;
+
+        let request = QuoteRequest {
+            input_is_fee_on_transfer: true,
+            ..quote_request(1_000_000)
+        };
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(api.uri()),
+            ..test_config()
+        });
+        let err = aggregator
+            .quote(Provider::Jupiter, &request)
+            .await
+            .expect_err("the flag is not silently ignored");
+        assert!(matches!(err, SwapError::InvalidConfig(_)), "got {err:?}");
+
+        let aggregator = aggregator.with_rpc_client(Arc::new(RpcClient::new(rpc_server.uri())));
+        let quote = aggregator
+            .quote(Provider::Jupiter, &request)
+            .await
+            .expect("quote for the net amount");
+
+        assert_eq!(quote.input_amount, 990_000);
+    }
+
+    #[tokio::test]
+    async fn refresh_requotes_same_trade() {
+        let taker = Pubkey::new_unique();
+        let request = QuoteRequest {
+            taker: Some(taker),
+            dexes: Some("Orca".into()),
+            ..quote_request(1_000)
+        };
+        let body = |out_amount: &str| {
+            serde_json::json!({
+                "inputMint": request.input_mint.to_string(),
+                "outputMint": request.output_mint.to_string(),
+                "inAmount": "1000",
+                "outAmount": out_amount,
+                "slippageBps": 50,
+            })
+        };
+        let server = MockServer::start().await;
+        for out_amount in ["900", "910"] {
+            Mock::given(method("GET"))
+                .and(path("/swap/v1/quote"))
+                .and(query_param("inputMint", request.input_mint.to_string()))
+                .and(query_param("outputMint", request.output_mint.to_string()))
+                .and(query_param("amount", "1000"))
+                .and(query_param("slippageBps", "50"))
+                .and(query_param("taker", taker.to_string()))
+                .and(query_param("dexes", "Orca"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(body(out_amount)))
+                .up_to_n_times(1)
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(server.uri()),
+            ..test_config()
+        });
+        let quote = aggregator
+            .quote(Provider::Jupiter, &request)
+            .await
+            .expect("quote");
+        let refreshed = aggregator.refresh(&quote).await.expect("refreshed quote");
+
+        assert_eq!(refreshed.provider, quote.provider);
+        assert_eq!(refreshed.input_mint, quote.input_mint);
+        assert_eq!(refreshed.output_mint, quote.output_mint);
+        assert_eq!(refreshed.input_amount, quote.input_amount);
+        assert_eq!(quote.output_amount, 900);
+        assert_eq!(refreshed.output_amount, 910);
+        assert_ne!(refreshed.swap_id, quote.swap_id);
+    }
+
+    #[test]
+    fn providers_supporting_filters_configured_providers() {
+        let aggregator = SwapAggregator::new(test_config());
+
+        assert_eq!(
+            aggregator.providers_supporting(Capability::ExactOut),
+            vec![Provider::Jupiter, Provider::Titan]
+        );
+        assert!(!aggregator
+            .providers_supporting(Capability::PrebuiltTransaction)
+            .contains(&Provider::Titan));
+    }
+
+    #[tokio::test]
+    async fn http_client_middleware_observes_quote_requests() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        struct CountingMiddleware(Arc<AtomicUsize>);
+
+        #[async_trait::async_trait]
+        impl reqwest_middleware::Middleware for CountingMiddleware {
+            async fn handle(
+                &self,
+                req: reqwest::Request,
+                extensions: &mut http::Extensions,
+                next: reqwest_middleware::Next<'_>,
+            ) -> reqwest_middleware::Result<reqwest::Response> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                next.run(req, extensions).await
+            }
+        }
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(quote_body(1_000)))
+            .mount(&server)
+            .await;
+
+        let count = Arc::new(AtomicUsize::new(0));
+        let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+            .with(CountingMiddleware(Arc::clone(&count)))
+            .build();
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(server.uri()),
+            ..test_config()
+        })
+        .with_http_client(client);
+
+        aggregator
+            .quote(Provider::Jupiter, &quote_request(1_000))
+            .await
+            .expect("quote through middleware");
+
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn try_new_rejects_zero_slippage_escalation_step() {
+        let result = SwapAggregator::try_new(SwapConfig {
+            slippage_escalation: Some(SlippageEscalation {
+                step_bps: 0,
+                max_bps: 300,
+            }),
+            ..test_config()
+        });
+        assert!(matches!(result, Err(SwapError::InvalidConfig(_))));
+    }
+
+    #[cfg(feature = "dflow")]
+    #[tokio::test]
+    async fn request_providers_limit_which_providers_are_queried() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(quote_body(1_000)))
+            .expect(0)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/order"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(quote_body(1_000)))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(server.uri()),
+            dflow_api_url: Some(server.uri()),
+            ..test_config()
+        });
+        let request = QuoteRequest {
+            providers: Some(vec![Provider::Dflow]),
+            ..quote_request(1_000)
+        };
+
+        let results = aggregator.quote_all(&request).await;
+        assert_eq!(results.len(), 1);
+        let best = aggregator.best_quote(&request).await.expect("dflow quote");
+        assert_eq!(best.provider, Provider::Dflow);
+    }
+
+    #[cfg(feature = "dflow")]
+    #[tokio::test]
+    async fn quote_with_fallback_skips_provider_without_route() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .respond_with(ResponseTemplate::new(400).set_body_string("No route found"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/order"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(quote_body(1_000)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(server.uri()),
+            dflow_api_url: Some(server.uri()),
+            ..test_config()
+        });
+        let quote = aggregator
+            .quote_with_fallback(&[Provider::Jupiter, Provider::Dflow], &quote_request(1_000))
+            .await
+            .expect("dflow fallback quote");
+
+        assert_eq!(quote.provider, Provider::Dflow);
+        assert_eq!(quote.output_amount, 500);
+    }
+
+    #[cfg(feature = "dflow")]
+    #[tokio::test]
+    async fn best_quote_stream_follows_the_current_winner() {
+        let body = |out_amount: u64| {
+            let mut body = quote_body(1_000);
+            body["outAmount"] = serde_json::json!(out_amount.to_string());
+            body
+        };
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body(1_000)))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body(800)))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/order"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body(900)))
+            .mount(&server)
+            .await;
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(server.uri()),
+            dflow_api_url: Some(server.uri()),
+            ..test_config()
+        });
+        let winners: Vec<(Provider, u64)> = aggregator
+            .best_quote_stream(quote_request(1_000), Duration::from_millis(10))
+            .take(2)
+            .map(|best| {
+                let quote = best.expect("best quote");
+                (quote.provider, quote.output_amount)
+            })
+            .collect()
+            .await;
+
+        assert_eq!(
+            winners,
+            vec![(Provider::Jupiter, 1_000), (Provider::Dflow, 900)]
+        );
+    }
+
+    #[tokio::test]
+    async fn quote_with_fallback_stops_on_non_transient_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("not json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(server.uri()),
+            ..test_config()
+        });
+        let err = aggregator
+            .quote_with_fallback(&[Provider::Jupiter, Provider::Dflow], &quote_request(1_000))
+            .await
+            .expect_err("serialization error is not retried");
+
+        assert!(matches!(err, SwapError::Serialization(_)), "got {err:?}");
+    }
+
+    #[cfg(feature = "dflow")]
+    #[tokio::test]
+    async fn quote_with_fallback_does_not_fall_through_a_rejected_request() {
+        let jupiter = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .respond_with(ResponseTemplate::new(400).set_body_string("invalid amount"))
+            .expect(1)
+            .mount(&jupiter)
+            .await;
+        let dflow = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&dflow)
+            .await;
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(jupiter.uri()),
+            dflow_api_url: Some(dflow.uri()),
+            ..test_config()
+        });
+        let err = aggregator
+            .quote_with_fallback(&[Provider::Jupiter, Provider::Dflow], &quote_request(1_000))
+            .await
+            .expect_err("a 400 is returned, not retried elsewhere");
+
+        assert!(
+            matches!(err, SwapError::Api { status: 400, .. }),
+            "got {err:?}"
+        );
+        dflow.verify().await;
+    }
+
+    #[test]
+    fn only_server_errors_and_rate_limits_fall_back() {
+        let api = |status| SwapError::Api {
+            provider: Provider::Jupiter,
+            status,
+            body: serde_json::Value::Null,
+            message: String::new(),
+        };
+        assert!(should_fall_back(&api(503)));
+        assert!(should_fall_back(&api(429)));
+        assert!(!should_fall_back(&api(400)));
+        assert!(!should_fall_back(&api(401)));
+        assert!(!should_fall_back(&api(403)));
+        assert!(!should_fall_back(&SwapError::PriceImpactTooHigh {
+            impact_bps: 500,
+            max_bps: 100,
+        }));
+    }
+
+    #[tokio::test]
+    async fn quote_batch_preserves_order_and_isolates_errors() {
+        let server = MockServer::start().await;
+        for amount in [1_000_u64, 3_000] {
+            Mock::given(method("GET"))
+                .and(path("/swap/v1/quote"))
+                .and(query_param("amount", amount.to_string()))
+                .respond_with(ResponseTemplate::new(200).set_body_json(quote_body(amount)))
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .and(query_param("amount", "2000"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("upstream error"))
+            .mount(&server)
+            .await;
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(server.uri()),
+            ..test_config()
+        });
+        let requests = [
+            quote_request(1_000),
+            quote_request(2_000),
+            quote_request(3_000),
+        ];
+
+        let results = aggregator.quote_batch(Provider::Jupiter, &requests).await;
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().expect("first").output_amount, 500);
+        assert!(matches!(
+            results[1],
+            Err(SwapError::Api { status: 500, ref body, .. }) if body == "upstream error"
+        ));
+        assert_eq!(results[2].as_ref().expect("third").output_amount, 1_500);
+    }
+
+    #[tokio::test]
+    async fn quotes_are_recorded_in_metrics() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("upstream error"))
+            .mount(&server)
+            .await;
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(server.uri()),
+            ..test_config()
+        });
+        let request = quote_request(1_000);
+        for _ in 0..3 {
+            let result = aggregator.quote(Provider::Jupiter, &request).await;
+            assert!(result.is_err());
+        }
+
+        let jupiter = aggregator.metrics.provider(Provider::Jupiter);
+        assert_eq!((jupiter.quotes, jupiter.quote_errors), (3, 3));
+
+        #[cfg(feature = "metrics")]
+        {
+            let text = aggregator.metrics_prometheus();
+            assert!(text.contains("swap_quote_total{provider=\"jupiter\"} 3\n"));
+            assert!(text.contains("swap_quote_errors_total{provider=\"jupiter\"} 3\n"));
+        }
+    }
+
+    #[test]
+    fn validate_url_accepts_listed_schemes() {
+        assert!(validate_url("url", "https://api.jup.ag/swap/v1", &["http", "https"]).is_ok());
+        assert!(validate_url("url", "http://localhost:8080", &["http", "https"]).is_ok());
+        assert!(validate_url("url", "wss://api.titan.ag/ws", &["ws", "wss"]).is_ok());
+    }
+
+    #[test]
+    fn new_panics_on_invalid_config() {
+        let result = std::panic::catch_unwind(|| {
+            SwapAggregator::new(SwapConfig {
+                default_slippage_bps: u16::MAX,
+                ..test_config()
+            })
+        });
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn swappable_amount_reserves_only_for_native_sol() {
+        let aggregator = SwapAggregator::new(test_config());
+        let other_mint = Pubkey::new_unique();
+
+        assert_eq!(
+            aggregator.swappable_amount(&NATIVE_MINT, 10_000_000),
+            10_000_000 - DEFAULT_SOL_FEE_RESERVE_LAMPORTS
+        );
+        assert_eq!(
+            aggregator.swappable_amount(&other_mint, 10_000_000),
+            10_000_000
+        );
+        assert_eq!(aggregator.swappable_amount(&NATIVE_MINT, 1_000), 0);
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            reserve_sol_for_fees: Some(5_000),
+            ..test_config()
+        });
+        assert_eq!(aggregator.swappable_amount(&NATIVE_MINT, 10_000), 5_000);
+    }
+
+    #[tokio::test]
+    async fn quote_beyond_reference_price_is_rejected() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(quote_body(1_000)))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(server.uri()),
+            ..test_config()
+        });
+        let request = |price| QuoteRequest {
+            reference_price: Some(crate::types::ReferencePrice {
+                price,
+                max_deviation_bps: 500,
+            }),
+            ..quote_request(1_000)
+        };
+
+        let quote = aggregator
+            .quote(Provider::Jupiter, &request(0.51))
+            .await
+            .expect("within 500 bps");
+        assert_eq!(quote.output_amount, 500);
+
+        let err = aggregator
+            .quote(Provider::Jupiter, &request(0.6))
+            .await
+            .expect_err("~1667 bps below the reference");
+        assert!(
+            matches!(
+                err,
+                SwapError::PriceDeviationTooHigh { deviation_bps, max_bps: 500 }
+                    if deviation_bps < -1_600.0
+            ),
+            "got {err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn swap_within_times_out_on_slow_lookup_table_fetch() {
+        let server = MockServer::start().await;
+        let mut body = swap_instructions_body();
+        body["addressLookupTableAddresses"] = serde_json::json!([Pubkey::new_unique().to_string()]);
+        Mock::given(method("POST"))
+            .and(path("/swap/v1/swap-instructions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let rpc_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+            .expect(1)
+            .mount(&rpc_server)
+            .await;
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(server.uri()),
+            ..test_config()
+        });
+        let rpc = RpcClient::new(rpc_server.uri());
+        let started = std::time::Instant::now();
+
+        let result = aggregator
+            .swap_within(
+                &jupiter_quote(),
+                &Pubkey::new_unique(),
+                &rpc,
+                Duration::from_millis(200),
+            )
+            .await;
+
+        assert!(matches!(result, Err(SwapError::Timeout)), "got {result:?}");
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn swap_cache_reuses_instructions_within_ttl() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/swap/v1/swap-instructions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(swap_instructions_body()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(server.uri()),
+            swap_cache_ttl: Some(Duration::from_secs(30)),
+            ..test_config()
+        });
+        let rpc_client = RpcClient::new("http://127.0.0.1:1".to_string());
+        let quote = jupiter_quote();
+        let user = Pubkey::new_unique();
+
+        let first = aggregator
+            .swap(&quote, &user, &rpc_client)
+            .await
+            .expect("first swap should succeed");
+        let second = aggregator
+            .swap(&quote, &user, &rpc_client)
+            .await
+            .expect("second swap should hit the cache");
+
+        let (
+            SwapResult::Instructions {
+                instructions: a, ..
+            },
+            SwapResult::Instructions {
+                instructions: b, ..
+            },
+        ) = (first, second)
+        else {
+            unreachable!("jupiter swaps return instructions");
+        };
+        assert_eq!(a, b);
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn swap_result_carries_quote_swap_id() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/swap/v1/swap-instructions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(swap_instructions_body()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(server.uri()),
+            swap_cache_ttl: Some(Duration::from_secs(30)),
+            ..test_config()
+        });
+        let rpc_client = RpcClient::new("http://127.0.0.1:1".to_string());
+        let quote = jupiter_quote();
+        let user = Pubkey::new_unique();
+
+        let result = aggregator
+            .swap(&quote, &user, &rpc_client)
+            .await
+            .expect("swap should succeed");
+        assert_eq!(result.swap_id(), quote.swap_id);
+
+        // a cache hit for an identical re-quote reports the new quote's id
+        let requote = QuoteResponse {
+            swap_id: Uuid::new_v4(),
+            ..quote
+        };
+        let cached = aggregator
+            .swap(&requote, &user, &rpc_client)
+            .await
+            .expect("cached swap should succeed");
+        assert_eq!(cached.swap_id(), requote.swap_id);
+    }
+
+    #[tokio::test]
+    async fn swap_without_cache_hits_upstream_each_time() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/swap/v1/swap-instructions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(swap_instructions_body()))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(server.uri()),
+            ..test_config()
+        });
+        let rpc_client = RpcClient::new("http://127.0.0.1:1".to_string());
+        let quote = jupiter_quote();
+        let user = Pubkey::new_unique();
+
+        for _ in 0..2 {
+            aggregator
+                .swap(&quote, &user, &rpc_client)
+                .await
+                .expect("swap should succeed");
+        }
+        server.verify().await;
+    }
 }
@@ -0,0 +1,143 @@
+use std::{future::Future, sync::OnceLock};
+
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use tokio::runtime::{Builder, Handle, Runtime};
+
+use crate::{
+    aggregator::SwapAggregator,
+    error::SwapError,
+    types::{Provider, QuoteRequest, QuoteResponse, SwapResult},
+};
+
+// One runtime shared by every blocking call, created on first use. It outlives the calls so the
+// HTTP connection pools and Titan's WebSocket tasks it spawns stay usable between them.
+fn runtime() -> Result<&'static Runtime, SwapError> {
+    static RUNTIME: OnceLock<Result<Runtime, String>> = OnceLock::new();
+    RUNTIME
+        .get_or_init(|| {
+            Builder::new_multi_thread()
+                .worker_threads(1)
+                .thread_name("swap-routers-blocking")
+                .enable_all()
+                .build()
+                .map_err(|e| e.to_string())
+        })
+        .as_ref()
+        .map_err(|e| SwapError::InvalidConfig(format!("cannot start blocking runtime: {e}")))
+}
+
+// Blocking inside a runtime would panic (or starve its workers), so it is refused instead.
+fn block_on<F: Future>(future: F) -> Result<F::Output, SwapError> {
+    if Handle::try_current().is_ok() {
+        return Err(SwapError::InvalidRequest(
+            "blocking call made from within an async runtime; await the async method instead"
+                .to_string(),
+        ));
+    }
+    Ok(runtime()?.block_on(future))
+}
+
+impl SwapAggregator {
+    pub fn blocking_quote(
+        &self,
+        provider: Provider,
+        request: &QuoteRequest,
+    ) -> Result<QuoteResponse, SwapError> {
+        block_on(self.quote(provider, request))?
+    }
+
+    pub fn blocking_swap(
+        &self,
+        quote: &QuoteResponse,
+        user_pubkey: &Pubkey,
+        rpc_client: &RpcClient,
+    ) -> Result<SwapResult, SwapError> {
+        block_on(self.swap(quote, user_pubkey, rpc_client))?
+    }
+}
+
+#[cfg(all(test, feature = "jupiter"))]
+#[expect(clippy::expect_used, reason = "test code")]
+mod tests {
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{
+        aggregator::tests::{quote_request, test_config},
+        types::SwapConfig,
+    };
+
+    #[test]
+    fn blocking_quote_and_swap_run_without_a_caller_runtime() {
+        let request = quote_request(1_000);
+        // wiremock serves from its own thread; this runtime only drives the setup calls.
+        let setup = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("setup runtime");
+        let server = setup.block_on(async {
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .and(path("/swap/v1/quote"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "inputMint": request.input_mint.to_string(),
+                    "outputMint": request.output_mint.to_string(),
+                    "inAmount": "1000",
+                    "outAmount": "900",
+                    "slippageBps": 50,
+                })))
+                .expect(1)
+                .mount(&server)
+                .await;
+            Mock::given(method("POST"))
+                .and(path("/swap/v1/swap-instructions"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "swapInstruction": {
+                        "programId": Pubkey::new_unique().to_string(),
+                        "accounts": [],
+                        "data": BASE64.encode([1, 2, 3]),
+                    },
+                    "addressLookupTableAddresses": [],
+                })))
+                .expect(1)
+                .mount(&server)
+                .await;
+            server
+        });
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some(server.uri()),
+            ..test_config()
+        });
+        let rpc = RpcClient::new(server.uri());
+
+        let quote = aggregator
+            .blocking_quote(Provider::Jupiter, &request)
+            .expect("blocking quote");
+        assert_eq!(quote.output_amount, 900);
+        let result = aggregator
+            .blocking_swap(&quote, &Pubkey::new_unique(), &rpc)
+            .expect("blocking swap");
+        assert_eq!(result.swap_id(), quote.swap_id);
+
+        setup.block_on(server.verify());
+    }
+
+    #[tokio::test]
+    async fn blocking_call_inside_runtime_is_refused() {
+        let aggregator = SwapAggregator::new(SwapConfig {
+            jupiter_api_url: Some("http://127.0.0.1:1".to_string()),
+            ..test_config()
+        });
+
+        let err = aggregator
+            .blocking_quote(Provider::Jupiter, &quote_request(1_000))
+            .expect_err("inside a runtime");
+
+        assert!(matches!(err, SwapError::InvalidRequest(_)), "got {err:?}");
+    }
+}
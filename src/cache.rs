@@ -0,0 +1,137 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Signature};
+
+use crate::types::{Provider, QuoteResponse, SwapResult};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct SwapCacheKey {
+    provider: Provider,
+    user: Pubkey,
+    input_mint: Pubkey,
+    output_mint: Pubkey,
+    input_amount: u64,
+    provider_data: String,
+}
+
+impl SwapCacheKey {
+    fn new(quote: &QuoteResponse, user: &Pubkey) -> Self {
+        Self {
+            provider: quote.provider,
+            user: *user,
+            input_mint: quote.input_mint,
+            output_mint: quote.output_mint,
+            input_amount: quote.input_amount,
+            provider_data: quote.provider_data.to_string(),
+        }
+    }
+}
+
+// Only the Instructions variant is cached: it is not bound to a blockhash, so a
+// re-send only needs a fresh one. Prebuilt transactions are always rebuilt.
+pub struct SwapCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<SwapCacheKey, (Instant, SwapResult)>>,
+}
+
+impl SwapCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, quote: &QuoteResponse, user: &Pubkey) -> Option<SwapResult> {
+        let entries = self.entries.lock().ok()?;
+        let (inserted_at, result) = entries.get(&SwapCacheKey::new(quote, user))?;
+        (inserted_at.elapsed() < self.ttl).then(|| result.clone())
+    }
+
+    pub fn insert(&self, quote: &QuoteResponse, user: &Pubkey, result: &SwapResult) {
+        if !matches!(result, SwapResult::Instructions { .. }) {
+            return;
+        }
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.retain(|_, (inserted_at, _)| inserted_at.elapsed() < self.ttl);
+            entries.insert(
+                SwapCacheKey::new(quote, user),
+                (Instant::now(), result.clone()),
+            );
+        }
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+}
+
+// A blockhash stays valid for ~150 slots (about a minute), so reusing one for a few seconds
+// saves a round trip per send at little cost to its remaining validity. Two swaps with the same
+// instructions compile to the same transaction under one blockhash, and the cluster drops the
+// second as already processed, so the signatures sent under the cached blockhash are tracked.
+pub struct BlockhashCache {
+    ttl: Duration,
+    entry: Mutex<Option<CachedBlockhash>>,
+}
+
+struct CachedBlockhash {
+    fetched_at: Instant,
+    blockhash: Hash,
+    sent: HashSet<Signature>,
+}
+
+impl BlockhashCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entry: Mutex::new(None),
+        }
+    }
+
+    pub fn get(&self) -> Option<Hash> {
+        let entry = self.entry.lock().ok()?;
+        let cached = entry.as_ref()?;
+        (cached.fetched_at.elapsed() < self.ttl).then_some(cached.blockhash)
+    }
+
+    // Re-inserting the cached blockhash keeps the signatures already sent under it.
+    pub fn insert(&self, blockhash: Hash) {
+        if let Ok(mut entry) = self.entry.lock() {
+            match entry.as_mut() {
+                Some(cached) if cached.blockhash == blockhash => cached.fetched_at = Instant::now(),
+                _ => {
+                    *entry = Some(CachedBlockhash {
+                        fetched_at: Instant::now(),
+                        blockhash,
+                        sent: HashSet::new(),
+                    });
+                }
+            }
+        }
+    }
+
+    // Records a transaction signed with `blockhash`; false when the same signature was already
+    // recorded, i.e. the transaction is byte-identical to one sent before.
+    pub fn mark_sent(&self, blockhash: &Hash, signature: Signature) -> bool {
+        let Ok(mut entry) = self.entry.lock() else {
+            return true;
+        };
+        match entry.as_mut() {
+            Some(cached) if cached.blockhash == *blockhash => cached.sent.insert(signature),
+            _ => true,
+        }
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut entry) = self.entry.lock() {
+            *entry = None;
+        }
+    }
+}
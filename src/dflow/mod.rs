@@ -1,24 +1,37 @@
 pub mod types;
 
+use std::{path::PathBuf, sync::Arc, time::Instant};
+
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use solana_sdk::{pubkey::Pubkey, transaction::VersionedTransaction};
-use tracing::debug;
+use tracing::{debug, warn};
+use uuid::Uuid;
 
 use crate::{
     error::SwapError,
-    types::{Provider, QuoteRequest, QuoteResponse, SwapResult},
+    http::{self, HttpClient},
+    types::{
+        Provider, ProviderParams, QuoteContext, QuoteMeta, QuoteRequest, QuoteResponse,
+        RequestSigner, SwapResult,
+    },
 };
 
 use self::types::DflowOrderResponse;
 
 const DEFAULT_DFLOW_API_URL: &str = "https://dev-quote-api.dflow.net";
 const DFLOW_API_URL_ENV: &str = "DFLOW_API_URL";
+const MAX_ROUTE_LENGTH: u32 = 4;
 
 pub struct DflowProvider {
-    pub client: reqwest::Client,
+    pub client: HttpClient,
     pub base_url: String,
     pub api_key: Option<String>,
     pub max_route_length: Option<u32>,
+    pub priority_fee_lamports: Option<u64>,
+    pub jito_tip_lamports: Option<u64>,
+    pub record_dir: Option<PathBuf>,
+    pub max_response_bytes: Option<usize>,
+    pub request_signer: Option<Arc<dyn RequestSigner>>,
 }
 
 impl DflowProvider {
@@ -26,15 +39,37 @@ impl DflowProvider {
         base_url: Option<String>,
         api_key: Option<String>,
         max_route_length: Option<u32>,
-    ) -> Self {
-        Self {
-            client: reqwest::Client::new(),
+        priority_fee_lamports: Option<u64>,
+        jito_tip_lamports: Option<u64>,
+    ) -> Result<Self, SwapError> {
+        Ok(Self {
+            client: crate::http::build_client()?,
             base_url: base_url
                 .or_else(|| std::env::var(DFLOW_API_URL_ENV).ok())
                 .unwrap_or_else(|| DEFAULT_DFLOW_API_URL.to_string()),
             api_key,
-            max_route_length,
-        }
+            max_route_length: max_route_length.map(clamp_max_route_length),
+            priority_fee_lamports,
+            jito_tip_lamports,
+            record_dir: None,
+            max_response_bytes: None,
+            request_signer: None,
+        })
+    }
+
+    pub fn with_record_dir(mut self, record_dir: Option<PathBuf>) -> Self {
+        self.record_dir = record_dir;
+        self
+    }
+
+    pub fn with_max_response_bytes(mut self, max_response_bytes: Option<usize>) -> Self {
+        self.max_response_bytes = max_response_bytes;
+        self
+    }
+
+    pub fn with_request_signer(mut self, request_signer: Option<Arc<dyn RequestSigner>>) -> Self {
+        self.request_signer = request_signer;
+        self
     }
 
     pub async fn quote(
@@ -42,29 +77,60 @@ impl DflowProvider {
         request: &QuoteRequest,
         default_slippage_bps: u16,
     ) -> Result<QuoteResponse, SwapError> {
-        let response = self
-            .fetch_order(request, default_slippage_bps, None)
+        self.quote_with_context(request, default_slippage_bps, &QuoteContext::default())
+            .await
+    }
+
+    pub async fn quote_with_context(
+        &self,
+        request: &QuoteRequest,
+        default_slippage_bps: u16,
+        context: &QuoteContext,
+    ) -> Result<QuoteResponse, SwapError> {
+        Provider::Dflow.ensure_swap_mode(request.swap_mode)?;
+        let (response, meta) = self
+            .fetch_order(request, default_slippage_bps, None, context)
             .await?;
 
-        let in_amount: u64 = response
-            .in_amount
-            .parse()
-            .map_err(|e: std::num::ParseIntError| SwapError::Serialization(e.to_string()))?;
-        let out_amount: u64 = response
-            .out_amount
-            .parse()
-            .map_err(|e: std::num::ParseIntError| SwapError::Serialization(e.to_string()))?;
+        SwapError::required(response.input_mint.as_ref(), Provider::Dflow, "inputMint")?;
+        SwapError::required(response.output_mint.as_ref(), Provider::Dflow, "outputMint")?;
+        let in_amount =
+            SwapError::required_amount(response.in_amount.as_deref(), Provider::Dflow, "inAmount")?;
+        let out_amount = SwapError::required_amount(
+            response.out_amount.as_deref(),
+            Provider::Dflow,
+            "outAmount",
+        )?;
+        let slippage_bps =
+            SwapError::required(response.slippage_bps, Provider::Dflow, "slippageBps")?;
 
         let price_impact_bps = response
             .price_impact_pct
             .and_then(|pct| pct.parse::<f64>().ok().map(|p| (p * 100.0) as u16));
 
+        let requested_slippage_bps = request.slippage_bps.unwrap_or(default_slippage_bps);
+        let slippage_adjusted = slippage_bps != requested_slippage_bps;
+
         let provider_data = serde_json::json!({
             "inputMint": request.input_mint.to_string(),
             "outputMint": request.output_mint.to_string(),
             "amount": request.amount,
-            "slippageBps": request.slippage_bps.unwrap_or(default_slippage_bps),
+            "slippageBps": requested_slippage_bps,
             "onlyDirectRoutes": request.only_direct_routes,
+            "taker": request.taker.map(|pk| pk.to_string()),
+            "dexes": request.dexes,
+            "excludeDexes": request.exclude_dexes,
+            "routePlan": response.route_plan,
+            "platformFee": response.platform_fee,
+            "prioritizationFeeLamports": response
+                .prioritization_fee_lamports
+                .or(self.priority_fee_lamports),
+            "jitoTipLamports": self.jito_tip_lamports,
+            "platformFeeBps": request.platform_fee_bps,
+            "feeAccount": request.fee_account.map(|pk| pk.to_string()),
+            "inputTokenProgram": request.input_token_program.map(|pk| pk.to_string()),
+            "outputTokenProgram": request.output_token_program.map(|pk| pk.to_string()),
+            "providerParams": request.provider_params.as_ref().map(|p| &p.dflow),
         });
 
         Ok(QuoteResponse {
@@ -74,8 +140,11 @@ impl DflowProvider {
             input_amount: in_amount,
             output_amount: out_amount,
             price_impact_bps,
-            slippage_bps: response.slippage_bps,
+            slippage_bps,
+            slippage_adjusted,
             provider_data,
+            meta,
+            swap_id: Uuid::new_v4(),
         })
     }
 
@@ -84,6 +153,17 @@ impl DflowProvider {
         quote: &QuoteResponse,
         user_pubkey: &Pubkey,
     ) -> Result<SwapResult, SwapError> {
+        self.swap_with_context(quote, user_pubkey, &QuoteContext::default())
+            .await
+    }
+
+    pub async fn swap_with_context(
+        &self,
+        quote: &QuoteResponse,
+        user_pubkey: &Pubkey,
+        context: &QuoteContext,
+    ) -> Result<SwapResult, SwapError> {
+        quote.ensure_provider(Provider::Dflow, &["amount", "slippageBps"])?;
         let amount: u64 = quote.provider_data["amount"].as_u64().ok_or_else(|| {
             SwapError::Serialization("missing amount in provider_data".to_string())
         })?;
@@ -92,6 +172,26 @@ impl DflowProvider {
         })? as u16;
 
         let only_direct_routes = quote.provider_data["onlyDirectRoutes"].as_bool();
+        let platform_fee_bps = quote.provider_data["platformFeeBps"]
+            .as_u64()
+            .and_then(|bps| u16::try_from(bps).ok());
+        let pubkey_field = |key: &str| {
+            quote.provider_data[key]
+                .as_str()
+                .map(str::parse::<Pubkey>)
+                .transpose()
+                .map_err(|e| SwapError::Serialization(format!("invalid {key}: {e}")))
+        };
+        let fee_account = pubkey_field("feeAccount")?;
+        let input_token_program = pubkey_field("inputTokenProgram")?;
+        let output_token_program = pubkey_field("outputTokenProgram")?;
+        // The swap re-requests /order, so it repeats the quote's passthrough params.
+        let provider_params = quote.provider_data["providerParams"]
+            .as_object()
+            .map(|dflow| ProviderParams {
+                dflow: dflow.clone(),
+                ..ProviderParams::default()
+            });
 
         let request = QuoteRequest {
             input_mint: quote.input_mint,
@@ -105,14 +205,25 @@ impl DflowProvider {
             swap_mode: None,
             dexes: None,
             exclude_dexes: None,
+            input_is_fee_on_transfer: false,
+            fee_account,
+            platform_fee_bps,
+            input_token_program,
+            output_token_program,
+            provider_params,
+            reference_price: None,
+            providers: None,
+            preferred_intermediate_mints: None,
         };
 
-        let response = self
-            .fetch_order(&request, slippage_bps, Some(user_pubkey))
+        let (response, _) = self
+            .fetch_order(&request, slippage_bps, Some(user_pubkey), context)
             .await?;
 
         let tx_base64 = response.transaction.ok_or_else(|| SwapError::Api {
             provider: Provider::Dflow,
+            status: 200,
+            body: serde_json::Value::Null,
             message: "no transaction in order response".to_string(),
         })?;
 
@@ -128,6 +239,9 @@ impl DflowProvider {
         Ok(SwapResult::Transaction {
             transaction,
             last_valid_block_height,
+            compute_units: response.compute_unit_limit,
+            swap_id: quote.swap_id,
+            source_quote: Some(quote.provider_data.clone()),
         })
     }
 
@@ -136,9 +250,19 @@ impl DflowProvider {
         request: &QuoteRequest,
         default_slippage_bps: u16,
         user_pubkey: Option<&Pubkey>,
-    ) -> Result<DflowOrderResponse, SwapError> {
-        let url = format!("{}/order", self.base_url);
+        context: &QuoteContext,
+    ) -> Result<(DflowOrderResponse, QuoteMeta), SwapError> {
+        let query = self.order_query(request, default_slippage_bps, user_pubkey)?;
+        let api_key = context.dflow_api_key.as_deref().or(self.api_key.as_deref());
+        self.fetch_order_rest(&query, api_key).await
+    }
 
+    fn order_query(
+        &self,
+        request: &QuoteRequest,
+        default_slippage_bps: u16,
+        user_pubkey: Option<&Pubkey>,
+    ) -> Result<Vec<(String, String)>, SwapError> {
         let mut query: Vec<(&str, String)> = vec![
             ("inputMint", request.input_mint.to_string()),
             ("outputMint", request.output_mint.to_string()),
@@ -157,6 +281,11 @@ impl DflowProvider {
         }
 
         if let Some(max_legs) = self.max_route_length {
+            if max_legs == 0 {
+                return Err(SwapError::InvalidRequest(
+                    "dflow max_route_length must be at least 1".to_string(),
+                ));
+            }
             query.push(("maxRouteLength", max_legs.to_string()));
         }
 
@@ -166,29 +295,415 @@ impl DflowProvider {
             query.push(("onlyDirectRoutes", "false".to_string()));
         }
 
-        let mut req = self.client.get(&url).query(&query);
-        if let Some(key) = &self.api_key {
+        if let Some(lamports) = self.priority_fee_lamports {
+            query.push(("prioritizationFeeLamports", lamports.to_string()));
+        }
+
+        if let Some(lamports) = self.jito_tip_lamports {
+            query.push(("jitoTipLamports", lamports.to_string()));
+        }
+
+        if let Some(bps) = request.platform_fee_bps {
+            query.push(("platformFeeBps", bps.to_string()));
+        }
+
+        if let Some(account) = request.fee_account {
+            query.push(("feeAccount", account.to_string()));
+        }
+
+        if let Some(program) = request.input_token_program {
+            query.push(("inputTokenProgram", program.to_string()));
+        }
+
+        if let Some(program) = request.output_token_program {
+            query.push(("outputTokenProgram", program.to_string()));
+        }
+
+        let typed_keys: Vec<&str> = query.iter().map(|(key, _)| *key).collect();
+        let extra = request.provider_query(Provider::Dflow, &typed_keys);
+        Ok(query
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .chain(extra)
+            .collect())
+    }
+
+    async fn fetch_order_rest(
+        &self,
+        query: &[(String, String)],
+        api_key: Option<&str>,
+    ) -> Result<(DflowOrderResponse, QuoteMeta), SwapError> {
+        let url = format!("{}/order", self.base_url);
+        let mut req = self.client.get(&url).query(query);
+        if let Some(key) = api_key {
             req = req.header("x-api-key", key);
         }
 
         debug!("dflow order: {url}");
-        let response = req.send().await?;
+        let started = Instant::now();
+        let response = http::send(&self.client, req, self.request_signer.as_deref()).await?;
 
         if !response.status().is_success() {
             let status = response.status();
-            let body = response.text().await.unwrap_or_default();
+            let body =
+                http::read_error_text(response, Provider::Dflow, self.max_response_bytes).await;
             if body.contains("route_not_found") || body.contains("No route") {
                 return Err(SwapError::NoRouteFound);
             }
-            return Err(SwapError::Api {
-                provider: Provider::Dflow,
-                message: format!("HTTP {status}: {body}"),
-            });
+            return Err(SwapError::api(Provider::Dflow, status, &body));
+        }
+
+        let body = http::read_body(response, Provider::Dflow, self.max_response_bytes).await?;
+        let meta = QuoteMeta::measured(started, body.len());
+        let raw_json: serde_json::Value =
+            serde_json::from_slice(&body).map_err(|e| SwapError::Serialization(e.to_string()))?;
+        if let Some(dir) = &self.record_dir {
+            let request = query
+                .iter()
+                .map(|(k, v)| (k.clone(), serde_json::Value::String(v.clone())))
+                .collect();
+            crate::record::record(
+                dir,
+                Provider::Dflow,
+                "order",
+                serde_json::Value::Object(request),
+                &raw_json,
+            )
+            .await;
+        }
+        let order = serde_json::from_value::<DflowOrderResponse>(raw_json)
+            .map_err(|e| SwapError::Serialization(e.to_string()))?;
+        Ok((order, meta))
+    }
+}
+
+fn clamp_max_route_length(max_legs: u32) -> u32 {
+    if max_legs > MAX_ROUTE_LENGTH {
+        warn!("dflow max_route_length {max_legs} capped to {MAX_ROUTE_LENGTH}");
+        return MAX_ROUTE_LENGTH;
+    }
+    max_legs
+}
+
+#[cfg(test)]
+#[expect(clippy::expect_used, reason = "test code")]
+mod tests {
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::types::SwapMode;
+
+    fn make_request() -> QuoteRequest {
+        QuoteRequest {
+            input_mint: Pubkey::new_unique(),
+            output_mint: Pubkey::new_unique(),
+            amount: 1_000,
+            slippage_bps: Some(50),
+            only_direct_routes: None,
+            taker: None,
+            restrict_intermediate_tokens: None,
+            as_legacy_transaction: None,
+            swap_mode: None,
+            dexes: None,
+            exclude_dexes: None,
+            input_is_fee_on_transfer: false,
+            fee_account: None,
+            platform_fee_bps: None,
+            input_token_program: None,
+            output_token_program: None,
+            provider_params: None,
+            reference_price: None,
+            providers: None,
+            preferred_intermediate_mints: None,
+        }
+    }
+
+    fn order_body(request: &QuoteRequest) -> serde_json::Value {
+        serde_json::json!({
+            "inputMint": request.input_mint.to_string(),
+            "outputMint": request.output_mint.to_string(),
+            "inAmount": "1000",
+            "outAmount": "900",
+            "slippageBps": 50,
+        })
+    }
+
+    #[tokio::test]
+    async fn order_query_carries_priority_fee_and_jito_tip() {
+        let request = make_request();
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/order"))
+            .and(query_param("prioritizationFeeLamports", "5000"))
+            .and(query_param("jitoTipLamports", "10000"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(order_body(&request)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider =
+            DflowProvider::new(Some(server.uri()), None, None, Some(5_000), Some(10_000))
+                .expect("http client");
+        let quote = provider.quote(&request, 50).await.expect("quote");
+
+        assert_eq!(quote.provider_data["prioritizationFeeLamports"], 5_000);
+        assert_eq!(quote.provider_data["jitoTipLamports"], 10_000);
+    }
+
+    #[tokio::test]
+    async fn order_missing_amount_names_the_field() {
+        let request = make_request();
+        for field in ["inAmount", "outAmount"] {
+            let mut body = order_body(&request);
+            body.as_object_mut().expect("object").remove(field);
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .and(path("/order"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(body))
+                .mount(&server)
+                .await;
+
+            let provider = DflowProvider::new(Some(server.uri()), None, None, None, None)
+                .expect("http client");
+            let err = provider
+                .quote(&request, 50)
+                .await
+                .expect_err("missing amount");
+
+            let expected = format!("field `{field}` missing from Dflow response");
+            assert!(
+                matches!(err, SwapError::Serialization(ref m) if *m == expected),
+                "got {err:?}"
+            );
         }
+    }
+
+    #[tokio::test]
+    async fn unavailable_order_reports_http_status() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/order"))
+            .respond_with(ResponseTemplate::new(503).set_body_string("upstream unavailable"))
+            .mount(&server)
+            .await;
+
+        let provider =
+            DflowProvider::new(Some(server.uri()), None, None, None, None).expect("http client");
+        let err = provider.quote(&make_request(), 50).await.expect_err("503");
+
+        assert!(
+            matches!(
+                err,
+                SwapError::Api {
+                    provider: Provider::Dflow,
+                    status: 503,
+                    ..
+                }
+            ),
+            "got {err:?}"
+        );
+        assert_eq!(err.http_status(), Some(503));
+    }
+
+    #[tokio::test]
+    async fn exact_out_is_rejected_before_any_request() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/order"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let request = QuoteRequest {
+            swap_mode: Some(SwapMode::ExactOut),
+            ..make_request()
+        };
 
-        response
-            .json::<DflowOrderResponse>()
+        let provider =
+            DflowProvider::new(Some(server.uri()), None, None, None, None).expect("http client");
+        let err = provider
+            .quote(&request, 50)
             .await
-            .map_err(|e| SwapError::Serialization(e.to_string()))
+            .expect_err("ExactIn only");
+
+        assert!(
+            matches!(
+                err,
+                SwapError::UnsupportedSwapMode {
+                    provider: Provider::Dflow,
+                    mode: SwapMode::ExactOut
+                }
+            ),
+            "got {err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn zero_max_route_length_is_rejected() {
+        let provider = DflowProvider::new(
+            Some("http://127.0.0.1:1".to_string()),
+            None,
+            Some(0),
+            None,
+            None,
+        )
+        .expect("http client");
+
+        let err = provider
+            .quote(&make_request(), 50)
+            .await
+            .expect_err("zero hops is invalid");
+
+        assert!(matches!(err, SwapError::InvalidRequest(_)), "got {err:?}");
+    }
+
+    #[tokio::test]
+    async fn max_route_length_is_capped() {
+        let request = make_request();
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/order"))
+            .and(query_param("maxRouteLength", "4"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(order_body(&request)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = DflowProvider::new(Some(server.uri()), None, Some(1_000), None, None)
+            .expect("http client");
+        assert_eq!(provider.max_route_length, Some(MAX_ROUTE_LENGTH));
+
+        provider.quote(&request, 50).await.expect("quote");
+    }
+
+    #[tokio::test]
+    async fn order_query_carries_referral_fee() {
+        let fee_account = Pubkey::new_unique();
+        let request = QuoteRequest {
+            fee_account: Some(fee_account),
+            platform_fee_bps: Some(25),
+            ..make_request()
+        };
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/order"))
+            .and(query_param("platformFeeBps", "25"))
+            .and(query_param("feeAccount", fee_account.to_string()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(order_body(&request)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider =
+            DflowProvider::new(Some(server.uri()), None, None, None, None).expect("http client");
+        let quote = provider.quote(&request, 50).await.expect("quote");
+
+        assert_eq!(quote.provider_data["platformFeeBps"], 25);
+        assert_eq!(quote.provider_data["feeAccount"], fee_account.to_string());
+    }
+
+    #[tokio::test]
+    async fn order_query_carries_token_programs() {
+        let token_2022 = crate::token::TOKEN_2022_PROGRAM;
+        let request = QuoteRequest {
+            input_token_program: Some(token_2022),
+            output_token_program: Some(crate::token::TOKEN_PROGRAM),
+            provider_params: None,
+            reference_price: None,
+            providers: None,
+            preferred_intermediate_mints: None,
+            ..make_request()
+        };
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/order"))
+            .and(query_param("inputTokenProgram", token_2022.to_string()))
+            .and(query_param(
+                "outputTokenProgram",
+                crate::token::TOKEN_PROGRAM.to_string(),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(order_body(&request)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider =
+            DflowProvider::new(Some(server.uri()), None, None, None, None).expect("http client");
+        let quote = provider.quote(&request, 50).await.expect("quote");
+
+        assert_eq!(
+            quote.provider_data["inputTokenProgram"],
+            token_2022.to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn swap_exposes_transaction_blockhash_height_and_compute_units() {
+        let request = make_request();
+        let user = Pubkey::new_unique();
+        let blockhash = solana_sdk::hash::Hash::new_unique();
+        let message = solana_sdk::message::v0::Message::try_compile(&user, &[], &[], blockhash)
+            .expect("compile");
+        let transaction = VersionedTransaction {
+            signatures: vec![solana_sdk::signature::Signature::default()],
+            message: solana_sdk::message::VersionedMessage::V0(message),
+        };
+        let mut body = order_body(&request);
+        body["transaction"] =
+            serde_json::json!(BASE64.encode(bincode::serialize(&transaction).expect("encode")));
+        body["lastValidBlockHeight"] = serde_json::json!(123_456);
+        body["computeUnitLimit"] = serde_json::json!(310_000);
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/order"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&server)
+            .await;
+
+        let provider =
+            DflowProvider::new(Some(server.uri()), None, None, None, None).expect("http client");
+        let quote = provider.quote(&request, 50).await.expect("quote");
+        let result = provider.swap(&quote, &user).await.expect("swap");
+
+        assert_eq!(result.blockhash(), Some(blockhash));
+        assert_eq!(result.last_valid_block_height(), Some(123_456));
+        assert!(matches!(
+            result,
+            SwapResult::Transaction {
+                compute_units: Some(310_000),
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn swap_rejects_tampered_provider_data_mint() {
+        let request = make_request();
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/order"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(order_body(&request)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider =
+            DflowProvider::new(Some(server.uri()), None, None, None, None).expect("http client");
+        let mut quote = provider.quote(&request, 50).await.expect("quote");
+        quote.provider_data["outputMint"] = serde_json::json!(Pubkey::new_unique().to_string());
+
+        let err = provider
+            .swap(&quote, &Pubkey::new_unique())
+            .await
+            .expect_err("tampered output mint");
+
+        assert!(
+            matches!(err, SwapError::InconsistentResponse(ref m) if m.contains("outputMint")),
+            "got {err:?}"
+        );
+        server.verify().await;
     }
 }
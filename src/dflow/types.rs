@@ -3,11 +3,16 @@ use serde::Deserialize;
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DflowOrderResponse {
-    pub input_mint: String,
-    pub output_mint: String,
-    pub in_amount: String,
-    pub out_amount: String,
-    pub slippage_bps: u16,
+    #[serde(default)]
+    pub input_mint: Option<String>,
+    #[serde(default)]
+    pub output_mint: Option<String>,
+    #[serde(default)]
+    pub in_amount: Option<String>,
+    #[serde(default)]
+    pub out_amount: Option<String>,
+    #[serde(default)]
+    pub slippage_bps: Option<u16>,
     #[serde(default)]
     pub price_impact_pct: Option<String>,
     #[serde(default)]
@@ -16,4 +21,10 @@ pub struct DflowOrderResponse {
     pub last_valid_block_height: Option<u64>,
     #[serde(default)]
     pub compute_unit_limit: Option<u32>,
+    #[serde(default)]
+    pub route_plan: Option<serde_json::Value>,
+    #[serde(default)]
+    pub platform_fee: Option<serde_json::Value>,
+    #[serde(default)]
+    pub prioritization_fee_lamports: Option<u64>,
 }
@@ -0,0 +1,112 @@
+use std::fmt::Write;
+
+use crate::types::QuoteResponse;
+
+const HEADERS: [&str; 5] = ["provider", "output", "impact", "slippage", "min received"];
+
+// Plain-text provider comparison, best output first. Column widths follow the widest cell so
+// the table stays aligned for any amount size.
+pub fn render_comparison(quotes: &[QuoteResponse]) -> String {
+    let mut sorted: Vec<&QuoteResponse> = quotes.iter().collect();
+    sorted.sort_by_key(|quote| std::cmp::Reverse(quote.output_amount));
+
+    let rows: Vec<[String; 5]> = sorted
+        .iter()
+        .map(|quote| {
+            [
+                quote.provider.to_string(),
+                quote.output_amount.to_string(),
+                quote
+                    .price_impact_bps
+                    .map_or_else(|| "n/a".to_string(), |bps| format!("{bps} bps")),
+                format!("{} bps", quote.slippage_bps),
+                quote.min_received().to_string(),
+            ]
+        })
+        .collect();
+
+    let mut widths = HEADERS.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let mut table = String::new();
+    let mut push_row = |cells: [&str; 5]| {
+        let line = cells
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(i, (cell, width))| {
+                // The provider column reads left to right; amounts line up on the right.
+                if i == 0 {
+                    format!("{cell:<width$}")
+                } else {
+                    format!("{cell:>width$}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ");
+        let _ = writeln!(table, "{}", line.trim_end());
+    };
+    push_row(HEADERS);
+    for row in &rows {
+        push_row(row.each_ref().map(String::as_str));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::pubkey::Pubkey;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::types::{Provider, QuoteMeta};
+
+    fn quote(
+        provider: Provider,
+        output_amount: u64,
+        price_impact_bps: Option<u16>,
+    ) -> QuoteResponse {
+        QuoteResponse {
+            provider,
+            input_mint: Pubkey::new_unique(),
+            output_mint: Pubkey::new_unique(),
+            input_amount: 1_000_000,
+            output_amount,
+            price_impact_bps,
+            slippage_bps: 50,
+            slippage_adjusted: false,
+            provider_data: serde_json::json!({}),
+            meta: QuoteMeta::default(),
+            swap_id: Uuid::new_v4(),
+        }
+    }
+
+    #[test]
+    fn comparison_lists_each_provider_best_output_first() {
+        let table = render_comparison(&[
+            quote(Provider::Jupiter, 900_000, Some(3)),
+            quote(Provider::Titan, 1_250_000, None),
+            quote(Provider::Dflow, 1_000_000, Some(12)),
+        ]);
+
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("provider"), "{table}");
+        let order: Vec<&str> = lines[1..]
+            .iter()
+            .map(|line| line.split_whitespace().next().unwrap_or_default())
+            .collect();
+        assert_eq!(order, vec!["Titan", "Dflow", "Jupiter"]);
+        assert!(lines[1].contains("n/a"), "{table}");
+        assert!(lines[2].contains("12 bps"), "{table}");
+        assert!(lines[3].contains("895500"), "{table}");
+        assert!(
+            lines.iter().all(|line| line.len() == lines[0].len()),
+            "{table}"
+        );
+    }
+}
@@ -1,4 +1,6 @@
-use crate::types::Provider;
+use solana_client::client_error::ClientError;
+
+use crate::types::{Provider, SwapMode};
 
 #[derive(Debug, thiserror::Error)]
 pub enum SwapError {
@@ -11,11 +13,75 @@ pub enum SwapError {
     #[error("quote expired")]
     QuoteExpired,
 
+    #[error("timeout")]
+    Timeout,
+
+    #[error("price impact {impact_bps} bps exceeds maximum {max_bps} bps")]
+    PriceImpactTooHigh { impact_bps: u16, max_bps: u16 },
+
+    #[error("price deviates {deviation_bps:.1} bps from the reference, beyond {max_bps} bps")]
+    PriceDeviationTooHigh { deviation_bps: f64, max_bps: u16 },
+
+    #[error("simulation failed: {err}")]
+    SimulationFailed { logs: Vec<String>, err: String },
+
+    #[error("output {output} below minimum {min}")]
+    OutputBelowMinimum { output: u64, min: u64 },
+
+    #[error("simulated output {output_delta} below minimum received {min_received}")]
+    SimulatedOutputBelowMinimum {
+        output_delta: u64,
+        min_received: u64,
+    },
+
+    #[error("input {input} above maximum {max}")]
+    InputAboveMaximum { input: u64, max: u64 },
+
+    #[error("route has {hops} hops, above maximum {max}")]
+    RouteTooLong { hops: usize, max: usize },
+
+    #[error("{provider} does not support {mode:?} swaps")]
+    UnsupportedSwapMode { provider: Provider, mode: SwapMode },
+
     #[error("provider not configured: {0}")]
     ProviderNotConfigured(Provider),
 
+    #[error("invalid config: {0}")]
+    InvalidConfig(String),
+
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[error("invalid keypair: {0}")]
+    InvalidKeypair(String),
+
+    #[error(
+        "too many accounts: {account_count} unique accounts exceed the v0 message limit; \
+         lower Jupiter maxAccounts or supply address lookup tables"
+    )]
+    TooManyAccounts { account_count: usize },
+
+    #[error("transaction too large: {bytes} bytes exceeds the {limit} byte packet limit")]
+    TransactionTooLarge { bytes: usize, limit: usize },
+
+    #[error("conflicting {instruction} compute budget instructions: {values:?}")]
+    ConflictingComputeBudget {
+        instruction: String,
+        values: Vec<u64>,
+    },
+
+    #[error("inconsistent response: {0}")]
+    InconsistentResponse(String),
+
+    // `body` is the provider's error payload as JSON (a JSON string when it was not JSON),
+    // so callers can branch on the provider's own error codes.
     #[error("{provider} API error: {message}")]
-    Api { provider: Provider, message: String },
+    Api {
+        provider: Provider,
+        status: u16,
+        body: serde_json::Value,
+        message: String,
+    },
 
     #[error("network error: {0}")]
     Network(#[from] reqwest::Error),
@@ -23,10 +89,136 @@ pub enum SwapError {
     #[error("solana error: {0}")]
     Solana(String),
 
+    #[error("RPC node has not reached minimum context slot {min_context_slot}")]
+    MinContextSlotNotReached { min_context_slot: u64 },
+
     #[error("serialization error: {0}")]
     Serialization(String),
 
     #[cfg(feature = "titan")]
     #[error("titan error: {0}")]
     Titan(String),
+
+    #[error("middleware error: {0}")]
+    Middleware(String),
+}
+
+impl From<reqwest_middleware::Error> for SwapError {
+    fn from(error: reqwest_middleware::Error) -> Self {
+        match error {
+            reqwest_middleware::Error::Reqwest(e) => Self::Network(e),
+            reqwest_middleware::Error::Middleware(e) => Self::Middleware(e.to_string()),
+        }
+    }
+}
+
+impl SwapError {
+    #[cfg(any(feature = "jupiter", feature = "dflow"))]
+    pub(crate) fn api(provider: Provider, status: reqwest::StatusCode, body: &str) -> Self {
+        Self::Api {
+            provider,
+            status: status.as_u16(),
+            body: serde_json::from_str(body)
+                .unwrap_or_else(|_| serde_json::Value::String(body.to_string())),
+            message: format!("HTTP {status}: {body}"),
+        }
+    }
+
+    // Provider responses deserialize their core fields leniently, so a field the API stopped
+    // sending is reported by name instead of as serde's line/column error.
+    #[cfg(any(feature = "jupiter", feature = "dflow"))]
+    pub(crate) fn required<T>(
+        value: Option<T>,
+        provider: Provider,
+        field: &str,
+    ) -> Result<T, Self> {
+        value.ok_or_else(|| {
+            Self::Serialization(format!("field `{field}` missing from {provider} response"))
+        })
+    }
+
+    #[cfg(any(feature = "jupiter", feature = "dflow"))]
+    pub(crate) fn required_amount(
+        value: Option<&str>,
+        provider: Provider,
+        field: &str,
+    ) -> Result<u64, Self> {
+        Self::required(value, provider, field)?
+            .parse()
+            .map_err(|e| {
+                Self::Serialization(format!("field `{field}` in {provider} response: {e}"))
+            })
+    }
+
+    // The HTTP status behind the error, for branching on 4xx vs 5xx: a provider's error
+    // response, or a transport error that got as far as a response.
+    pub fn http_status(&self) -> Option<u16> {
+        match self {
+            Self::Api { status, .. } => Some(*status),
+            Self::Network(e) => e.status().map(|s| s.as_u16()),
+            _ => None,
+        }
+    }
+
+    // `get_account_with_config` flattens RPC errors into text, so match on the
+    // server message rather than the -32016 code.
+    pub(crate) fn from_rpc(err: &ClientError, min_context_slot: Option<u64>) -> Self {
+        match min_context_slot {
+            Some(slot)
+                if err
+                    .to_string()
+                    .contains("Minimum context slot has not been reached") =>
+            {
+                Self::MinContextSlotNotReached {
+                    min_context_slot: slot,
+                }
+            }
+            _ => Self::Solana(err.to_string()),
+        }
+    }
+}
+
+#[cfg(all(test, any(feature = "jupiter", feature = "dflow")))]
+#[expect(clippy::expect_used, reason = "test code")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_error_keeps_structured_body_and_status() {
+        let err = SwapError::api(
+            Provider::Jupiter,
+            reqwest::StatusCode::BAD_REQUEST,
+            r#"{"error":"Slippage too low","errorCode":"SLIPPAGE_TOO_LOW"}"#,
+        );
+
+        let SwapError::Api {
+            provider,
+            status,
+            body,
+            message,
+        } = err
+        else {
+            unreachable!("expected Api, got {err:?}");
+        };
+        assert_eq!(provider, Provider::Jupiter);
+        assert_eq!(status, 400);
+        assert_eq!(body["errorCode"], "SLIPPAGE_TOO_LOW");
+        assert!(message.starts_with("HTTP 400 Bad Request"), "{message}");
+    }
+
+    #[test]
+    fn api_error_wraps_plain_text_body() {
+        let err = SwapError::api(
+            Provider::Dflow,
+            reqwest::StatusCode::BAD_GATEWAY,
+            "upstream error",
+        );
+
+        let SwapError::Api { status, body, .. } = err else {
+            unreachable!("expected Api, got {err:?}");
+        };
+        assert_eq!(status, 502);
+        assert_eq!(body, serde_json::json!("upstream error"));
+        let _ = serde_json::to_string(&body).expect("serializable");
+    }
 }
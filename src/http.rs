@@ -0,0 +1,134 @@
+use reqwest::header::HeaderValue;
+
+use crate::{
+    error::SwapError,
+    types::{Provider, RequestSigner},
+};
+
+// rustls wins when both TLS features are enabled so `--features native-tls` on
+// top of the defaults stays buildable.
+pub(crate) fn client_builder() -> reqwest::ClientBuilder {
+    let builder = reqwest::Client::builder();
+    #[cfg(feature = "rustls-tls")]
+    let builder = builder.use_rustls_tls();
+    #[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+    let builder = builder.use_native_tls();
+    builder
+}
+
+// Providers send through a `ClientWithMiddleware`, so callers can layer their own
+// retry/tracing/caching stack; the default stack is empty.
+pub type HttpClient = reqwest_middleware::ClientWithMiddleware;
+
+pub(crate) type RequestBuilder = reqwest_middleware::RequestBuilder;
+
+// Fails when the TLS backend cannot be initialised.
+pub(crate) fn build_client() -> Result<HttpClient, SwapError> {
+    let client = client_builder().build()?;
+    Ok(reqwest_middleware::ClientBuilder::new(client).build())
+}
+
+// The request is built first so the signer sees exactly what goes out: the method, the path
+// with its query string, and the body bytes.
+pub(crate) async fn send(
+    client: &HttpClient,
+    builder: RequestBuilder,
+    signer: Option<&dyn RequestSigner>,
+) -> Result<reqwest::Response, SwapError> {
+    let mut request = builder.build()?;
+    if let Some(signer) = signer {
+        let url = request.url();
+        let path = match url.query() {
+            Some(query) => format!("{}?{query}", url.path()),
+            None => url.path().to_string(),
+        };
+        let body = request
+            .body()
+            .and_then(reqwest::Body::as_bytes)
+            .unwrap_or_default();
+        for (name, value) in signer.sign(request.method(), &path, body) {
+            let value = HeaderValue::from_str(&value).map_err(|e| {
+                SwapError::InvalidRequest(format!("invalid {name} header from signer: {e}"))
+            })?;
+            request.headers_mut().insert(name, value);
+        }
+    }
+    Ok(client.execute(request).await?)
+}
+
+// Buffers a response body, failing once it grows past `max_bytes` rather than holding whatever
+// a misbehaving provider sends. A declared Content-Length over the cap fails before any read.
+pub(crate) async fn read_body(
+    mut response: reqwest::Response,
+    provider: Provider,
+    max_bytes: Option<usize>,
+) -> Result<Vec<u8>, SwapError> {
+    let Some(max_bytes) = max_bytes else {
+        return Ok(response.bytes().await?.to_vec());
+    };
+    let too_large = |status: reqwest::StatusCode| SwapError::Api {
+        provider,
+        status: status.as_u16(),
+        body: serde_json::Value::Null,
+        message: format!("HTTP {status}: response body exceeds {max_bytes} bytes"),
+    };
+    let status = response.status();
+    if response
+        .content_length()
+        .is_some_and(|len| len > max_bytes as u64)
+    {
+        return Err(too_large(status));
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > max_bytes {
+            return Err(too_large(status));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+// For error responses, where the body only feeds the error message: an unreadable or oversized
+// body leaves it empty.
+pub(crate) async fn read_error_text(
+    response: reqwest::Response,
+    provider: Provider,
+    max_bytes: Option<usize>,
+) -> String {
+    read_body(response, provider, max_bytes)
+        .await
+        .map(|body| String::from_utf8_lossy(&body).into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+#[expect(clippy::expect_used, reason = "test code")]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "rustls-tls")]
+    #[test]
+    fn rustls_client_builds() {
+        reqwest::Client::builder()
+            .use_rustls_tls()
+            .build()
+            .expect("rustls client should build");
+    }
+
+    #[cfg(feature = "native-tls")]
+    #[test]
+    fn native_tls_client_builds() {
+        reqwest::Client::builder()
+            .use_native_tls()
+            .build()
+            .expect("native-tls client should build");
+    }
+
+    #[test]
+    fn default_client_builds() {
+        client_builder()
+            .build()
+            .expect("client should build with the selected TLS backend");
+    }
+}
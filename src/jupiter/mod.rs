@@ -1,45 +1,138 @@
 pub mod types;
 
-use std::str::FromStr;
+use std::{
+    collections::HashMap,
+    hash::BuildHasher,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
-use solana_address_lookup_table_interface::state::AddressLookupTable;
-use solana_client::nonblocking::rpc_client::RpcClient;
+use futures::Stream;
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcAccountInfoConfig};
 use solana_sdk::{
-    address_lookup_table::AddressLookupTableAccount,
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
 };
+use tokio::sync::OnceCell;
 use tracing::debug;
+use uuid::Uuid;
 
 use crate::{
     error::SwapError,
-    types::{Provider, QuoteRequest, QuoteResponse, SwapMode, SwapResult},
+    http::{self, HttpClient},
+    lookup::fetch_address_lookup_tables,
+    token,
+    types::{
+        ComputeBudget, InstructionPhases, JupiterTier, Provider, QuoteContext, QuoteMeta,
+        QuoteRequest, QuoteResponse, RequestSigner, RouteHop, SwapMode, SwapResult,
+    },
 };
 
 use self::types::{
     JupiterInstruction, JupiterQuoteApiResponse, JupiterQuoteParams,
-    JupiterSwapInstructionsResponse, JupiterSwapRequest,
+    JupiterSwapInstructionsResponse, JupiterSwapRequest, PriceUpdate, QuoteConstraint,
 };
 
-const DEFAULT_JUPITER_API_URL: &str = "https://lite-api.jup.ag/swap/v1";
 const JUPITER_API_URL_ENV: &str = "JUPITER_API_URL";
+const JUPITER_VERSION_PATH: &str = "/swap/v1";
+// Fields of Jupiter's /quote response that /swap-instructions reads back from `quoteResponse`.
+const QUOTE_RESPONSE_FIELDS: [&str; 8] = [
+    "inputMint",
+    "outputMint",
+    "inAmount",
+    "outAmount",
+    "otherAmountThreshold",
+    "swapMode",
+    "slippageBps",
+    "routePlan",
+];
 
 pub struct JupiterProvider {
-    pub client: reqwest::Client,
+    pub client: HttpClient,
     pub base_url: String,
     pub api_key: Option<String>,
+    pub use_token_ledger: Option<bool>,
+    pub dynamic_compute_unit_limit: bool,
+    pub record_dir: Option<PathBuf>,
+    pub max_response_bytes: Option<usize>,
+    pub request_signer: Option<Arc<dyn RequestSigner>>,
+    program_labels: OnceCell<HashMap<String, String>>,
 }
 
 impl JupiterProvider {
-    pub fn new(base_url: Option<String>, api_key: Option<String>) -> Self {
-        Self {
-            client: reqwest::Client::new(),
-            base_url: base_url
-                .or_else(|| std::env::var(JUPITER_API_URL_ENV).ok())
-                .unwrap_or_else(|| DEFAULT_JUPITER_API_URL.to_string()),
+    pub fn new(
+        base_url: Option<String>,
+        api_key: Option<String>,
+        tier: Option<JupiterTier>,
+        use_token_ledger: Option<bool>,
+        dynamic_compute_unit_limit: Option<bool>,
+    ) -> Result<Self, SwapError> {
+        Ok(Self {
+            client: crate::http::build_client()?,
+            base_url: resolve_base_url(
+                base_url,
+                std::env::var(JUPITER_API_URL_ENV).ok(),
+                tier.as_ref(),
+            ),
             api_key,
+            use_token_ledger,
+            dynamic_compute_unit_limit: dynamic_compute_unit_limit.unwrap_or(true),
+            record_dir: None,
+            max_response_bytes: None,
+            request_signer: None,
+            program_labels: OnceCell::new(),
+        })
+    }
+
+    pub fn with_record_dir(mut self, record_dir: Option<PathBuf>) -> Self {
+        self.record_dir = record_dir;
+        self
+    }
+
+    pub fn with_max_response_bytes(mut self, max_response_bytes: Option<usize>) -> Self {
+        self.max_response_bytes = max_response_bytes;
+        self
+    }
+
+    pub fn with_request_signer(mut self, request_signer: Option<Arc<dyn RequestSigner>>) -> Self {
+        self.request_signer = request_signer;
+        self
+    }
+
+    // Rebuilds the quote from a `record_dir` dump of a /quote call, so a production payload can
+    // be replayed in a test. `meta` and `swap_id` are fresh, as for a live quote.
+    pub fn quote_from_recording(path: &Path) -> Result<QuoteResponse, SwapError> {
+        let recording = crate::record::load(path)?;
+        if recording.provider != Provider::Jupiter.to_string() || recording.endpoint != "quote" {
+            return Err(SwapError::InvalidRequest(format!(
+                "{} is a {} {} recording, not a Jupiter quote",
+                path.display(),
+                recording.provider,
+                recording.endpoint
+            )));
         }
+        let mint = |key: &str| {
+            recording.request[key]
+                .as_str()
+                .and_then(|s| Pubkey::from_str(s).ok())
+                .ok_or_else(|| SwapError::Serialization(format!("recording missing {key}")))
+        };
+        let slippage_bps = recording.request["slippageBps"]
+            .as_u64()
+            .and_then(|bps| u16::try_from(bps).ok())
+            .ok_or_else(|| SwapError::Serialization("recording missing slippageBps".to_string()))?;
+
+        build_quote(
+            mint("inputMint")?,
+            mint("outputMint")?,
+            slippage_bps,
+            recording.response,
+            QuoteMeta::default(),
+        )
     }
 
     pub async fn quote(
@@ -47,14 +140,78 @@ impl JupiterProvider {
         request: &QuoteRequest,
         default_slippage_bps: u16,
     ) -> Result<QuoteResponse, SwapError> {
+        self.quote_with_context(request, default_slippage_bps, &QuoteContext::default())
+            .await
+    }
+
+    pub async fn quote_with_context(
+        &self,
+        request: &QuoteRequest,
+        default_slippage_bps: u16,
+        context: &QuoteContext,
+    ) -> Result<QuoteResponse, SwapError> {
+        self.quote_constrained(
+            request,
+            default_slippage_bps,
+            &QuoteConstraint::default(),
+            context.jupiter_api_key.as_deref(),
+        )
+        .await
+    }
+
+    // Quotes every variant concurrently and keeps the ones that succeed; when none does, the last
+    // error is returned rather than an empty list.
+    pub async fn quote_variants(
+        &self,
+        request: &QuoteRequest,
+        default_slippage_bps: u16,
+        variants: &[QuoteConstraint],
+    ) -> Result<Vec<QuoteResponse>, SwapError> {
+        let results = futures::future::join_all(
+            variants
+                .iter()
+                .map(|c| self.quote_constrained(request, default_slippage_bps, c, None)),
+        )
+        .await;
+
+        let mut quotes = Vec::new();
+        let mut last_error = None;
+        for (result, constraint) in results.into_iter().zip(variants) {
+            match result {
+                Ok(quote) => quotes.push(quote),
+                Err(e) => {
+                    debug!("jupiter quote variant {constraint:?} failed: {e}");
+                    last_error = Some(e);
+                }
+            }
+        }
+        match last_error {
+            Some(e) if quotes.is_empty() => Err(e),
+            _ => Ok(quotes),
+        }
+    }
+
+    async fn quote_constrained(
+        &self,
+        request: &QuoteRequest,
+        default_slippage_bps: u16,
+        constraint: &QuoteConstraint,
+        api_key: Option<&str>,
+    ) -> Result<QuoteResponse, SwapError> {
+        Provider::Jupiter.ensure_swap_mode(request.swap_mode)?;
         let params = JupiterQuoteParams {
             input_mint: request.input_mint.to_string(),
             output_mint: request.output_mint.to_string(),
             amount: request.amount,
             slippage_bps: request.slippage_bps.unwrap_or(default_slippage_bps),
-            only_direct_routes: request.only_direct_routes,
+            only_direct_routes: constraint.only_direct_routes.or(request.only_direct_routes),
             taker: request.taker.map(|p| p.to_string()),
-            restrict_intermediate_tokens: request.restrict_intermediate_tokens,
+            // Jupiter takes no mint list, only a switch to its curated intermediate set, so a
+            // stated preference turns that on unless the caller set it explicitly.
+            restrict_intermediate_tokens: constraint
+                .restrict_intermediate_tokens
+                .or(request.restrict_intermediate_tokens)
+                .or(request.preferred_intermediate_mints.as_ref().map(|_| true)),
             as_legacy_transaction: request.as_legacy_transaction,
             swap_mode: request.swap_mode.map(|m| match m {
                 SwapMode::ExactIn => "ExactIn".to_string(),
@@ -62,113 +219,216 @@ impl JupiterProvider {
             }),
             dexes: request.dexes.clone(),
             exclude_dexes: request.exclude_dexes.clone(),
+            max_accounts: constraint.max_accounts,
         };
 
+        let typed =
+            serde_json::to_value(&params).map_err(|e| SwapError::Serialization(e.to_string()))?;
+        let typed_keys: Vec<&str> = typed
+            .as_object()
+            .map(|fields| fields.keys().map(String::as_str).collect())
+            .unwrap_or_default();
+        let extra = request.provider_query(Provider::Jupiter, &typed_keys);
+
         let url = format!("{}/quote", self.base_url);
-        let mut req = self.client.get(&url).query(&params);
-        if let Some(key) = &self.api_key {
+        let mut req = self.client.get(&url).query(&params).query(&extra);
+        if let Some(key) = api_key.or(self.api_key.as_deref()) {
             req = req.header("x-api-key", key);
         }
 
         debug!("jupiter quote: {url}");
-        let response = req.send().await?;
+        let started = Instant::now();
+        let response = http::send(&self.client, req, self.request_signer.as_deref()).await?;
 
         if !response.status().is_success() {
             let status = response.status();
-            let body = response.text().await.unwrap_or_default();
+            let body =
+                http::read_error_text(response, Provider::Jupiter, self.max_response_bytes).await;
             if body.contains("No route found") || body.contains("could not find any route") {
                 return Err(SwapError::NoRouteFound);
             }
-            return Err(SwapError::Api {
-                provider: Provider::Jupiter,
-                message: format!("HTTP {status}: {body}"),
-            });
+            return Err(SwapError::api(Provider::Jupiter, status, &body));
         }
 
-        let raw_json: serde_json::Value = response.json().await?;
-        let api_response: JupiterQuoteApiResponse = serde_json::from_value(raw_json.clone())
-            .map_err(|e| SwapError::Serialization(e.to_string()))?;
+        let body = http::read_body(response, Provider::Jupiter, self.max_response_bytes).await?;
+        let meta = QuoteMeta::measured(started, body.len());
+        let raw_json: serde_json::Value =
+            serde_json::from_slice(&body).map_err(|e| SwapError::Serialization(e.to_string()))?;
+        if let Some(dir) = &self.record_dir {
+            let request = serde_json::to_value(&params).unwrap_or_default();
+            crate::record::record(dir, Provider::Jupiter, "quote", request, &raw_json).await;
+        }
 
-        let in_amount: u64 = api_response
-            .in_amount
-            .parse()
-            .map_err(|e: std::num::ParseIntError| SwapError::Serialization(e.to_string()))?;
-        let out_amount: u64 = api_response
-            .out_amount
-            .parse()
-            .map_err(|e: std::num::ParseIntError| SwapError::Serialization(e.to_string()))?;
+        let mut quote = build_quote(
+            request.input_mint,
+            request.output_mint,
+            params.slippage_bps,
+            raw_json,
+            meta,
+        )?;
+        record_request_hints(&mut quote.provider_data, request);
+        Ok(quote)
+    }
 
-        let price_impact_bps = api_response
-            .price_impact_pct
-            .and_then(|pct| pct.parse::<f64>().ok().map(|p| (p * 100.0) as u16));
+    // Jupiter has no public price WebSocket, so this polls the quote endpoint every
+    // `interval`. Failed polls are yielded as errors and the stream keeps going.
+    pub fn price_stream(
+        &self,
+        request: QuoteRequest,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<PriceUpdate, SwapError>> + '_ {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
 
-        Ok(QuoteResponse {
-            provider: Provider::Jupiter,
-            input_mint: request.input_mint,
-            output_mint: request.output_mint,
-            input_amount: in_amount,
-            output_amount: out_amount,
-            price_impact_bps,
-            slippage_bps: api_response.slippage_bps,
-            provider_data: raw_json,
+        futures::stream::unfold((ticker, request), move |(mut ticker, request)| async move {
+            ticker.tick().await;
+            // Slippage does not change the quoted amounts, only the threshold.
+            let update = self
+                .quote(&request, request.slippage_bps.unwrap_or(0))
+                .await
+                .map(|quote| PriceUpdate {
+                    input_mint: quote.input_mint,
+                    output_mint: quote.output_mint,
+                    in_amount: quote.input_amount,
+                    out_amount: quote.output_amount,
+                });
+            Some((update, (ticker, request)))
         })
     }
 
+    // Fetched once per provider; the map changes only when Jupiter adds a DEX.
+    pub async fn program_labels(&self) -> Result<&HashMap<String, String>, SwapError> {
+        self.program_labels
+            .get_or_try_init(|| async {
+                let url = format!("{}/program-id-to-label", self.base_url);
+                let mut req = self.client.get(&url);
+                if let Some(key) = &self.api_key {
+                    req = req.header("x-api-key", key);
+                }
+
+                debug!("jupiter program labels: {url}");
+                let response =
+                    http::send(&self.client, req, self.request_signer.as_deref()).await?;
+                if !response.status().is_success() {
+                    let status = response.status();
+                    let body =
+                        http::read_error_text(response, Provider::Jupiter, self.max_response_bytes)
+                            .await;
+                    return Err(SwapError::api(Provider::Jupiter, status, &body));
+                }
+                let body =
+                    http::read_body(response, Provider::Jupiter, self.max_response_bytes).await?;
+                serde_json::from_slice::<HashMap<String, String>>(&body)
+                    .map_err(|e| SwapError::Serialization(e.to_string()))
+            })
+            .await
+    }
+
+    pub async fn labeled_route(&self, quote: &QuoteResponse) -> Result<Vec<RouteHop>, SwapError> {
+        let mut route = quote.route();
+        apply_program_labels(&mut route, self.program_labels().await?);
+        Ok(route)
+    }
+
     pub async fn swap(
         &self,
         quote: &QuoteResponse,
         user_pubkey: &Pubkey,
         rpc_client: &RpcClient,
     ) -> Result<SwapResult, SwapError> {
-        let swap_request = JupiterSwapRequest {
-            user_public_key: user_pubkey.to_string(),
-            quote_response: quote.provider_data.clone(),
-            dynamic_compute_unit_limit: true,
-            skip_user_accounts_rpc_calls: Some(true),
-            wrap_and_unwrap_sol: Some(false),
-        };
+        self.swap_at_slot(quote, user_pubkey, rpc_client, None)
+            .await
+    }
+
+    // `min_context_slot` makes a lagging RPC node fail the lookup-table reads
+    // instead of returning tables older than the quote.
+    pub async fn swap_at_slot(
+        &self,
+        quote: &QuoteResponse,
+        user_pubkey: &Pubkey,
+        rpc_client: &RpcClient,
+        min_context_slot: Option<u64>,
+    ) -> Result<SwapResult, SwapError> {
+        self.swap_with_context(
+            quote,
+            user_pubkey,
+            rpc_client,
+            min_context_slot,
+            &QuoteContext::default(),
+        )
+        .await
+    }
+
+    // Checks the quote JSON offline, so a hand-built or edited quote fails before the swap call
+    // rather than with a 4xx from /swap-instructions.
+    pub fn validate_quote(&self, quote: &QuoteResponse) -> Result<(), SwapError> {
+        quote.ensure_provider(Provider::Jupiter, &QUOTE_RESPONSE_FIELDS)?;
+        if !quote.provider_data["routePlan"].is_array() {
+            return Err(SwapError::InconsistentResponse(
+                "Jupiter quote provider_data `routePlan` is not an array".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub async fn swap_with_context(
+        &self,
+        quote: &QuoteResponse,
+        user_pubkey: &Pubkey,
+        rpc_client: &RpcClient,
+        min_context_slot: Option<u64>,
+        context: &QuoteContext,
+    ) -> Result<SwapResult, SwapError> {
+        quote.ensure_provider(Provider::Jupiter, &["inAmount", "outAmount"])?;
+        let swap_request = self.swap_request(quote, user_pubkey);
 
         let url = format!("{}/swap-instructions", self.base_url);
         let mut req = self.client.post(&url).json(&swap_request);
-        if let Some(key) = &self.api_key {
+        if let Some(key) = context
+            .jupiter_api_key
+            .as_deref()
+            .or(self.api_key.as_deref())
+        {
             req = req.header("x-api-key", key);
         }
 
         debug!("jupiter swap-instructions: {url}");
-        let response = req.send().await?;
+        let response = http::send(&self.client, req, self.request_signer.as_deref()).await?;
 
         if !response.status().is_success() {
             let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            return Err(SwapError::Api {
-                provider: Provider::Jupiter,
-                message: format!("HTTP {status}: {body}"),
-            });
+            let body =
+                http::read_error_text(response, Provider::Jupiter, self.max_response_bytes).await;
+            return Err(SwapError::api(Provider::Jupiter, status, &body));
         }
 
-        let api_response: JupiterSwapInstructionsResponse = response
-            .json()
-            .await
+        let body = http::read_body(response, Provider::Jupiter, self.max_response_bytes).await?;
+        let raw_json: serde_json::Value =
+            serde_json::from_slice(&body).map_err(|e| SwapError::Serialization(e.to_string()))?;
+        if let Some(dir) = &self.record_dir {
+            let request = serde_json::to_value(&swap_request).unwrap_or_default();
+            crate::record::record(
+                dir,
+                Provider::Jupiter,
+                "swap-instructions",
+                request,
+                &raw_json,
+            )
+            .await;
+        }
+        let api_response: JupiterSwapInstructionsResponse = serde_json::from_value(raw_json)
             .map_err(|e| SwapError::Serialization(e.to_string()))?;
 
-        let mut instructions = Vec::new();
-
-        if let Some(ref ix) = api_response.token_ledger_instruction {
-            instructions.push(convert_instruction(ix)?);
-        }
-        for ix in &api_response.compute_budget_instructions {
-            instructions.push(convert_instruction(ix)?);
-        }
-        for ix in &api_response.setup_instructions {
-            instructions.push(convert_instruction(ix)?);
-        }
-        instructions.push(convert_instruction(&api_response.swap_instruction)?);
-        if let Some(ref ix) = api_response.cleanup_instruction {
-            instructions.push(convert_instruction(ix)?);
-        }
-        for ix in &api_response.other_instructions {
-            instructions.push(convert_instruction(ix)?);
-        }
+        check_simulation_error(&api_response)?;
+        let sol_wrap = match sol_wrap_amount(quote)? {
+            Some(lamports) => Some(SolWrap {
+                lamports,
+                close_after: !wsol_account_exists(user_pubkey, rpc_client, min_context_slot)
+                    .await?,
+            }),
+            None => None,
+        };
+        let phases = assemble_instructions(&api_response, user_pubkey, sol_wrap)?;
 
         let alt_addresses: Vec<Pubkey> = api_response
             .address_lookup_table_addresses
@@ -176,18 +436,260 @@ impl JupiterProvider {
             .filter_map(|s| Pubkey::from_str(s).ok())
             .collect();
 
-        let address_lookup_tables = fetch_address_lookup_tables(&alt_addresses, rpc_client).await?;
+        let address_lookup_tables =
+            fetch_address_lookup_tables(&alt_addresses, rpc_client, min_context_slot).await?;
 
         Ok(SwapResult::Instructions {
-            instructions,
+            instructions: phases.flatten(),
             address_lookup_tables,
-            compute_units: if api_response.compute_unit_limit > 0 {
-                Some(api_response.compute_unit_limit)
-            } else {
-                None
+            compute_budget: ComputeBudget {
+                limit: (api_response.compute_unit_limit > 0)
+                    .then_some(api_response.compute_unit_limit),
+                price_micro_lamports: None,
             },
+            phases: Some(phases),
+            swap_id: quote.swap_id,
+            source_quote: Some(quote.provider_data.clone()),
         })
     }
+
+    fn swap_request(&self, quote: &QuoteResponse, user_pubkey: &Pubkey) -> JupiterSwapRequest {
+        JupiterSwapRequest {
+            user_public_key: user_pubkey.to_string(),
+            quote_response: without_request_hints(&quote.provider_data),
+            dynamic_compute_unit_limit: self.dynamic_compute_unit_limit,
+            skip_user_accounts_rpc_calls: Some(true),
+            wrap_and_unwrap_sol: Some(false),
+            use_token_ledger: self.use_token_ledger,
+        }
+    }
+}
+
+// Request fields Jupiter's quote response does not echo, kept in `provider_data` so
+// `QuoteResponse::to_request` can rebuild the request. They are stripped again before the
+// response goes back to Jupiter as the swap's `quoteResponse`.
+const REQUEST_HINT_KEYS: [&str; 6] = [
+    "taker",
+    "dexes",
+    "excludeDexes",
+    "onlyDirectRoutes",
+    "feeAccount",
+    "platformFeeBps",
+];
+
+fn record_request_hints(provider_data: &mut serde_json::Value, request: &QuoteRequest) {
+    let Some(fields) = provider_data.as_object_mut() else {
+        return;
+    };
+    let hints = [
+        serde_json::json!(request.taker.map(|pk| pk.to_string())),
+        serde_json::json!(request.dexes),
+        serde_json::json!(request.exclude_dexes),
+        serde_json::json!(request.only_direct_routes),
+        serde_json::json!(request.fee_account.map(|pk| pk.to_string())),
+        serde_json::json!(request.platform_fee_bps),
+    ];
+    for (key, hint) in REQUEST_HINT_KEYS.into_iter().zip(hints) {
+        if !hint.is_null() {
+            fields.insert(key.to_string(), hint);
+        }
+    }
+}
+
+fn without_request_hints(provider_data: &serde_json::Value) -> serde_json::Value {
+    let mut quote_response = provider_data.clone();
+    if let Some(fields) = quote_response.as_object_mut() {
+        for key in REQUEST_HINT_KEYS {
+            fields.remove(key);
+        }
+    }
+    quote_response
+}
+
+fn build_quote(
+    input_mint: Pubkey,
+    output_mint: Pubkey,
+    requested_slippage_bps: u16,
+    raw_json: serde_json::Value,
+    meta: QuoteMeta,
+) -> Result<QuoteResponse, SwapError> {
+    let api_response: JupiterQuoteApiResponse = serde_json::from_value(raw_json.clone())
+        .map_err(|e| SwapError::Serialization(e.to_string()))?;
+
+    SwapError::required(api_response.input_mint, Provider::Jupiter, "inputMint")?;
+    SwapError::required(api_response.output_mint, Provider::Jupiter, "outputMint")?;
+    let in_amount = SwapError::required_amount(
+        api_response.in_amount.as_deref(),
+        Provider::Jupiter,
+        "inAmount",
+    )?;
+    let out_amount = SwapError::required_amount(
+        api_response.out_amount.as_deref(),
+        Provider::Jupiter,
+        "outAmount",
+    )?;
+    let slippage_bps =
+        SwapError::required(api_response.slippage_bps, Provider::Jupiter, "slippageBps")?;
+
+    let price_impact_bps = api_response
+        .price_impact_pct
+        .and_then(|pct| pct.parse::<f64>().ok().map(|p| (p * 100.0) as u16));
+    // Dynamic slippage lets Jupiter return a different value than was asked for.
+    let slippage_adjusted = slippage_bps != requested_slippage_bps;
+
+    Ok(QuoteResponse {
+        provider: Provider::Jupiter,
+        input_mint,
+        output_mint,
+        input_amount: in_amount,
+        output_amount: out_amount,
+        price_impact_bps,
+        slippage_bps,
+        slippage_adjusted,
+        provider_data: raw_json,
+        meta,
+        swap_id: Uuid::new_v4(),
+    })
+}
+
+fn resolve_base_url(
+    base_url: Option<String>,
+    env_url: Option<String>,
+    tier: Option<&JupiterTier>,
+) -> String {
+    let (source, url) = match (base_url, env_url, tier) {
+        (Some(url), _, _) => ("jupiter_api_url", url),
+        (None, Some(url), _) => (JUPITER_API_URL_ENV, url),
+        (None, None, Some(tier)) => ("jupiter_tier", tier.base_url().to_string()),
+        (None, None, None) => ("default", JupiterTier::Lite.base_url().to_string()),
+    };
+    let url = normalize_base_url(&url);
+    debug!("jupiter base url from {source}: {url}");
+    url
+}
+
+// Self-hosted and custom URLs are often given as a bare host; the quote and
+// swap-instructions paths hang off the versioned prefix.
+fn normalize_base_url(url: &str) -> String {
+    let trimmed = url.trim().trim_end_matches('/');
+    let has_version = trimmed
+        .rsplit('/')
+        .next()
+        .and_then(|segment| segment.strip_prefix('v'))
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+
+    if has_version {
+        trimmed.to_string()
+    } else {
+        format!("{trimmed}{JUPITER_VERSION_PATH}")
+    }
+}
+
+fn check_simulation_error(api_response: &JupiterSwapInstructionsResponse) -> Result<(), SwapError> {
+    let Some(sim) = &api_response.simulation_error else {
+        return Ok(());
+    };
+    let err = match (&sim.error_code, &sim.error) {
+        (Some(code), Some(message)) => format!("{code}: {message}"),
+        (Some(code), None) => code.clone(),
+        (None, Some(message)) => message.clone(),
+        (None, None) => "unknown simulation error".to_string(),
+    };
+    Err(SwapError::SimulationFailed {
+        logs: sim.logs.clone(),
+        err,
+    })
+}
+
+// `wrapAndUnwrapSol` is off, so an ExactOut swap from SOL needs its own wSOL; the exact
+// input is unknown until execution, so wrap the quote's maximum and unwrap what is left.
+fn sol_wrap_amount(quote: &QuoteResponse) -> Result<Option<u64>, SwapError> {
+    if quote.input_mint != token::NATIVE_MINT || quote.provider_data["swapMode"] != "ExactOut" {
+        return Ok(None);
+    }
+    quote.maximum_input().map(Some)
+}
+
+// Closing the wSOL account after the swap returns what is left as SOL, but would also unwrap
+// any wSOL the user held before, so only an account this swap creates is closed.
+#[derive(Debug, Clone, Copy)]
+struct SolWrap {
+    lamports: u64,
+    close_after: bool,
+}
+
+async fn wsol_account_exists(
+    user_pubkey: &Pubkey,
+    rpc_client: &RpcClient,
+    min_context_slot: Option<u64>,
+) -> Result<bool, SwapError> {
+    let ata =
+        token::associated_token_address(user_pubkey, &token::NATIVE_MINT, &token::TOKEN_PROGRAM);
+    let config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        commitment: Some(rpc_client.commitment()),
+        min_context_slot,
+        ..RpcAccountInfoConfig::default()
+    };
+    let account = rpc_client
+        .get_account_with_config(&ata, config)
+        .await
+        .map_err(|e| SwapError::from_rpc(&e, min_context_slot))?;
+    Ok(account.value.is_some())
+}
+
+// The ledger instruction leads the compute budget phase so the flattened phases keep it first.
+fn assemble_instructions(
+    api_response: &JupiterSwapInstructionsResponse,
+    user_pubkey: &Pubkey,
+    sol_wrap: Option<SolWrap>,
+) -> Result<InstructionPhases, SwapError> {
+    let mut phases = InstructionPhases::default();
+
+    if let Some(ref ix) = api_response.token_ledger_instruction {
+        phases.compute_budget.push(convert_instruction(ix)?);
+    }
+    for ix in &api_response.compute_budget_instructions {
+        phases.compute_budget.push(convert_instruction(ix)?);
+    }
+    for ix in &api_response.setup_instructions {
+        phases.setup.push(convert_instruction(ix)?);
+    }
+    if let Some(wrap) = sol_wrap {
+        phases
+            .setup
+            .extend(token::wrap_sol_instructions(user_pubkey, wrap.lamports));
+    }
+    phases
+        .swap
+        .push(convert_instruction(&api_response.swap_instruction)?);
+    if let Some(ref ix) = api_response.cleanup_instruction {
+        phases.cleanup.push(convert_instruction(ix)?);
+    }
+    if sol_wrap.is_some_and(|wrap| wrap.close_after) {
+        phases
+            .cleanup
+            .push(token::unwrap_sol_instruction(user_pubkey));
+    }
+    for ix in &api_response.other_instructions {
+        phases.other.push(convert_instruction(ix)?);
+    }
+
+    Ok(phases)
+}
+
+// Fills in labels the route plan omitted, keeping any label the quote already carried.
+pub fn apply_program_labels<S: BuildHasher>(
+    route: &mut [RouteHop],
+    labels: &HashMap<String, String, S>,
+) {
+    for hop in route.iter_mut().filter(|hop| hop.label.is_none()) {
+        hop.label = hop
+            .program_id
+            .as_ref()
+            .and_then(|id| labels.get(id))
+            .cloned();
+    }
 }
 
 fn convert_instruction(ix: &JupiterInstruction) -> Result<Instruction, SwapError> {
@@ -219,25 +721,1074 @@ fn convert_instruction(ix: &JupiterInstruction) -> Result<Instruction, SwapError
     })
 }
 
-async fn fetch_address_lookup_tables(
-    addresses: &[Pubkey],
-    rpc_client: &RpcClient,
-) -> Result<Vec<AddressLookupTableAccount>, SwapError> {
-    let mut tables = Vec::new();
-    for key in addresses {
-        let account = rpc_client
-            .get_account(key)
+#[cfg(test)]
+#[expect(clippy::expect_used, reason = "test code")]
+mod tests {
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::types::ProviderParams;
+
+    fn json_instruction(program_id: &Pubkey, data: &[u8]) -> serde_json::Value {
+        serde_json::json!({
+            "programId": program_id.to_string(),
+            "accounts": [],
+            "data": BASE64.encode(data),
+        })
+    }
+
+    #[test]
+    fn swap_request_serializes_use_token_ledger() {
+        let request = JupiterSwapRequest {
+            user_public_key: Pubkey::new_unique().to_string(),
+            quote_response: serde_json::json!({}),
+            dynamic_compute_unit_limit: true,
+            skip_user_accounts_rpc_calls: Some(true),
+            wrap_and_unwrap_sol: Some(false),
+            use_token_ledger: Some(true),
+        };
+
+        let json = serde_json::to_value(&request).expect("should serialize");
+        assert_eq!(json["useTokenLedger"], true);
+
+        let request = JupiterSwapRequest {
+            use_token_ledger: None,
+            ..request
+        };
+        let json = serde_json::to_value(&request).expect("should serialize");
+        assert!(json.get("useTokenLedger").is_none());
+    }
+
+    #[test]
+    fn quote_missing_amount_names_the_field() {
+        for field in ["inAmount", "outAmount"] {
+            let mut body = serde_json::json!({
+                "inputMint": Pubkey::new_unique().to_string(),
+                "outputMint": Pubkey::new_unique().to_string(),
+                "inAmount": "1000",
+                "outAmount": "900",
+                "slippageBps": 50,
+            });
+            body.as_object_mut().expect("object").remove(field);
+
+            let err = build_quote(
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                50,
+                body,
+                QuoteMeta::default(),
+            )
+            .expect_err("missing amount");
+
+            let expected = format!("field `{field}` missing from Jupiter response");
+            assert!(
+                matches!(err, SwapError::Serialization(ref m) if *m == expected),
+                "got {err:?}"
+            );
+        }
+    }
+
+    fn make_quote() -> QuoteResponse {
+        QuoteResponse {
+            provider: Provider::Jupiter,
+            input_mint: Pubkey::new_unique(),
+            output_mint: Pubkey::new_unique(),
+            input_amount: 1_000,
+            output_amount: 900,
+            price_impact_bps: None,
+            slippage_bps: 50,
+            slippage_adjusted: false,
+            provider_data: serde_json::json!({"outAmount": "900"}),
+            meta: QuoteMeta::default(),
+            swap_id: Uuid::new_v4(),
+        }
+    }
+
+    fn make_request() -> QuoteRequest {
+        QuoteRequest {
+            input_mint: Pubkey::new_unique(),
+            output_mint: Pubkey::new_unique(),
+            amount: 1_000,
+            slippage_bps: Some(50),
+            only_direct_routes: None,
+            taker: None,
+            restrict_intermediate_tokens: None,
+            as_legacy_transaction: None,
+            swap_mode: None,
+            dexes: None,
+            exclude_dexes: None,
+            input_is_fee_on_transfer: false,
+            fee_account: None,
+            platform_fee_bps: None,
+            input_token_program: None,
+            output_token_program: None,
+            provider_params: None,
+            reference_price: None,
+            providers: None,
+            preferred_intermediate_mints: None,
+        }
+    }
+
+    fn quote_body(request: &QuoteRequest) -> String {
+        serde_json::json!({
+            "inputMint": request.input_mint.to_string(),
+            "outputMint": request.output_mint.to_string(),
+            "inAmount": "1000",
+            "outAmount": "900",
+            "slippageBps": 50,
+            "priceImpactPct": "0.01",
+            "routePlan": [],
+        })
+        .to_string()
+    }
+
+    // A SOL -> USDC quote with platformFeeBps=20, in the shape Jupiter returns: the route's last
+    // leg pays out `outAmount` plus `platformFee.amount`.
+    const PLATFORM_FEE_QUOTE: &str = r#"{
+        "inputMint": "So11111111111111111111111111111111111111112",
+        "inAmount": "1000000000",
+        "outputMint": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+        "outAmount": "147258104",
+        "otherAmountThreshold": "146521813",
+        "swapMode": "ExactIn",
+        "slippageBps": 50,
+        "platformFee": {"amount": "295106", "feeBps": 20},
+        "priceImpactPct": "0.0001",
+        "routePlan": [{
+            "swapInfo": {
+                "ammKey": "Czfq3xZZDmsdGdUyrNLtRhGc47cXcZtLG4crryfu44zE",
+                "label": "Whirlpool",
+                "inputMint": "So11111111111111111111111111111111111111112",
+                "outputMint": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+                "inAmount": "1000000000",
+                "outAmount": "147553210",
+                "feeAmount": "40000",
+                "feeMint": "So11111111111111111111111111111111111111112"
+            },
+            "percent": 100
+        }],
+        "contextSlot": 300000000,
+        "timeTaken": 0.004
+    }"#;
+
+    #[tokio::test]
+    async fn platform_fee_is_already_out_of_out_amount() {
+        let body: serde_json::Value =
+            serde_json::from_str(PLATFORM_FEE_QUOTE).expect("fixture json");
+        let request = QuoteRequest {
+            input_mint: crate::token::NATIVE_MINT,
+            output_mint: Pubkey::from_str_const("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"),
+            amount: 1_000_000_000,
+            ..make_request()
+        };
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw(PLATFORM_FEE_QUOTE, "application/json"),
+            )
+            .mount(&server)
+            .await;
+
+        let provider =
+            JupiterProvider::new(Some(server.uri()), None, None, None, None).expect("http client");
+        let quote = provider.quote(&request, 50).await.expect("quote");
+
+        let leg_out: u64 = body["routePlan"][0]["swapInfo"]["outAmount"]
+            .as_str()
+            .and_then(|s| s.parse().ok())
+            .expect("leg outAmount");
+        assert_eq!(leg_out - 295_106, quote.output_amount);
+        assert_eq!(quote.net_output(), 147_258_104);
+        assert_eq!(quote.net_output_after_bps(0), quote.output_amount);
+    }
+
+    #[tokio::test]
+    async fn quote_records_latency_and_response_size() {
+        let request = make_request();
+        let body = quote_body(&request);
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(body.clone(), "application/json")
+                    .set_delay(std::time::Duration::from_millis(20)),
+            )
+            .mount(&server)
+            .await;
+
+        let provider =
+            JupiterProvider::new(Some(server.uri()), None, None, None, None).expect("http client");
+        let quote = provider.quote(&request, 50).await.expect("quote");
+
+        assert_eq!(quote.meta.response_bytes, body.len());
+        assert!(
+            quote.meta.latency_ms >= 20,
+            "latency {}",
+            quote.meta.latency_ms
+        );
+        assert_eq!(quote.output_amount, 900);
+        assert!(!quote.slippage_adjusted);
+    }
+
+    struct DummySigner;
+
+    impl RequestSigner for DummySigner {
+        fn sign(
+            &self,
+            method: &reqwest::Method,
+            path: &str,
+            body: &[u8],
+        ) -> Vec<(reqwest::header::HeaderName, String)> {
+            vec![(
+                reqwest::header::HeaderName::from_static("x-signature"),
+                format!("{method} {path} {}", body.len()),
+            )]
+        }
+    }
+
+    #[tokio::test]
+    async fn request_signer_headers_are_attached() {
+        let request = make_request();
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .and(|req: &wiremock::Request| {
+                let expected = format!("GET /swap/v1/quote?{} 0", req.url.query().unwrap_or(""));
+                req.headers
+                    .get("x-signature")
+                    .is_some_and(|v| v.to_str().ok() == Some(expected.as_str()))
+            })
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw(quote_body(&request), "application/json"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = JupiterProvider::new(Some(server.uri()), None, None, None, None)
+            .expect("http client")
+            .with_request_signer(Some(Arc::new(DummySigner)));
+        let quote = provider.quote(&request, 50).await.expect("signed quote");
+
+        assert_eq!(quote.output_amount, 900);
+    }
+
+    #[tokio::test]
+    async fn unavailable_quote_reports_http_status() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .respond_with(ResponseTemplate::new(503).set_body_string("upstream unavailable"))
+            .mount(&server)
+            .await;
+
+        let provider =
+            JupiterProvider::new(Some(server.uri()), None, None, None, None).expect("http client");
+        let err = provider.quote(&make_request(), 50).await.expect_err("503");
+
+        assert!(
+            matches!(err, SwapError::Api { status: 503, .. }),
+            "got {err:?}"
+        );
+        assert_eq!(err.http_status(), Some(503));
+    }
+
+    #[tokio::test]
+    async fn oversized_response_body_is_rejected() {
+        let request = make_request();
+        let body = quote_body(&request);
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body.clone(), "application/json"))
+            .mount(&server)
+            .await;
+
+        let capped = JupiterProvider::new(Some(server.uri()), None, None, None, None)
+            .expect("http client")
+            .with_max_response_bytes(Some(body.len() - 1));
+        let err = capped.quote(&request, 50).await.expect_err("over the cap");
+        assert!(
+            matches!(
+                err,
+                SwapError::Api {
+                    provider: Provider::Jupiter,
+                    status: 200,
+                    ..
+                }
+            ),
+            "got {err:?}"
+        );
+
+        let exact = JupiterProvider::new(Some(server.uri()), None, None, None, None)
+            .expect("http client")
+            .with_max_response_bytes(Some(body.len()));
+        let quote = exact.quote(&request, 50).await.expect("within the cap");
+        assert_eq!(quote.output_amount, 900);
+    }
+
+    #[tokio::test]
+    async fn recorded_quote_replays_to_same_quote() {
+        let request = make_request();
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw(quote_body(&request), "application/json"),
+            )
+            .mount(&server)
+            .await;
+        let dir = std::env::temp_dir().join(format!("jupiter-record-{}", Uuid::new_v4()));
+        std::fs::create_dir(&dir).expect("record dir");
+
+        let provider = JupiterProvider::new(
+            Some(server.uri()),
+            Some("secret-key".to_string()),
+            None,
+            None,
+            None,
+        )
+        .expect("http client")
+        .with_record_dir(Some(dir.clone()));
+        let live = provider.quote(&request, 50).await.expect("quote");
+
+        let files: Vec<PathBuf> = std::fs::read_dir(&dir)
+            .expect("read record dir")
+            .map(|entry| entry.expect("dir entry").path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        let contents = std::fs::read_to_string(&files[0]).expect("recording");
+        assert!(!contents.contains("secret-key"));
+
+        let replayed = JupiterProvider::quote_from_recording(&files[0]).expect("replay");
+        std::fs::remove_dir_all(&dir).expect("clean up");
+
+        assert_eq!(replayed.provider, live.provider);
+        assert_eq!(replayed.input_mint, live.input_mint);
+        assert_eq!(replayed.output_mint, live.output_mint);
+        assert_eq!(replayed.input_amount, live.input_amount);
+        assert_eq!(replayed.output_amount, live.output_amount);
+        assert_eq!(replayed.price_impact_bps, live.price_impact_bps);
+        assert_eq!(replayed.slippage_bps, live.slippage_bps);
+        assert_eq!(replayed.slippage_adjusted, live.slippage_adjusted);
+        assert_eq!(replayed.provider_data, live.provider_data);
+    }
+
+    #[tokio::test]
+    async fn quote_flags_dynamically_adjusted_slippage() {
+        let request = make_request();
+        let mut body: serde_json::Value =
+            serde_json::from_str(&quote_body(&request)).expect("quote body");
+        body["slippageBps"] = serde_json::json!(120);
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .and(query_param("slippageBps", "50"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&server)
+            .await;
+
+        let provider =
+            JupiterProvider::new(Some(server.uri()), None, None, None, None).expect("http client");
+        let quote = provider.quote(&request, 50).await.expect("quote");
+
+        assert_eq!(quote.slippage_bps, 120);
+        assert!(quote.slippage_adjusted);
+    }
+
+    #[tokio::test]
+    async fn provider_params_reach_query_without_overriding_typed_fields() {
+        let mut jupiter = serde_json::Map::new();
+        jupiter.insert("preferLiquidDexes".to_string(), serde_json::json!(true));
+        jupiter.insert("slippageBps".to_string(), serde_json::json!(999));
+        let request = QuoteRequest {
+            provider_params: Some(ProviderParams {
+                jupiter,
+                ..ProviderParams::default()
+            }),
+            ..make_request()
+        };
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .and(query_param("preferLiquidDexes", "true"))
+            .and(query_param("slippageBps", "50"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw(quote_body(&request), "application/json"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider =
+            JupiterProvider::new(Some(server.uri()), None, None, None, None).expect("http client");
+        provider.quote(&request, 50).await.expect("quote");
+
+        let received = server.received_requests().await.expect("recorded");
+        let slippage_params = received[0]
+            .url
+            .query_pairs()
+            .filter(|(key, _)| key == "slippageBps")
+            .count();
+        assert_eq!(slippage_params, 1);
+    }
+
+    #[tokio::test]
+    async fn preferred_intermediate_mints_restrict_intermediate_tokens() {
+        let request = QuoteRequest {
+            preferred_intermediate_mints: Some(vec![Pubkey::new_unique()]),
+            ..make_request()
+        };
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .and(query_param("restrictIntermediateTokens", "true"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw(quote_body(&request), "application/json"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider =
+            JupiterProvider::new(Some(server.uri()), None, None, None, None).expect("http client");
+        provider.quote(&request, 50).await.expect("quote");
+
+        let explicit = QuoteRequest {
+            restrict_intermediate_tokens: Some(false),
+            ..request
+        };
+        let err = provider
+            .quote(&explicit, 50)
             .await
-            .map_err(|e| SwapError::Solana(e.to_string()))?;
+            .expect_err("explicit false is sent as-is and misses the mock");
+        assert!(
+            matches!(err, SwapError::Api { status: 404, .. }),
+            "got {err:?}"
+        );
+    }
 
-        let lookup_table = AddressLookupTable::deserialize(&account.data).map_err(
-            |e: solana_sdk::instruction::InstructionError| SwapError::Solana(e.to_string()),
-        )?;
+    #[tokio::test]
+    async fn quote_variants_returns_successful_variants() {
+        let request = make_request();
+        let server = MockServer::start().await;
+        for max_accounts in ["20", "40"] {
+            Mock::given(method("GET"))
+                .and(path("/swap/v1/quote"))
+                .and(query_param("maxAccounts", max_accounts))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_body_raw(quote_body(&request), "application/json"),
+                )
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .and(query_param("maxAccounts", "10"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("upstream error"))
+            .expect(1)
+            .mount(&server)
+            .await;
 
-        tables.push(AddressLookupTableAccount {
-            key: *key,
-            addresses: lookup_table.addresses.to_vec(),
+        let variants: Vec<QuoteConstraint> = [20, 40, 10]
+            .into_iter()
+            .map(|max_accounts| QuoteConstraint {
+                max_accounts: Some(max_accounts),
+                ..QuoteConstraint::default()
+            })
+            .collect();
+        let provider =
+            JupiterProvider::new(Some(server.uri()), None, None, None, None).expect("http client");
+        let quotes = provider
+            .quote_variants(&request, 50, &variants)
+            .await
+            .expect("variants");
+
+        assert_eq!(quotes.len(), 2);
+        assert!(quotes.iter().all(|q| q.provider == Provider::Jupiter));
+    }
+
+    #[tokio::test]
+    async fn quote_variants_returns_last_error_when_all_fail() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("unauthorized"))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let variants = [20, 40].map(|max_accounts| QuoteConstraint {
+            max_accounts: Some(max_accounts),
+            ..QuoteConstraint::default()
         });
+        let provider =
+            JupiterProvider::new(Some(server.uri()), None, None, None, None).expect("http client");
+        let err = provider
+            .quote_variants(&make_request(), 50, &variants)
+            .await
+            .expect_err("no variant succeeded");
+
+        assert!(
+            matches!(err, SwapError::Api { status: 401, .. }),
+            "got {err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn price_stream_polls_quote_endpoint() {
+        use futures::StreamExt;
+
+        let request = make_request();
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw(quote_body(&request), "application/json"),
+            )
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        let provider =
+            JupiterProvider::new(Some(server.uri()), None, None, None, None).expect("http client");
+        let started = Instant::now();
+        let updates: Vec<_> = provider
+            .price_stream(request.clone(), Duration::from_millis(20))
+            .take(3)
+            .collect()
+            .await;
+
+        assert_eq!(updates.len(), 3);
+        for update in updates {
+            let update = update.expect("price update");
+            assert_eq!(update.input_mint, request.input_mint);
+            assert_eq!(update.in_amount, 1_000);
+            assert_eq!(update.out_amount, 900);
+            assert!((update.price() - 0.9).abs() < f64::EPSILON);
+        }
+        // The first tick fires immediately, the next two wait one interval each.
+        assert!(started.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[tokio::test]
+    async fn swap_rejects_quote_from_another_provider() {
+        let provider = JupiterProvider::new(None, None, None, None, None).expect("http client");
+        let rpc = RpcClient::new("http://127.0.0.1:1".to_string());
+        let dflow_quote = QuoteResponse {
+            provider: Provider::Dflow,
+            provider_data: serde_json::json!({"amount": 1_000, "slippageBps": 50}),
+            ..make_quote()
+        };
+
+        let err = provider
+            .swap(&dflow_quote, &Pubkey::new_unique(), &rpc)
+            .await
+            .expect_err("provider mismatch");
+        assert!(
+            matches!(err, SwapError::InconsistentResponse(ref m) if m.contains("Dflow")),
+            "got {err:?}"
+        );
+
+        let relabeled = QuoteResponse {
+            provider: Provider::Jupiter,
+            ..dflow_quote
+        };
+        let err = provider
+            .swap(&relabeled, &Pubkey::new_unique(), &rpc)
+            .await
+            .expect_err("dflow-shaped provider_data");
+        assert!(
+            matches!(err, SwapError::InconsistentResponse(ref m) if m.contains("inAmount")),
+            "got {err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn validate_quote_names_missing_field() {
+        let request = make_request();
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/quote"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "inputMint": request.input_mint.to_string(),
+                "outputMint": request.output_mint.to_string(),
+                "inAmount": "1000",
+                "outAmount": "900",
+                "otherAmountThreshold": "895",
+                "swapMode": "ExactIn",
+                "slippageBps": 50,
+                "routePlan": [],
+            })))
+            .mount(&server)
+            .await;
+        let provider =
+            JupiterProvider::new(Some(server.uri()), None, None, None, None).expect("http client");
+        let quote = provider.quote(&request, 50).await.expect("quote");
+        provider
+            .validate_quote(&quote)
+            .expect("live quote is valid");
+
+        let mut malformed = quote.clone();
+        if let Some(data) = malformed.provider_data.as_object_mut() {
+            data.remove("otherAmountThreshold");
+        }
+        let err = provider
+            .validate_quote(&malformed)
+            .expect_err("missing threshold");
+        assert!(
+            matches!(err, SwapError::InconsistentResponse(ref m) if m.contains("otherAmountThreshold")),
+            "got {err:?}"
+        );
+
+        let mut malformed = quote;
+        malformed.provider_data["routePlan"] = serde_json::json!("none");
+        let err = provider
+            .validate_quote(&malformed)
+            .expect_err("routePlan is not a list");
+        assert!(
+            matches!(err, SwapError::InconsistentResponse(ref m) if m.contains("routePlan")),
+            "got {err:?}"
+        );
+    }
+
+    #[test]
+    fn apply_program_labels_enriches_unlabeled_hops() {
+        let hop = RouteHop {
+            amm_key: Some("amm".to_string()),
+            program_id: Some("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc".to_string()),
+            label: None,
+            input_mint: None,
+            output_mint: None,
+            in_amount: None,
+            out_amount: None,
+            percent: Some(100),
+        };
+        let labeled = RouteHop {
+            label: Some("Raydium".to_string()),
+            ..hop.clone()
+        };
+        let unknown = RouteHop {
+            program_id: Some("unknown".to_string()),
+            ..hop.clone()
+        };
+        let labels = HashMap::from([(
+            "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc".to_string(),
+            "Whirlpool".to_string(),
+        )]);
+        let mut route = vec![hop, labeled, unknown];
+
+        apply_program_labels(&mut route, &labels);
+
+        assert_eq!(route[0].label.as_deref(), Some("Whirlpool"));
+        assert_eq!(route[1].label.as_deref(), Some("Raydium"));
+        assert_eq!(route[2].label, None);
+    }
+
+    #[tokio::test]
+    async fn program_labels_are_fetched_once() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/swap/v1/program-id-to-label"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({"prog": "Meteora"})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider =
+            JupiterProvider::new(Some(server.uri()), None, None, None, None).expect("http client");
+        for _ in 0..2 {
+            let labels = provider.program_labels().await.expect("labels");
+            assert_eq!(labels.get("prog").map(String::as_str), Some("Meteora"));
+        }
+        server.verify().await;
+    }
+
+    #[test]
+    fn dynamic_cu_limit_defaults_to_true() {
+        let provider = JupiterProvider::new(None, None, None, None, None).expect("http client");
+        let request = provider.swap_request(&make_quote(), &Pubkey::new_unique());
+
+        let json = serde_json::to_value(&request).expect("should serialize");
+        assert_eq!(json["dynamicComputeUnitLimit"], true);
+    }
+
+    #[test]
+    fn dynamic_cu_limit_reflects_config() {
+        let provider =
+            JupiterProvider::new(None, None, None, None, Some(false)).expect("http client");
+        let request = provider.swap_request(&make_quote(), &Pubkey::new_unique());
+
+        let json = serde_json::to_value(&request).expect("should serialize");
+        assert_eq!(json["dynamicComputeUnitLimit"], false);
+    }
+
+    #[test]
+    fn request_hints_are_kept_for_refresh_but_not_sent_with_the_swap() {
+        let fee_account = Pubkey::new_unique();
+        let request = QuoteRequest {
+            dexes: Some("Orca,Raydium".to_string()),
+            fee_account: Some(fee_account),
+            platform_fee_bps: Some(20),
+            ..make_request()
+        };
+        let mut quote = make_quote();
+        record_request_hints(&mut quote.provider_data, &request);
+
+        let rebuilt = quote.to_request();
+        assert_eq!(rebuilt.dexes.as_deref(), Some("Orca,Raydium"));
+        assert_eq!(rebuilt.fee_account, Some(fee_account));
+        assert_eq!(rebuilt.platform_fee_bps, Some(20));
+
+        let provider = JupiterProvider::new(None, None, None, None, None).expect("http client");
+        let swap = provider.swap_request(&quote, &Pubkey::new_unique());
+        for key in REQUEST_HINT_KEYS {
+            assert!(
+                swap.quote_response.get(key).is_none(),
+                "{key} sent to Jupiter"
+            );
+        }
+    }
+
+    #[test]
+    fn base_url_resolves_each_tier() {
+        assert_eq!(
+            resolve_base_url(None, None, None),
+            "https://lite-api.jup.ag/swap/v1"
+        );
+        assert_eq!(
+            resolve_base_url(None, None, Some(&JupiterTier::Lite)),
+            "https://lite-api.jup.ag/swap/v1"
+        );
+        assert_eq!(
+            resolve_base_url(None, None, Some(&JupiterTier::Pro)),
+            "https://api.jup.ag/swap/v1"
+        );
+        assert_eq!(
+            resolve_base_url(
+                None,
+                None,
+                Some(&JupiterTier::Custom("https://jup.internal/".to_string()))
+            ),
+            "https://jup.internal/swap/v1"
+        );
+    }
+
+    #[test]
+    fn base_url_normalizes_trailing_slashes_and_version() {
+        assert_eq!(
+            normalize_base_url("https://api.jup.ag/swap/v1/"),
+            "https://api.jup.ag/swap/v1"
+        );
+        assert_eq!(
+            normalize_base_url("https://api.jup.ag/swap/v1//"),
+            "https://api.jup.ag/swap/v1"
+        );
+        assert_eq!(
+            normalize_base_url("http://localhost:8080"),
+            "http://localhost:8080/swap/v1"
+        );
+        assert_eq!(
+            normalize_base_url("https://self.hosted/jupiter/v6"),
+            "https://self.hosted/jupiter/v6"
+        );
+    }
+
+    #[test]
+    fn explicit_and_env_urls_take_precedence_over_tier() {
+        assert_eq!(
+            resolve_base_url(
+                Some("https://explicit.example/".to_string()),
+                Some("https://env.example".to_string()),
+                Some(&JupiterTier::Pro)
+            ),
+            "https://explicit.example/swap/v1"
+        );
+        assert_eq!(
+            resolve_base_url(
+                None,
+                Some("https://env.example/swap/v1".to_string()),
+                Some(&JupiterTier::Pro)
+            ),
+            "https://env.example/swap/v1"
+        );
+    }
+
+    #[test]
+    fn simulation_error_is_surfaced() {
+        let response: JupiterSwapInstructionsResponse = serde_json::from_value(serde_json::json!({
+            "swapInstruction": json_instruction(&Pubkey::new_unique(), &[1]),
+            "simulationError": {
+                "errorCode": "INSUFFICIENT_FUNDS",
+                "error": "Attempt to debit an account but found no record of a prior credit.",
+                "logs": ["Program log: Error: insufficient funds"],
+            },
+        }))
+        .expect("should deserialize");
+
+        let err = check_simulation_error(&response).expect_err("should fail");
+        let SwapError::SimulationFailed { logs, err } = err else {
+            unreachable!("expected SimulationFailed, got {err:?}");
+        };
+        assert!(err.starts_with("INSUFFICIENT_FUNDS: Attempt to debit"));
+        assert_eq!(logs, vec!["Program log: Error: insufficient funds"]);
+    }
+
+    #[test]
+    fn missing_simulation_error_passes() {
+        let response: JupiterSwapInstructionsResponse = serde_json::from_value(serde_json::json!({
+            "swapInstruction": json_instruction(&Pubkey::new_unique(), &[1]),
+            "simulationError": null,
+        }))
+        .expect("should deserialize");
+
+        assert!(check_simulation_error(&response).is_ok());
+    }
+
+    #[test]
+    fn token_ledger_instruction_is_ordered_first() {
+        let ledger = Pubkey::new_unique();
+        let budget = Pubkey::new_unique();
+        let setup = Pubkey::new_unique();
+        let swap = Pubkey::new_unique();
+        let cleanup = Pubkey::new_unique();
+
+        let response: JupiterSwapInstructionsResponse = serde_json::from_value(serde_json::json!({
+            "computeBudgetInstructions": [json_instruction(&budget, &[1])],
+            "setupInstructions": [json_instruction(&setup, &[2])],
+            "swapInstruction": json_instruction(&swap, &[3]),
+            "cleanupInstruction": json_instruction(&cleanup, &[4]),
+            "tokenLedgerInstruction": json_instruction(&ledger, &[5]),
+        }))
+        .expect("should deserialize");
+
+        let instructions = assemble_instructions(&response, &Pubkey::new_unique(), None)
+            .expect("should assemble")
+            .flatten();
+        let programs: Vec<Pubkey> = instructions.iter().map(|ix| ix.program_id).collect();
+
+        assert_eq!(programs, vec![ledger, budget, setup, swap, cleanup]);
+        assert_eq!(instructions[0].data, vec![5]);
+    }
+
+    #[test]
+    fn exact_out_sol_input_wraps_maximum_input() {
+        let user = Pubkey::new_unique();
+        let quote = QuoteResponse {
+            input_mint: token::NATIVE_MINT,
+            input_amount: 1_000_000,
+            provider_data: serde_json::json!({
+                "inAmount": "1000000",
+                "outAmount": "900",
+                "swapMode": "ExactOut",
+                "otherAmountThreshold": "1010000",
+            }),
+            ..make_quote()
+        };
+        let swap = Pubkey::new_unique();
+        let response: JupiterSwapInstructionsResponse = serde_json::from_value(serde_json::json!({
+            "swapInstruction": json_instruction(&swap, &[3]),
+        }))
+        .expect("should deserialize");
+
+        let wrap = sol_wrap_amount(&quote).expect("wrap amount");
+        assert_eq!(wrap, Some(1_010_000));
+        assert_eq!(wrap, Some(quote.maximum_input().expect("maximum input")));
+
+        let sol_wrap = SolWrap {
+            lamports: 1_010_000,
+            close_after: true,
+        };
+        let instructions = assemble_instructions(&response, &user, Some(sol_wrap))
+            .expect("should assemble")
+            .flatten();
+        let programs: Vec<Pubkey> = instructions.iter().map(|ix| ix.program_id).collect();
+        assert_eq!(
+            programs,
+            vec![
+                token::ASSOCIATED_TOKEN_PROGRAM,
+                token::SYSTEM_PROGRAM,
+                token::TOKEN_PROGRAM,
+                swap,
+                token::TOKEN_PROGRAM,
+            ]
+        );
+        assert_eq!(instructions[1].data[4..], 1_010_000_u64.to_le_bytes());
+        assert_eq!(instructions[4].accounts[1].pubkey, user);
+
+        let existing = SolWrap {
+            close_after: false,
+            ..sol_wrap
+        };
+        let instructions = assemble_instructions(&response, &user, Some(existing))
+            .expect("should assemble")
+            .flatten();
+        assert_eq!(instructions.len(), 4);
+        assert_eq!(instructions[3].program_id, swap);
+
+        let exact_in = QuoteResponse {
+            provider_data: serde_json::json!({"swapMode": "ExactIn"}),
+            ..quote
+        };
+        assert_eq!(sol_wrap_amount(&exact_in).expect("wrap amount"), None);
+    }
+
+    #[tokio::test]
+    async fn exact_out_sol_input_keeps_an_existing_wsol_account_open() {
+        let user = Pubkey::new_unique();
+        let output_mint = Pubkey::new_unique();
+        let quote = QuoteResponse {
+            input_mint: token::NATIVE_MINT,
+            output_mint,
+            input_amount: 1_000_000,
+            provider_data: serde_json::json!({
+                "inputMint": token::NATIVE_MINT.to_string(),
+                "outputMint": output_mint.to_string(),
+                "inAmount": "1000000",
+                "outAmount": "900",
+                "swapMode": "ExactOut",
+                "otherAmountThreshold": "1010000",
+            }),
+            ..make_quote()
+        };
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/swap/v1/swap-instructions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "swapInstruction": json_instruction(&Pubkey::new_unique(), &[3]),
+            })))
+            .mount(&server)
+            .await;
+        let rpc_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "context": {"slot": 1},
+                    "value": {
+                        "data": ["", "base64"],
+                        "executable": false,
+                        "lamports": 2_039_280,
+                        "owner": token::TOKEN_PROGRAM.to_string(),
+                        "rentEpoch": 0,
+                        "space": 0,
+                    },
+                },
+            })))
+            .expect(1)
+            .mount(&rpc_server)
+            .await;
+
+        let provider =
+            JupiterProvider::new(Some(server.uri()), None, None, None, None).expect("http client");
+        let result = provider
+            .swap(&quote, &user, &RpcClient::new(rpc_server.uri()))
+            .await
+            .expect("swap");
+
+        let SwapResult::Instructions { instructions, .. } = result else {
+            unreachable!("Jupiter returns instructions");
+        };
+        let wsol =
+            token::associated_token_address(&user, &token::NATIVE_MINT, &token::TOKEN_PROGRAM);
+        assert_eq!(instructions[1].data[4..], 1_010_000_u64.to_le_bytes());
+        assert!(!instructions
+            .iter()
+            .any(|ix| token::closes_account(ix, &wsol)));
+    }
+
+    #[tokio::test]
+    async fn swap_phases_concatenate_to_flat_instructions() {
+        let (budget, setup, swap, cleanup, other) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/swap/v1/swap-instructions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "computeBudgetInstructions": [json_instruction(&budget, &[1])],
+                "setupInstructions": [
+                    json_instruction(&setup, &[2]),
+                    json_instruction(&setup, &[3]),
+                ],
+                "swapInstruction": json_instruction(&swap, &[4]),
+                "cleanupInstruction": json_instruction(&cleanup, &[5]),
+                "otherInstructions": [json_instruction(&other, &[6])],
+                "addressLookupTableAddresses": [],
+            })))
+            .mount(&server)
+            .await;
+        let provider =
+            JupiterProvider::new(Some(server.uri()), None, None, None, None).expect("http client");
+        let rpc = RpcClient::new(server.uri());
+        let quote = QuoteResponse {
+            provider_data: serde_json::json!({"inAmount": "1000", "outAmount": "900"}),
+            ..make_quote()
+        };
+
+        let result = provider
+            .swap(&quote, &Pubkey::new_unique(), &rpc)
+            .await
+            .expect("swap instructions");
+
+        let SwapResult::Instructions {
+            instructions,
+            phases: Some(phases),
+            ..
+        } = result
+        else {
+            unreachable!("Jupiter reports phases");
+        };
+        assert_eq!(phases.flatten(), instructions);
+        assert_eq!(phases.compute_budget[0].program_id, budget);
+        assert_eq!(phases.setup.len(), 2);
+        assert_eq!(phases.swap[0].program_id, swap);
+        assert_eq!(phases.cleanup[0].program_id, cleanup);
+        assert_eq!(phases.other[0].program_id, other);
+    }
+
+    #[tokio::test]
+    async fn swap_result_carries_source_quote() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/swap/v1/swap-instructions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "swapInstruction": json_instruction(&Pubkey::new_unique(), &[1]),
+                "addressLookupTableAddresses": [],
+            })))
+            .mount(&server)
+            .await;
+        let provider =
+            JupiterProvider::new(Some(server.uri()), None, None, None, None).expect("http client");
+        let rpc = RpcClient::new(server.uri());
+        let quote = QuoteResponse {
+            provider_data: serde_json::json!({
+                "inAmount": "1000",
+                "outAmount": "900",
+                "routePlan": [{"swapInfo": {"label": "Whirlpool"}, "percent": 100}],
+            }),
+            ..make_quote()
+        };
+
+        let result = provider
+            .swap(&quote, &Pubkey::new_unique(), &rpc)
+            .await
+            .expect("swap instructions");
+
+        assert_eq!(result.source_quote(), Some(&quote.provider_data));
     }
-    Ok(tables)
 }
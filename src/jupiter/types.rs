@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
 
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -21,16 +22,50 @@ pub struct JupiterQuoteParams {
     pub dexes: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub exclude_dexes: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_accounts: Option<u16>,
+}
+
+// Per-variant overrides for `JupiterProvider::quote_variants`; `None` keeps the request's value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuoteConstraint {
+    pub max_accounts: Option<u16>,
+    pub only_direct_routes: Option<bool>,
+    pub restrict_intermediate_tokens: Option<bool>,
+}
+
+// One sample from `JupiterProvider::price_stream`: the quoted amounts only, no route or swap data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriceUpdate {
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub in_amount: u64,
+    pub out_amount: u64,
+}
+
+impl PriceUpdate {
+    // Output per unit of input, in raw token units.
+    pub fn price(&self) -> f64 {
+        if self.in_amount == 0 {
+            return 0.0;
+        }
+        self.out_amount as f64 / self.in_amount as f64
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct JupiterQuoteApiResponse {
-    pub input_mint: String,
-    pub output_mint: String,
-    pub in_amount: String,
-    pub out_amount: String,
-    pub slippage_bps: u16,
+    #[serde(default)]
+    pub input_mint: Option<String>,
+    #[serde(default)]
+    pub output_mint: Option<String>,
+    #[serde(default)]
+    pub in_amount: Option<String>,
+    #[serde(default)]
+    pub out_amount: Option<String>,
+    #[serde(default)]
+    pub slippage_bps: Option<u16>,
     #[serde(default)]
     pub other_amount_threshold: Option<String>,
     #[serde(default)]
@@ -47,6 +82,8 @@ pub struct JupiterSwapRequest {
     pub skip_user_accounts_rpc_calls: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub wrap_and_unwrap_sol: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_token_ledger: Option<bool>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -65,6 +102,19 @@ pub struct JupiterSwapInstructionsResponse {
     pub address_lookup_table_addresses: Vec<String>,
     #[serde(default)]
     pub compute_unit_limit: u32,
+    #[serde(default)]
+    pub simulation_error: Option<JupiterSimulationError>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JupiterSimulationError {
+    #[serde(default)]
+    pub error_code: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub logs: Vec<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -1,7 +1,26 @@
 pub mod aggregator;
+pub mod cache;
 pub mod error;
+pub mod metrics;
+pub mod preflight;
+pub mod send;
+pub mod service;
+pub mod signer;
+pub mod simulate;
+pub mod token;
 pub mod types;
 
+#[cfg(feature = "blocking")]
+mod blocking;
+#[cfg(feature = "display")]
+pub mod display;
+#[cfg(any(feature = "jupiter", feature = "dflow"))]
+mod http;
+#[cfg(any(feature = "jupiter", feature = "titan"))]
+mod lookup;
+#[cfg(any(feature = "jupiter", feature = "dflow"))]
+pub mod record;
+
 #[cfg(feature = "dflow")]
 pub mod dflow;
 #[cfg(feature = "jupiter")]
@@ -10,8 +29,18 @@ pub mod jupiter;
 pub mod titan;
 
 pub use aggregator::SwapAggregator;
+#[cfg(feature = "display")]
+pub use display::render_comparison;
 pub use error::SwapError;
+#[cfg(any(feature = "jupiter", feature = "dflow"))]
+pub use http::HttpClient;
+pub use preflight::PreflightReport;
+pub use service::SwapService;
+pub use simulate::SimSummary;
 pub use types::{
-    CpiSwapResult, Provider, QuoteRequest, QuoteResponse, SwapConfig, SwapMode, SwapResult,
-    JUPITER_PROGRAM, TITAN_PROGRAM,
+    apply_slippage, apply_slippage_up, split_amount, AcceptableOutput, Capabilities, Capability,
+    ComputeBudget, CpiSwapResult, InstructionPhases, JupiterTier, NormalizedQuote,
+    PostSwapTransfer, Provider, ProviderParams, QuoteContext, QuoteGuard, QuoteMeta, QuoteRequest,
+    QuoteResponse, ReferencePrice, RequestSigner, RouteHop, Slippage, SlippageEscalation,
+    SwapConfig, SwapMode, SwapResult, JUPITER_PROGRAM, MAX_TRANSACTION_BYTES, TITAN_PROGRAM,
 };
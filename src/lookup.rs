@@ -0,0 +1,87 @@
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_address_lookup_table_interface::state::AddressLookupTable;
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcAccountInfoConfig};
+use solana_sdk::{address_lookup_table::AddressLookupTableAccount, pubkey::Pubkey};
+
+use crate::error::SwapError;
+
+// Loads the lookup tables a provider's instructions reference. Any missing or unreadable table
+// fails the swap, since the compiled transaction would not resolve without it.
+pub(crate) async fn fetch_address_lookup_tables(
+    addresses: &[Pubkey],
+    rpc_client: &RpcClient,
+    min_context_slot: Option<u64>,
+) -> Result<Vec<AddressLookupTableAccount>, SwapError> {
+    let config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        commitment: Some(rpc_client.commitment()),
+        min_context_slot,
+        ..RpcAccountInfoConfig::default()
+    };
+    let mut tables = Vec::new();
+    for key in addresses {
+        let account = rpc_client
+            .get_account_with_config(key, config.clone())
+            .await
+            .map_err(|e| SwapError::from_rpc(&e, min_context_slot))?
+            .value
+            .ok_or_else(|| SwapError::Solana(format!("address lookup table {key} not found")))?;
+
+        let lookup_table = AddressLookupTable::deserialize(&account.data).map_err(
+            |e: solana_sdk::instruction::InstructionError| SwapError::Solana(e.to_string()),
+        )?;
+
+        tables.push(AddressLookupTableAccount {
+            key: *key,
+            addresses: lookup_table.addresses.to_vec(),
+        });
+    }
+    Ok(tables)
+}
+
+#[cfg(test)]
+#[expect(clippy::expect_used, reason = "test code")]
+mod tests {
+    use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn lookup_table_fetch_forwards_min_context_slot() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(|request: &wiremock::Request| {
+                let body: serde_json::Value =
+                    serde_json::from_slice(&request.body).expect("json-rpc body");
+                body["method"] == "getAccountInfo"
+                    && body["params"][1]["minContextSlot"] == 500
+                    && body["params"][1]["encoding"] == "base64"
+            })
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "error": {
+                    "code": -32016,
+                    "message": "Minimum context slot has not been reached",
+                },
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let rpc = RpcClient::new(server.uri());
+        let err = fetch_address_lookup_tables(&[Pubkey::new_unique()], &rpc, Some(500))
+            .await
+            .expect_err("node behind min context slot");
+
+        assert!(
+            matches!(
+                err,
+                SwapError::MinContextSlotNotReached {
+                    min_context_slot: 500
+                }
+            ),
+            "got {err:?}"
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use crate::types::Provider;
+
+pub const LATENCY_BUCKETS_MS: [u64; 8] = [50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProviderMetrics {
+    pub quotes: u64,
+    pub quote_errors: u64,
+    // per-bucket (non-cumulative) counts for LATENCY_BUCKETS_MS; slower quotes only show in `quotes`
+    pub latency_buckets: [u64; LATENCY_BUCKETS_MS.len()],
+    pub latency_sum_ms: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct MetricsCollector {
+    providers: Mutex<HashMap<Provider, ProviderMetrics>>,
+}
+
+impl MetricsCollector {
+    pub fn record_quote(&self, provider: Provider, ok: bool, latency: Duration) {
+        let Ok(mut providers) = self.providers.lock() else {
+            return;
+        };
+        let metrics = providers.entry(provider).or_default();
+        let latency_ms = u64::try_from(latency.as_millis()).unwrap_or(u64::MAX);
+
+        metrics.quotes += 1;
+        if !ok {
+            metrics.quote_errors += 1;
+        }
+        metrics.latency_sum_ms = metrics.latency_sum_ms.saturating_add(latency_ms);
+        if let Some(i) = LATENCY_BUCKETS_MS.iter().position(|le| latency_ms <= *le) {
+            metrics.latency_buckets[i] += 1;
+        }
+    }
+
+    pub fn provider(&self, provider: Provider) -> ProviderMetrics {
+        self.providers
+            .lock()
+            .ok()
+            .and_then(|providers| providers.get(&provider).cloned())
+            .unwrap_or_default()
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn render_prometheus(&self) -> String {
+        use std::fmt::Write;
+
+        let mut out = String::new();
+        let snapshot: Vec<(String, ProviderMetrics)> = Provider::ALL
+            .iter()
+            .map(|p| (p.to_string().to_lowercase(), self.provider(*p)))
+            .collect();
+
+        out.push_str("# HELP swap_quote_total Quote requests per provider.\n");
+        out.push_str("# TYPE swap_quote_total counter\n");
+        for (label, m) in &snapshot {
+            let _ = writeln!(out, "swap_quote_total{{provider=\"{label}\"}} {}", m.quotes);
+        }
+
+        out.push_str("# HELP swap_quote_errors_total Failed quote requests per provider.\n");
+        out.push_str("# TYPE swap_quote_errors_total counter\n");
+        for (label, m) in &snapshot {
+            let _ = writeln!(
+                out,
+                "swap_quote_errors_total{{provider=\"{label}\"}} {}",
+                m.quote_errors
+            );
+        }
+
+        out.push_str("# HELP swap_quote_latency_ms Quote latency in milliseconds.\n");
+        out.push_str("# TYPE swap_quote_latency_ms histogram\n");
+        for (label, m) in &snapshot {
+            let mut cumulative = 0;
+            for (le, count) in LATENCY_BUCKETS_MS.iter().zip(m.latency_buckets) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "swap_quote_latency_ms_bucket{{provider=\"{label}\",le=\"{le}\"}} {cumulative}"
+                );
+            }
+            let _ = writeln!(
+                out,
+                "swap_quote_latency_ms_bucket{{provider=\"{label}\",le=\"+Inf\"}} {}",
+                m.quotes
+            );
+            let _ = writeln!(
+                out,
+                "swap_quote_latency_ms_sum{{provider=\"{label}\"}} {}",
+                m.latency_sum_ms
+            );
+            let _ = writeln!(
+                out,
+                "swap_quote_latency_ms_count{{provider=\"{label}\"}} {}",
+                m.quotes
+            );
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_quote_counts_errors_and_buckets_latency() {
+        let metrics = MetricsCollector::default();
+        metrics.record_quote(Provider::Jupiter, true, Duration::from_millis(40));
+        metrics.record_quote(Provider::Jupiter, false, Duration::from_millis(300));
+        metrics.record_quote(Provider::Jupiter, true, Duration::from_secs(20));
+
+        let jupiter = metrics.provider(Provider::Jupiter);
+        assert_eq!(jupiter.quotes, 3);
+        assert_eq!(jupiter.quote_errors, 1);
+        assert_eq!(jupiter.latency_buckets, [1, 0, 0, 1, 0, 0, 0, 0]);
+        assert_eq!(jupiter.latency_sum_ms, 20_340);
+        assert_eq!(
+            metrics.provider(Provider::Dflow),
+            ProviderMetrics::default()
+        );
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn render_prometheus_emits_cumulative_histogram() {
+        let metrics = MetricsCollector::default();
+        metrics.record_quote(Provider::Titan, true, Duration::from_millis(80));
+        metrics.record_quote(Provider::Titan, true, Duration::from_millis(400));
+
+        let text = metrics.render_prometheus();
+
+        assert!(text.contains("swap_quote_total{provider=\"titan\"} 2\n"));
+        assert!(text.contains("swap_quote_total{provider=\"jupiter\"} 0\n"));
+        assert!(text.contains("swap_quote_latency_ms_bucket{provider=\"titan\",le=\"100\"} 1\n"));
+        assert!(text.contains("swap_quote_latency_ms_bucket{provider=\"titan\",le=\"500\"} 2\n"));
+        assert!(text.contains("swap_quote_latency_ms_bucket{provider=\"titan\",le=\"+Inf\"} 2\n"));
+        assert!(text.contains("swap_quote_latency_ms_sum{provider=\"titan\"} 480\n"));
+    }
+}
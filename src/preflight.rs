@@ -0,0 +1,231 @@
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{account::Account, pubkey::Pubkey};
+
+use crate::{
+    error::SwapError,
+    token::{
+        associated_token_address, is_token_program, token_account_amount, NATIVE_MINT,
+        TOKEN_PROGRAM,
+    },
+    types::QuoteResponse,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreflightReport {
+    pub input_token_account: Pubkey,
+    pub output_token_account: Pubkey,
+    pub missing_token_accounts: Vec<Pubkey>,
+    pub input_balance: u64,
+    pub required_input: u64,
+    pub insufficient_input_balance: bool,
+    pub needs_wsol_wrap: bool,
+}
+
+impl PreflightReport {
+    pub fn is_ready(&self) -> bool {
+        self.missing_token_accounts.is_empty()
+            && !self.insufficient_input_balance
+            && !self.needs_wsol_wrap
+    }
+}
+
+pub async fn preflight(
+    quote: &QuoteResponse,
+    user: &Pubkey,
+    rpc_client: &RpcClient,
+) -> Result<PreflightReport, SwapError> {
+    let accounts = rpc_client
+        .get_multiple_accounts(&[*user, quote.input_mint, quote.output_mint])
+        .await
+        .map_err(|e| SwapError::Solana(e.to_string()))?;
+    let [user_account, input_mint_account, output_mint_account]: [Option<Account>; 3] = accounts
+        .try_into()
+        .map_err(|_| SwapError::Solana("unexpected get_multiple_accounts length".to_string()))?;
+
+    let input_token_account = associated_token_address(
+        user,
+        &quote.input_mint,
+        &token_program_of(input_mint_account.as_ref()),
+    );
+    let output_token_account = associated_token_address(
+        user,
+        &quote.output_mint,
+        &token_program_of(output_mint_account.as_ref()),
+    );
+
+    let token_accounts = rpc_client
+        .get_multiple_accounts(&[input_token_account, output_token_account])
+        .await
+        .map_err(|e| SwapError::Solana(e.to_string()))?;
+    let [input_account, output_account]: [Option<Account>; 2] = token_accounts
+        .try_into()
+        .map_err(|_| SwapError::Solana("unexpected get_multiple_accounts length".to_string()))?;
+
+    Ok(assemble_report(
+        quote,
+        input_token_account,
+        output_token_account,
+        user_account.map_or(0, |a| a.lamports),
+        input_account.as_ref(),
+        output_account.as_ref(),
+    ))
+}
+
+pub(crate) fn token_program_of(mint_account: Option<&Account>) -> Pubkey {
+    mint_account
+        .map(|a| a.owner)
+        .filter(is_token_program)
+        .unwrap_or(TOKEN_PROGRAM)
+}
+
+pub(crate) fn assemble_report(
+    quote: &QuoteResponse,
+    input_token_account: Pubkey,
+    output_token_account: Pubkey,
+    user_lamports: u64,
+    input_account: Option<&Account>,
+    output_account: Option<&Account>,
+) -> PreflightReport {
+    let required_input = quote.input_amount;
+    let token_balance = input_account
+        .and_then(|a| token_account_amount(&a.data))
+        .unwrap_or(0);
+
+    let mut missing_token_accounts = Vec::new();
+    if input_account.is_none() {
+        missing_token_accounts.push(input_token_account);
+    }
+    if output_account.is_none() {
+        missing_token_accounts.push(output_token_account);
+    }
+
+    let (input_balance, needs_wsol_wrap) = if quote.input_mint == NATIVE_MINT {
+        (
+            token_balance.saturating_add(user_lamports),
+            token_balance < required_input,
+        )
+    } else {
+        (token_balance, false)
+    };
+
+    PreflightReport {
+        input_token_account,
+        output_token_account,
+        missing_token_accounts,
+        input_balance,
+        required_input,
+        insufficient_input_balance: input_balance < required_input,
+        needs_wsol_wrap,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::types::{Provider, QuoteMeta};
+
+    fn make_quote(input_mint: Pubkey, input_amount: u64) -> QuoteResponse {
+        QuoteResponse {
+            provider: Provider::Jupiter,
+            input_mint,
+            output_mint: Pubkey::new_unique(),
+            input_amount,
+            output_amount: 1,
+            price_impact_bps: None,
+            slippage_bps: 50,
+            slippage_adjusted: false,
+            provider_data: serde_json::Value::Null,
+            meta: QuoteMeta::default(),
+            swap_id: Uuid::new_v4(),
+        }
+    }
+
+    fn token_account(amount: u64) -> Account {
+        let mut data = vec![0u8; 165];
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+        Account {
+            lamports: 2_039_280,
+            data,
+            owner: TOKEN_PROGRAM,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn report_flags_missing_output_account() {
+        let quote = make_quote(Pubkey::new_unique(), 1_000);
+        let input_ata = Pubkey::new_unique();
+        let output_ata = Pubkey::new_unique();
+        let input = token_account(5_000);
+
+        let report = assemble_report(&quote, input_ata, output_ata, 0, Some(&input), None);
+
+        assert_eq!(report.missing_token_accounts, vec![output_ata]);
+        assert_eq!(report.input_balance, 5_000);
+        assert!(!report.insufficient_input_balance);
+        assert!(!report.needs_wsol_wrap);
+        assert!(!report.is_ready());
+    }
+
+    #[test]
+    fn report_flags_insufficient_token_balance() {
+        let quote = make_quote(Pubkey::new_unique(), 1_000);
+        let input = token_account(999);
+        let output = token_account(0);
+
+        let report = assemble_report(
+            &quote,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            10_000_000,
+            Some(&input),
+            Some(&output),
+        );
+
+        assert!(report.missing_token_accounts.is_empty());
+        assert!(report.insufficient_input_balance);
+        assert!(!report.needs_wsol_wrap);
+    }
+
+    #[test]
+    fn report_native_input_counts_lamports_and_needs_wrap() {
+        let quote = make_quote(NATIVE_MINT, 1_000_000);
+        let wsol_ata = Pubkey::new_unique();
+        let output = token_account(0);
+
+        let report = assemble_report(
+            &quote,
+            wsol_ata,
+            Pubkey::new_unique(),
+            5_000_000,
+            None,
+            Some(&output),
+        );
+
+        assert_eq!(report.missing_token_accounts, vec![wsol_ata]);
+        assert_eq!(report.input_balance, 5_000_000);
+        assert!(!report.insufficient_input_balance);
+        assert!(report.needs_wsol_wrap);
+    }
+
+    #[test]
+    fn report_ready_when_everything_present() {
+        let quote = make_quote(Pubkey::new_unique(), 1_000);
+        let input = token_account(1_000);
+        let output = token_account(0);
+
+        let report = assemble_report(
+            &quote,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            Some(&input),
+            Some(&output),
+        );
+
+        assert!(report.is_ready());
+    }
+}
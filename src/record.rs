@@ -0,0 +1,62 @@
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{error::SwapError, types::Provider};
+
+// One provider round trip as written under `record_dir`. Headers are never captured, so API
+// keys stay out of the files; error responses are not recorded since `SwapError::Api` carries them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recording {
+    pub provider: String,
+    pub endpoint: String,
+    pub request: serde_json::Value,
+    pub response: serde_json::Value,
+}
+
+// A failed write is logged rather than returned: recording must never fail the swap.
+pub(crate) async fn record(
+    dir: &Path,
+    provider: Provider,
+    endpoint: &str,
+    request: serde_json::Value,
+    response: &serde_json::Value,
+) {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis());
+    let name = format!(
+        "{millis}-{}-{endpoint}-{}.json",
+        provider.to_string().to_lowercase(),
+        Uuid::new_v4().simple()
+    );
+    let recording = Recording {
+        provider: provider.to_string(),
+        endpoint: endpoint.to_string(),
+        request,
+        response: response.clone(),
+    };
+
+    let result = match serde_json::to_vec_pretty(&recording) {
+        Ok(bytes) => tokio::fs::write(dir.join(&name), bytes).await,
+        Err(e) => Err(e.into()),
+    };
+    if let Err(e) = result {
+        warn!(
+            "failed to record {provider} {endpoint} to {}: {e}",
+            dir.display()
+        );
+    }
+}
+
+pub fn load(path: &Path) -> Result<Recording, SwapError> {
+    let bytes = std::fs::read(path).map_err(|e| {
+        SwapError::InvalidRequest(format!("cannot read recording {}: {e}", path.display()))
+    })?;
+    serde_json::from_slice(&bytes).map_err(|e| SwapError::Serialization(e.to_string()))
+}
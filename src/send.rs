@@ -0,0 +1,323 @@
+use solana_client::{
+    client_error::ClientError,
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::RpcContextConfig,
+    rpc_request::RpcRequest,
+    rpc_response::{Response, RpcBlockhash},
+};
+use solana_sdk::{
+    hash::Hash,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    transaction::{TransactionError, VersionedTransaction},
+};
+use tracing::debug;
+
+use crate::{
+    cache::BlockhashCache,
+    error::SwapError,
+    types::{Provider, SwapResult},
+};
+
+// Signs `result` against a fresh blockhash and sends it. A blockhash that expired between fetch
+// and send is refreshed and the transaction rebuilt and re-signed, up to `max_refreshes` times.
+// With `min_context_slot`, a node that has not caught up to that slot fails the blockhash fetch.
+// A `blockhash_cache` hit skips the fetch; an expired blockhash is evicted before refreshing.
+// A transaction identical to one already sent under the cached blockhash gets a fresh one, and
+// fails with `InvalidRequest` if the cluster has not moved on yet, rather than being dropped.
+pub async fn send_with_blockhash_refresh(
+    result: &SwapResult,
+    signer: &Keypair,
+    rpc_client: &RpcClient,
+    max_refreshes: u32,
+    min_context_slot: Option<u64>,
+    blockhash_cache: Option<&BlockhashCache>,
+) -> Result<Signature, SwapError> {
+    let payer = signer.pubkey();
+    let mut refreshes = 0;
+    let mut use_cache = true;
+
+    loop {
+        let cached = blockhash_cache
+            .filter(|_| use_cache)
+            .and_then(BlockhashCache::get);
+        let blockhash = if let Some(blockhash) = cached {
+            blockhash
+        } else {
+            let blockhash = latest_blockhash(rpc_client, min_context_slot).await?;
+            if let Some(cache) = blockhash_cache {
+                cache.insert(blockhash);
+            }
+            blockhash
+        };
+        let unsigned = result
+            .clone()
+            .into_unsigned_transaction(&payer, blockhash)?;
+        let signed = VersionedTransaction::try_new(unsigned.message, &[signer])
+            .map_err(|e| SwapError::Solana(e.to_string()))?;
+
+        if let Some(cache) = blockhash_cache {
+            let duplicate = signed
+                .signatures
+                .first()
+                .is_some_and(|signature| !cache.mark_sent(&blockhash, *signature));
+            if duplicate && cached.is_some() {
+                debug!(swap_id = %result.swap_id(), "identical transaction already sent, refetching blockhash");
+                use_cache = false;
+                continue;
+            }
+            if duplicate {
+                return Err(SwapError::InvalidRequest(format!(
+                    "an identical transaction was already sent with blockhash {blockhash}"
+                )));
+            }
+        }
+
+        match rpc_client.send_transaction(&signed).await {
+            Ok(signature) => return Ok(signature),
+            Err(e) if is_blockhash_expired(&e) && refreshes < max_refreshes => {
+                refreshes += 1;
+                if let Some(cache) = blockhash_cache {
+                    cache.clear();
+                }
+                debug!(
+                    swap_id = %result.swap_id(),
+                    "blockhash expired, refreshing ({refreshes}/{max_refreshes})"
+                );
+            }
+            Err(e) => return Err(SwapError::Solana(e.to_string())),
+        }
+    }
+}
+
+async fn latest_blockhash(
+    rpc_client: &RpcClient,
+    min_context_slot: Option<u64>,
+) -> Result<Hash, SwapError> {
+    let config = RpcContextConfig {
+        commitment: Some(rpc_client.commitment()),
+        min_context_slot,
+    };
+    let response: Response<RpcBlockhash> = rpc_client
+        .send(RpcRequest::GetLatestBlockhash, serde_json::json!([config]))
+        .await
+        .map_err(|e| SwapError::from_rpc(&e, min_context_slot))?;
+    response
+        .value
+        .blockhash
+        .parse()
+        .map_err(|e| SwapError::Solana(format!("invalid blockhash: {e}")))
+}
+
+// Only Jupiter's program error code for slippage (custom error 6001, 0x1771) is recognised; the
+// same custom code from another provider's program means something else.
+pub(crate) fn is_slippage_exceeded(provider: Provider, err: &SwapError) -> bool {
+    let SwapError::Solana(message) = err else {
+        return false;
+    };
+    provider == Provider::Jupiter && message.contains("custom program error: 0x1771")
+}
+
+fn is_blockhash_expired(err: &ClientError) -> bool {
+    if err.get_transaction_error() == Some(TransactionError::BlockhashNotFound) {
+        return true;
+    }
+    let message = err.to_string();
+    message.contains("Blockhash not found") || message.contains("block height exceeded")
+}
+
+#[cfg(test)]
+#[expect(clippy::expect_used, reason = "test code")]
+mod tests {
+    use std::time::Duration;
+
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+    use solana_sdk::{hash::Hash, instruction::Instruction, pubkey::Pubkey};
+    use uuid::Uuid;
+    use wiremock::{
+        matchers::{body_partial_json, method},
+        Mock, MockServer, Request, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::types::ComputeBudget;
+
+    fn rpc_result(result: serde_json::Value) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": result,
+        }))
+    }
+
+    // Echoes the submitted transaction's signature, as a real node does.
+    fn echo_signature(request: &Request) -> ResponseTemplate {
+        let body: serde_json::Value = serde_json::from_slice(&request.body).expect("json-rpc body");
+        let encoded = body["params"][0].as_str().expect("encoded transaction");
+        let bytes = BASE64.decode(encoded).expect("base64 transaction");
+        let tx: VersionedTransaction = bincode::deserialize(&bytes).expect("transaction");
+        rpc_result(serde_json::json!(tx.signatures[0].to_string()))
+    }
+
+    async fn mount_blockhash(server: &MockServer, expected_calls: u64) {
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"method": "getLatestBlockhash"}),
+            ))
+            .respond_with(rpc_result(serde_json::json!({
+                "context": {"slot": 1},
+                "value": {
+                    "blockhash": Hash::new_unique().to_string(),
+                    "lastValidBlockHeight": 100,
+                },
+            })))
+            .expect(expected_calls)
+            .mount(server)
+            .await;
+    }
+
+    fn swap_result() -> SwapResult {
+        SwapResult::Instructions {
+            instructions: vec![Instruction {
+                program_id: Pubkey::new_unique(),
+                accounts: vec![],
+                data: vec![1],
+            }],
+            address_lookup_tables: vec![],
+            compute_budget: ComputeBudget::default(),
+            phases: None,
+            swap_id: Uuid::new_v4(),
+            source_quote: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn expired_blockhash_is_refreshed_once() {
+        let server = MockServer::start().await;
+        mount_blockhash(&server, 2).await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"method": "sendTransaction"}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "error": {
+                    "code": -32002,
+                    "message": "Transaction simulation failed: Blockhash not found",
+                },
+            })))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"method": "sendTransaction"}),
+            ))
+            .respond_with(echo_signature)
+            .mount(&server)
+            .await;
+
+        // A long-lived cache entry must not survive the expiry, or the refresh would reuse it.
+        let cache = BlockhashCache::new(Duration::from_secs(30));
+        let rpc = RpcClient::new(server.uri());
+        let signature = send_with_blockhash_refresh(
+            &swap_result(),
+            &Keypair::new(),
+            &rpc,
+            3,
+            None,
+            Some(&cache),
+        )
+        .await
+        .expect("sent after refresh");
+
+        assert_ne!(signature, Signature::default());
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn refreshes_stop_at_configured_limit() {
+        let server = MockServer::start().await;
+        mount_blockhash(&server, 2).await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"method": "sendTransaction"}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "error": {
+                    "code": -32002,
+                    "message": "Transaction simulation failed: Blockhash not found",
+                },
+            })))
+            .mount(&server)
+            .await;
+
+        let rpc = RpcClient::new(server.uri());
+        let err = send_with_blockhash_refresh(&swap_result(), &Keypair::new(), &rpc, 1, None, None)
+            .await
+            .expect_err("blockhash never valid");
+
+        assert!(matches!(err, SwapError::Solana(_)));
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn blockhash_fetch_forwards_min_context_slot() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"method": "getLatestBlockhash"}),
+            ))
+            .and(|request: &Request| {
+                let body: serde_json::Value =
+                    serde_json::from_slice(&request.body).expect("json-rpc body");
+                body["params"][0]["minContextSlot"] == 500
+            })
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "error": {
+                    "code": -32016,
+                    "message": "Minimum context slot has not been reached",
+                    "data": {"contextSlot": 400},
+                },
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let rpc = RpcClient::new(server.uri());
+        let err =
+            send_with_blockhash_refresh(&swap_result(), &Keypair::new(), &rpc, 1, Some(500), None)
+                .await
+                .expect_err("node behind min context slot");
+
+        assert!(
+            matches!(
+                err,
+                SwapError::MinContextSlotNotReached {
+                    min_context_slot: 500
+                }
+            ),
+            "got {err:?}"
+        );
+        server.verify().await;
+    }
+
+    #[test]
+    fn slippage_is_recognised_by_jupiter_error_code_only() {
+        let jupiter = SwapError::Solana(
+            "Error processing Instruction 2: custom program error: 0x1771".to_string(),
+        );
+        assert!(is_slippage_exceeded(Provider::Jupiter, &jupiter));
+        assert!(!is_slippage_exceeded(Provider::Titan, &jupiter));
+        assert!(!is_slippage_exceeded(
+            Provider::Jupiter,
+            &SwapError::Solana("account has no slippage buffer".to_string())
+        ));
+    }
+}
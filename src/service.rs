@@ -0,0 +1,209 @@
+use std::{future::Future, pin::Pin};
+
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    aggregator::SwapAggregator,
+    error::SwapError,
+    types::{Provider, QuoteRequest, QuoteResponse, SwapResult},
+};
+
+pub type ServiceFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+// Object-safe mirror of the aggregator's async API so applications can hold an
+// `Arc<dyn SwapService>` and substitute a stub in tests.
+pub trait SwapService: Send + Sync {
+    fn quote<'a>(
+        &'a self,
+        provider: Provider,
+        request: &'a QuoteRequest,
+    ) -> ServiceFuture<'a, Result<QuoteResponse, SwapError>>;
+
+    fn quote_all<'a>(
+        &'a self,
+        request: &'a QuoteRequest,
+    ) -> ServiceFuture<'a, Vec<Result<QuoteResponse, SwapError>>>;
+
+    fn best_quote<'a>(
+        &'a self,
+        request: &'a QuoteRequest,
+    ) -> ServiceFuture<'a, Result<QuoteResponse, SwapError>>;
+
+    fn swap<'a>(
+        &'a self,
+        quote: &'a QuoteResponse,
+        user_pubkey: &'a Pubkey,
+        rpc_client: &'a RpcClient,
+    ) -> ServiceFuture<'a, Result<SwapResult, SwapError>>;
+}
+
+impl SwapService for SwapAggregator {
+    fn quote<'a>(
+        &'a self,
+        provider: Provider,
+        request: &'a QuoteRequest,
+    ) -> ServiceFuture<'a, Result<QuoteResponse, SwapError>> {
+        Box::pin(SwapAggregator::quote(self, provider, request))
+    }
+
+    fn quote_all<'a>(
+        &'a self,
+        request: &'a QuoteRequest,
+    ) -> ServiceFuture<'a, Vec<Result<QuoteResponse, SwapError>>> {
+        Box::pin(SwapAggregator::quote_all(self, request))
+    }
+
+    fn best_quote<'a>(
+        &'a self,
+        request: &'a QuoteRequest,
+    ) -> ServiceFuture<'a, Result<QuoteResponse, SwapError>> {
+        Box::pin(SwapAggregator::best_quote(self, request))
+    }
+
+    fn swap<'a>(
+        &'a self,
+        quote: &'a QuoteResponse,
+        user_pubkey: &'a Pubkey,
+        rpc_client: &'a RpcClient,
+    ) -> ServiceFuture<'a, Result<SwapResult, SwapError>> {
+        Box::pin(SwapAggregator::swap(self, quote, user_pubkey, rpc_client))
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::expect_used, reason = "test code")]
+mod tests {
+    use std::sync::Arc;
+
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::types::{ComputeBudget, QuoteMeta};
+
+    struct StubService {
+        output_amount: u64,
+    }
+
+    impl StubService {
+        fn make_quote(&self, provider: Provider, request: &QuoteRequest) -> QuoteResponse {
+            QuoteResponse {
+                provider,
+                input_mint: request.input_mint,
+                output_mint: request.output_mint,
+                input_amount: request.amount,
+                output_amount: self.output_amount,
+                price_impact_bps: None,
+                slippage_bps: request.slippage_bps.unwrap_or(50),
+                slippage_adjusted: false,
+                provider_data: serde_json::Value::Null,
+                meta: QuoteMeta::default(),
+                swap_id: Uuid::new_v4(),
+            }
+        }
+    }
+
+    impl SwapService for StubService {
+        fn quote<'a>(
+            &'a self,
+            provider: Provider,
+            request: &'a QuoteRequest,
+        ) -> ServiceFuture<'a, Result<QuoteResponse, SwapError>> {
+            Box::pin(async move { Ok(self.make_quote(provider, request)) })
+        }
+
+        fn quote_all<'a>(
+            &'a self,
+            request: &'a QuoteRequest,
+        ) -> ServiceFuture<'a, Vec<Result<QuoteResponse, SwapError>>> {
+            Box::pin(async move {
+                Provider::ALL
+                    .iter()
+                    .map(|p| Ok(self.make_quote(*p, request)))
+                    .collect()
+            })
+        }
+
+        fn best_quote<'a>(
+            &'a self,
+            request: &'a QuoteRequest,
+        ) -> ServiceFuture<'a, Result<QuoteResponse, SwapError>> {
+            Box::pin(async move { Ok(self.make_quote(Provider::Dflow, request)) })
+        }
+
+        fn swap<'a>(
+            &'a self,
+            quote: &'a QuoteResponse,
+            _user_pubkey: &'a Pubkey,
+            _rpc_client: &'a RpcClient,
+        ) -> ServiceFuture<'a, Result<SwapResult, SwapError>> {
+            Box::pin(async move {
+                Ok(SwapResult::Instructions {
+                    instructions: vec![],
+                    address_lookup_tables: vec![],
+                    compute_budget: ComputeBudget::default(),
+                    phases: None,
+                    swap_id: quote.swap_id,
+                    source_quote: None,
+                })
+            })
+        }
+    }
+
+    fn make_request() -> QuoteRequest {
+        QuoteRequest {
+            input_mint: Pubkey::new_unique(),
+            output_mint: Pubkey::new_unique(),
+            amount: 1_000,
+            slippage_bps: Some(30),
+            only_direct_routes: None,
+            taker: None,
+            restrict_intermediate_tokens: None,
+            as_legacy_transaction: None,
+            swap_mode: None,
+            dexes: None,
+            exclude_dexes: None,
+            input_is_fee_on_transfer: false,
+            fee_account: None,
+            platform_fee_bps: None,
+            input_token_program: None,
+            output_token_program: None,
+            provider_params: None,
+            reference_price: None,
+            providers: None,
+            preferred_intermediate_mints: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn stub_is_usable_through_trait_object() {
+        let service: Arc<dyn SwapService> = Arc::new(StubService { output_amount: 42 });
+        let request = make_request();
+        let rpc_client = RpcClient::new("http://127.0.0.1:1".to_string());
+
+        let quote = service
+            .quote(Provider::Jupiter, &request)
+            .await
+            .expect("stub quote");
+        assert_eq!(quote.provider, Provider::Jupiter);
+        assert_eq!(quote.output_amount, 42);
+        assert_eq!(quote.slippage_bps, 30);
+
+        let all = service.quote_all(&request).await;
+        assert_eq!(all.len(), Provider::ALL.len());
+
+        let best = service.best_quote(&request).await.expect("stub best");
+        assert_eq!(best.provider, Provider::Dflow);
+
+        let result = service
+            .swap(&quote, &Pubkey::new_unique(), &rpc_client)
+            .await
+            .expect("stub swap");
+        assert!(matches!(result, SwapResult::Instructions { .. }));
+    }
+
+    #[test]
+    fn aggregator_coerces_to_trait_object() {
+        let _: fn(SwapAggregator) -> Arc<dyn SwapService> = |a| Arc::new(a);
+    }
+}
@@ -0,0 +1,113 @@
+use std::path::Path;
+
+use solana_sdk::{bs58, signature::Keypair};
+
+use crate::error::SwapError;
+
+// Reads the `solana-keygen` format: a JSON array of the 64 secret+public key bytes.
+pub fn load_keypair_from_file(path: impl AsRef<Path>) -> Result<Keypair, SwapError> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        SwapError::InvalidKeypair(format!("failed to read {}: {e}", path.display()))
+    })?;
+    let bytes: Vec<u8> = serde_json::from_str(&contents).map_err(|e| {
+        SwapError::InvalidKeypair(format!("{} is not a JSON byte array: {e}", path.display()))
+    })?;
+    keypair_from_bytes(&bytes)
+}
+
+pub fn keypair_from_base58(encoded: &str) -> Result<Keypair, SwapError> {
+    let bytes = bs58::decode(encoded.trim())
+        .into_vec()
+        .map_err(|e| SwapError::InvalidKeypair(format!("invalid base58: {e}")))?;
+    keypair_from_bytes(&bytes)
+}
+
+pub fn keypair_from_bytes(bytes: &[u8]) -> Result<Keypair, SwapError> {
+    if bytes.len() != 64 {
+        return Err(SwapError::InvalidKeypair(format!(
+            "expected 64 bytes, got {}",
+            bytes.len()
+        )));
+    }
+    Keypair::try_from(bytes).map_err(|e| SwapError::InvalidKeypair(e.to_string()))
+}
+
+#[cfg(test)]
+#[expect(clippy::expect_used, reason = "test code")]
+mod tests {
+    use solana_sdk::signer::Signer;
+
+    use super::*;
+
+    #[test]
+    fn keypair_from_bytes_round_trips() {
+        let keypair = Keypair::new();
+
+        let loaded = keypair_from_bytes(&keypair.to_bytes()).expect("valid bytes");
+
+        assert_eq!(loaded.pubkey(), keypair.pubkey());
+    }
+
+    #[test]
+    fn keypair_from_bytes_rejects_wrong_length() {
+        let err = keypair_from_bytes(&[1, 2, 3]).expect_err("too short");
+
+        assert!(
+            err.to_string().contains("expected 64 bytes, got 3"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn keypair_from_bytes_rejects_mismatched_public_key() {
+        let mut bytes = Keypair::new().to_bytes();
+        bytes[32..].copy_from_slice(&Keypair::new().pubkey().to_bytes());
+
+        let err = keypair_from_bytes(&bytes).expect_err("public half does not match");
+
+        assert!(matches!(err, SwapError::InvalidKeypair(_)));
+    }
+
+    #[test]
+    fn keypair_from_base58_round_trips() {
+        let keypair = Keypair::new();
+
+        let loaded = keypair_from_base58(&keypair.to_base58_string()).expect("valid base58");
+
+        assert_eq!(loaded.pubkey(), keypair.pubkey());
+    }
+
+    #[test]
+    fn keypair_from_base58_rejects_invalid_characters() {
+        let err = keypair_from_base58("not-base58-0OIl").expect_err("invalid alphabet");
+
+        assert!(err.to_string().contains("invalid base58"), "{err}");
+    }
+
+    #[test]
+    fn load_keypair_from_file_reads_json_array() {
+        let keypair = Keypair::new();
+        let path = std::env::temp_dir().join(format!("{}.json", keypair.pubkey()));
+        let json = serde_json::to_string(&keypair.to_bytes().to_vec()).expect("serialize");
+        std::fs::write(&path, json).expect("write keypair");
+
+        let loaded = load_keypair_from_file(&path);
+        std::fs::remove_file(&path).expect("cleanup");
+
+        assert_eq!(loaded.expect("valid file").pubkey(), keypair.pubkey());
+    }
+
+    #[test]
+    fn load_keypair_from_file_reports_missing_and_malformed_files() {
+        let missing = std::env::temp_dir().join(format!("{}.json", Keypair::new().pubkey()));
+        let err = load_keypair_from_file(&missing).expect_err("missing file");
+        assert!(err.to_string().contains("failed to read"), "{err}");
+
+        let path = std::env::temp_dir().join(format!("{}.json", Keypair::new().pubkey()));
+        std::fs::write(&path, "not json").expect("write file");
+        let err = load_keypair_from_file(&path).expect_err("malformed file");
+        std::fs::remove_file(&path).expect("cleanup");
+        assert!(err.to_string().contains("not a JSON byte array"), "{err}");
+    }
+}
@@ -0,0 +1,286 @@
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig},
+};
+use solana_sdk::{hash::Hash, pubkey::Pubkey};
+
+use crate::{
+    error::SwapError,
+    preflight::token_program_of,
+    token::{associated_token_address, token_account_amount},
+    types::{QuoteResponse, SwapResult},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimSummary {
+    pub output_delta: Option<u64>,
+    pub consumed_cu: Option<u64>,
+    pub logs: Vec<String>,
+}
+
+// The node swaps in its own blockhash and skips signature checks, so the swap can be simulated
+// without a signer or a blockhash round trip. The user's output ATA is read before and returned
+// after simulation; a delivered amount below the quote's `min_received` fails the simulation.
+pub async fn simulate(
+    result: &SwapResult,
+    quote: &QuoteResponse,
+    user: &Pubkey,
+    rpc_client: &RpcClient,
+) -> Result<SimSummary, SwapError> {
+    let mint_account = rpc_client
+        .get_multiple_accounts(&[quote.output_mint])
+        .await
+        .map_err(|e| SwapError::Solana(e.to_string()))?
+        .pop()
+        .flatten();
+    let output_account = associated_token_address(
+        user,
+        &quote.output_mint,
+        &token_program_of(mint_account.as_ref()),
+    );
+    let pre_balance = rpc_client
+        .get_multiple_accounts(&[output_account])
+        .await
+        .map_err(|e| SwapError::Solana(e.to_string()))?
+        .pop()
+        .flatten()
+        .and_then(|account| token_account_amount(&account.data))
+        .unwrap_or(0);
+
+    let transaction = result
+        .clone()
+        .into_unsigned_transaction(user, Hash::default())?;
+    let config = RpcSimulateTransactionConfig {
+        sig_verify: false,
+        replace_recent_blockhash: true,
+        commitment: Some(rpc_client.commitment()),
+        accounts: Some(RpcSimulateTransactionAccountsConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            addresses: vec![output_account.to_string()],
+        }),
+        ..RpcSimulateTransactionConfig::default()
+    };
+    let response = rpc_client
+        .simulate_transaction_with_config(&transaction, config)
+        .await
+        .map_err(|e| SwapError::Solana(e.to_string()))?;
+
+    let value = response.value;
+    let logs = value.logs.unwrap_or_default();
+    if let Some(err) = value.err {
+        return Err(SwapError::SimulationFailed {
+            logs,
+            err: err.to_string(),
+        });
+    }
+
+    let post_balance = value
+        .accounts
+        .and_then(|accounts| accounts.into_iter().next().flatten())
+        .and_then(|account| account.data.decode())
+        .and_then(|data| token_account_amount(&data));
+    let output_delta = output_delta(pre_balance, post_balance);
+    if let Some(delta) = output_delta {
+        let min_received = quote.min_received();
+        if delta < min_received {
+            return Err(SwapError::SimulatedOutputBelowMinimum {
+                output_delta: delta,
+                min_received,
+            });
+        }
+    }
+
+    Ok(SimSummary {
+        output_delta,
+        consumed_cu: value.units_consumed,
+        logs,
+    })
+}
+
+// `None` when the output account is gone after the swap (e.g. wSOL closed on unwrap), since the
+// delivered amount then cannot be read from it.
+fn output_delta(pre_balance: u64, post_balance: Option<u64>) -> Option<u64> {
+    post_balance.map(|post| post.saturating_sub(pre_balance))
+}
+
+#[cfg(test)]
+#[expect(clippy::expect_used, reason = "test code")]
+mod tests {
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+    use solana_sdk::instruction::Instruction;
+    use uuid::Uuid;
+    use wiremock::{
+        matchers::{body_partial_json, method},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{
+        token::TOKEN_PROGRAM,
+        types::{ComputeBudget, Provider, QuoteMeta},
+    };
+
+    fn swap_result() -> SwapResult {
+        SwapResult::Instructions {
+            instructions: vec![Instruction {
+                program_id: Pubkey::new_unique(),
+                accounts: vec![],
+                data: vec![1],
+            }],
+            address_lookup_tables: vec![],
+            compute_budget: ComputeBudget::default(),
+            phases: None,
+            swap_id: Uuid::new_v4(),
+            source_quote: None,
+        }
+    }
+
+    fn quote() -> QuoteResponse {
+        QuoteResponse {
+            provider: Provider::Jupiter,
+            input_mint: Pubkey::new_unique(),
+            output_mint: Pubkey::new_unique(),
+            input_amount: 1_000,
+            output_amount: 900,
+            price_impact_bps: None,
+            slippage_bps: 50,
+            slippage_adjusted: false,
+            provider_data: serde_json::json!({"otherAmountThreshold": "895"}),
+            meta: QuoteMeta::default(),
+            swap_id: Uuid::new_v4(),
+        }
+    }
+
+    fn token_account(amount: u64) -> serde_json::Value {
+        let mut data = vec![0u8; 165];
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+        serde_json::json!({
+            "data": [BASE64.encode(data), "base64"],
+            "executable": false,
+            "lamports": 2_039_280,
+            "owner": TOKEN_PROGRAM.to_string(),
+            "rentEpoch": 0,
+            "space": 165,
+        })
+    }
+
+    fn rpc_result(result: serde_json::Value) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": result,
+        }))
+    }
+
+    // Mint and output ATA lookups find nothing, so the pre-swap balance is zero.
+    async fn mount_rpc(server: &MockServer, simulation: serde_json::Value) {
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"method": "getMultipleAccounts"}),
+            ))
+            .respond_with(rpc_result(
+                serde_json::json!({"context": {"slot": 1}, "value": [null]}),
+            ))
+            .expect(2)
+            .mount(server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"method": "simulateTransaction"}),
+            ))
+            .respond_with(rpc_result(
+                serde_json::json!({"context": {"slot": 1}, "value": simulation}),
+            ))
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn simulate_reports_output_delta_consumed_units_and_logs() {
+        let server = MockServer::start().await;
+        mount_rpc(
+            &server,
+            serde_json::json!({
+                "err": null,
+                "logs": ["Program log: swapped"],
+                "unitsConsumed": 48_000,
+                "accounts": [token_account(900)],
+            }),
+        )
+        .await;
+        let rpc = RpcClient::new(server.uri());
+
+        let summary = simulate(&swap_result(), &quote(), &Pubkey::new_unique(), &rpc)
+            .await
+            .expect("simulation succeeds");
+
+        assert_eq!(summary.output_delta, Some(900));
+        assert_eq!(summary.consumed_cu, Some(48_000));
+        assert_eq!(summary.logs, vec!["Program log: swapped".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn simulate_rejects_output_below_min_received() {
+        let server = MockServer::start().await;
+        mount_rpc(
+            &server,
+            serde_json::json!({
+                "err": null,
+                "logs": [],
+                "unitsConsumed": 48_000,
+                "accounts": [token_account(500)],
+            }),
+        )
+        .await;
+        let rpc = RpcClient::new(server.uri());
+
+        let err = simulate(&swap_result(), &quote(), &Pubkey::new_unique(), &rpc)
+            .await
+            .expect_err("delta below minimum");
+
+        assert!(
+            matches!(
+                err,
+                SwapError::SimulatedOutputBelowMinimum {
+                    output_delta: 500,
+                    min_received: 895
+                }
+            ),
+            "got {err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn simulate_surfaces_transaction_error() {
+        let server = MockServer::start().await;
+        mount_rpc(
+            &server,
+            serde_json::json!({
+                "err": {"InstructionError": [0, {"Custom": 6001}]},
+                "logs": ["Program log: slippage"],
+                "unitsConsumed": 30_000,
+            }),
+        )
+        .await;
+        let rpc = RpcClient::new(server.uri());
+
+        let err = simulate(&swap_result(), &quote(), &Pubkey::new_unique(), &rpc)
+            .await
+            .expect_err("simulation fails");
+
+        assert!(
+            matches!(&err, SwapError::SimulationFailed { logs, .. } if logs.len() == 1),
+            "got {err:?}"
+        );
+    }
+
+    #[test]
+    fn output_delta_subtracts_pre_balance() {
+        assert_eq!(output_delta(100, Some(1_100)), Some(1_000));
+        assert_eq!(output_delta(0, Some(0)), Some(0));
+        assert_eq!(output_delta(500, Some(400)), Some(0));
+        assert_eq!(output_delta(500, None), None);
+    }
+}
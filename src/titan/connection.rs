@@ -0,0 +1,230 @@
+use std::{
+    future::Future,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use tokio::sync::{Mutex, OnceCell};
+use tracing::{debug, warn};
+
+// Lazily connected client shared by every call on a provider. Counts connection attempts so a
+// reconnect (an earlier attempt failed and the cell is still empty) is visible in logs and tests.
+// Connecting and closing both hold `lock`, so a close cannot miss a connection still in flight.
+pub(crate) struct ConnectionCell<T> {
+    cell: OnceCell<T>,
+    lock: Mutex<()>,
+    connects: AtomicUsize,
+    closed: AtomicBool,
+}
+
+impl<T> ConnectionCell<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            cell: OnceCell::new(),
+            lock: Mutex::new(()),
+            connects: AtomicUsize::new(0),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) fn connects(&self) -> usize {
+        self.connects.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn is_connected(&self) -> bool {
+        self.cell.initialized()
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
+
+    // Runs `close` on the connected client, if any, and marks the cell closed only once it
+    // succeeds, so a failed close can be retried. Waits for an in-flight connect to finish first.
+    pub(crate) async fn close<'a, E, F, Fut>(&'a self, close: F) -> Result<(), E>
+    where
+        F: FnOnce(&'a T) -> Fut,
+        Fut: Future<Output = Result<(), E>>,
+    {
+        let _guard = self.lock.lock().await;
+        if self.is_closed() {
+            return Ok(());
+        }
+        if let Some(client) = self.cell.get() {
+            close(client).await?;
+        }
+        self.closed.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    // `closed` builds the error returned once the cell has been closed; it is checked under the
+    // same lock as `close`, so no connection is opened after a close returns.
+    pub(crate) async fn get_or_connect<E, F, Fut>(
+        &self,
+        connect: F,
+        closed: impl FnOnce() -> E,
+    ) -> Result<&T, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        if let Some(client) = self.cell.get() {
+            if !self.is_closed() {
+                return Ok(client);
+            }
+        }
+        let _guard = self.lock.lock().await;
+        if self.is_closed() {
+            return Err(closed());
+        }
+        self.cell
+            .get_or_try_init(|| async {
+                let attempt = self.connects.fetch_add(1, Ordering::Relaxed) + 1;
+                if attempt > 1 {
+                    warn!("titan reconnecting, attempt {attempt}");
+                } else {
+                    debug!("titan connecting");
+                }
+                connect().await
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::expect_used, reason = "test code")]
+mod tests {
+    use super::*;
+
+    fn closed() -> String {
+        "closed".to_string()
+    }
+
+    #[tokio::test]
+    async fn connects_once_across_calls() {
+        let cell = ConnectionCell::new();
+        let factory_calls = AtomicUsize::new(0);
+        let connect = || async {
+            factory_calls.fetch_add(1, Ordering::Relaxed);
+            Ok::<_, String>(7_u32)
+        };
+
+        // quote, then swap, on the same provider instance
+        let first = *cell
+            .get_or_connect(connect, closed)
+            .await
+            .expect("quote client");
+        let second = *cell
+            .get_or_connect(connect, closed)
+            .await
+            .expect("swap client");
+
+        assert_eq!((first, second), (7, 7));
+        assert_eq!(factory_calls.load(Ordering::Relaxed), 1);
+        assert_eq!(cell.connects(), 1);
+    }
+
+    #[tokio::test]
+    async fn concurrent_callers_share_one_connection() {
+        let cell = ConnectionCell::new();
+        let connect = || async { Ok::<_, String>(1_u32) };
+
+        let (a, b) = tokio::join!(
+            cell.get_or_connect(connect, closed),
+            cell.get_or_connect(connect, closed)
+        );
+
+        assert!(a.is_ok() && b.is_ok());
+        assert_eq!(cell.connects(), 1);
+    }
+
+    #[tokio::test]
+    async fn close_runs_once_and_blocks_later_connects() {
+        let cell = ConnectionCell::new();
+        cell.get_or_connect(|| async { Ok::<_, String>(5_u32) }, closed)
+            .await
+            .expect("connected");
+        let closes = AtomicUsize::new(0);
+        let close = |client: &u32| {
+            assert_eq!(*client, 5);
+            closes.fetch_add(1, Ordering::Relaxed);
+            async { Ok::<_, String>(()) }
+        };
+
+        cell.close(close).await.expect("first close");
+        cell.close(close).await.expect("second close");
+
+        assert_eq!(closes.load(Ordering::Relaxed), 1);
+        let err = cell
+            .get_or_connect(|| async { Ok::<_, String>(6_u32) }, closed)
+            .await
+            .expect_err("closed");
+        assert_eq!(err, "closed");
+    }
+
+    #[tokio::test]
+    async fn close_waits_for_in_flight_connect() {
+        let cell = ConnectionCell::new();
+        let (release, wait) = tokio::sync::oneshot::channel::<()>();
+        let closed_client = std::sync::Mutex::new(None);
+
+        let connect = cell.get_or_connect(
+            || async {
+                wait.await.ok();
+                Ok::<_, String>(9_u32)
+            },
+            closed,
+        );
+        let close = async {
+            // Let the connect take the lock first.
+            tokio::task::yield_now().await;
+            release.send(()).ok();
+            cell.close(|client: &u32| {
+                *closed_client.lock().expect("lock") = Some(*client);
+                async { Ok::<_, String>(()) }
+            })
+            .await
+        };
+        let (connected, close) = tokio::join!(connect, close);
+
+        assert_eq!(connected.ok(), Some(&9));
+        assert!(close.is_ok());
+        assert_eq!(*closed_client.lock().expect("lock"), Some(9));
+        assert!(cell.is_closed());
+    }
+
+    #[tokio::test]
+    async fn failed_close_leaves_cell_open() {
+        let cell = ConnectionCell::new();
+        cell.get_or_connect(|| async { Ok::<_, String>(1_u32) }, closed)
+            .await
+            .expect("connected");
+
+        let err = cell
+            .close(|_: &u32| async { Err::<(), _>("socket error".to_string()) })
+            .await;
+
+        assert!(err.is_err());
+        assert!(!cell.is_closed());
+        cell.close(|_: &u32| async { Ok::<_, String>(()) })
+            .await
+            .expect("retried close");
+        assert!(cell.is_closed());
+    }
+
+    #[tokio::test]
+    async fn failed_connect_is_retried_as_reconnect() {
+        let cell = ConnectionCell::new();
+
+        let failed = cell
+            .get_or_connect(|| async { Err::<u32, _>("refused".to_string()) }, closed)
+            .await;
+        assert!(failed.is_err());
+
+        let client = cell
+            .get_or_connect(|| async { Ok::<_, String>(3_u32) }, closed)
+            .await
+            .expect("reconnected");
+        assert_eq!(*client, 3);
+        assert_eq!(cell.connects(), 2);
+    }
+}
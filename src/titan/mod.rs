@@ -1,25 +1,79 @@
+mod connection;
+mod stream;
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
-use tokio::sync::OnceCell;
-use tracing::debug;
+use tracing::{debug, warn};
+use uuid::Uuid;
 
 use titan_rust_client::{
-    types::SwapPriceRequest,
-    TitanClient, TitanConfig,
+    connection::Connection,
+    types::{
+        SwapMode as TitanSwapMode, SwapParams, SwapQuoteRequest, SwapQuotes, SwapRoute,
+        TransactionParams,
+    },
+    QuoteStream, StreamManager, TitanClientError, TitanConfig, TitanInstructions,
 };
 
 use crate::{
     error::SwapError,
-    types::{Provider, QuoteRequest, QuoteResponse, SwapResult},
+    lookup::fetch_address_lookup_tables,
+    types::{
+        ComputeBudget, Provider, QuoteMeta, QuoteRequest, QuoteResponse, RouteHop, SwapMode,
+        SwapResult,
+    },
 };
 
+use self::connection::ConnectionCell;
+use self::stream::latest_quotes_reconnecting;
+
 const DEFAULT_TITAN_WS_URL: &str = "wss://api.titan.ag/api/v1/ws";
 const TITAN_WS_URL_ENV: &str = "TITAN_WS_URL";
+// Titan sends a fresh set of routes every update interval; a quote keeps the newest set seen
+// within this window.
+const DEFAULT_QUOTE_WINDOW: Duration = Duration::from_secs(1);
+// `TitanClient` asks the server for its stream limit and falls back to this when it cannot.
+const MAX_CONCURRENT_STREAMS: u32 = 10;
 
 pub struct TitanProvider {
     pub ws_url: String,
     pub token: String,
-    client: OnceCell<TitanClient>,
+    pub quote_window: Duration,
+    client: ConnectionCell<TitanSession>,
+}
+
+// `TitanClient` 0.1.9 only exposes `get_swap_price`, which carries no route, so the provider
+// drives the connection and stream manager `TitanClient` is built from to open quote streams.
+struct TitanSession {
+    connection: Arc<Connection>,
+    streams: Arc<StreamManager>,
+}
+
+impl TitanSession {
+    async fn connect(ws_url: &str, token: &str) -> Result<Self, SwapError> {
+        let connection = Connection::connect(TitanConfig::new(ws_url, token))
+            .await
+            .map_err(map_titan_error)?;
+        let connection = Arc::new(connection);
+        let streams = StreamManager::new(Arc::clone(&connection), MAX_CONCURRENT_STREAMS);
+        Ok(Self {
+            connection,
+            streams,
+        })
+    }
+
+    async fn open_stream(&self, request: SwapQuoteRequest) -> Result<QuoteStream, SwapError> {
+        self.streams
+            .request_stream(request)
+            .await
+            .map_err(map_titan_error)
+    }
 }
 
 impl TitanProvider {
@@ -29,83 +83,769 @@ impl TitanProvider {
                 .or_else(|| std::env::var(TITAN_WS_URL_ENV).ok())
                 .unwrap_or_else(|| DEFAULT_TITAN_WS_URL.to_string()),
             token: token.unwrap_or_default(),
-            client: OnceCell::new(),
+            quote_window: DEFAULT_QUOTE_WINDOW,
+            client: ConnectionCell::new(),
         }
     }
 
-    async fn get_client(&self) -> Result<&TitanClient, SwapError> {
+    // Longer windows can pick up a better update; every Titan quote waits out the full window.
+    #[must_use]
+    pub fn with_quote_window(mut self, window: Duration) -> Self {
+        self.quote_window = window;
+        self
+    }
+
+    pub fn connection_count(&self) -> usize {
+        self.client.connects()
+    }
+
+    // The Titan connection does not close its socket on drop, so call this before dropping the
+    // provider. Safe to call repeatedly and when no connection was made; later calls fail. A
+    // close that fails leaves the provider open so it can be retried.
+    pub async fn close(&self) -> Result<(), SwapError> {
         self.client
-            .get_or_try_init(|| async {
-                let config = TitanConfig::new(&self.ws_url, &self.token);
-                TitanClient::new(config)
-                    .await
-                    .map_err(|e| SwapError::Titan(e.to_string()))
+            .close(|session| async move {
+                debug!("titan closing connection");
+                // Stops every open stream before the socket goes away.
+                session.connection.shutdown().await;
+                Ok(())
             })
             .await
     }
 
+    async fn get_session(&self) -> Result<&TitanSession, SwapError> {
+        self.client
+            .get_or_connect(
+                || TitanSession::connect(&self.ws_url, &self.token),
+                || SwapError::Titan("provider is closed".to_string()),
+            )
+            .await
+    }
+
     pub async fn quote(
         &self,
         request: &QuoteRequest,
         default_slippage_bps: u16,
     ) -> Result<QuoteResponse, SwapError> {
-        let client = self.get_client().await?;
+        Provider::Titan.ensure_swap_mode(request.swap_mode)?;
+        let session = self.get_session().await?;
 
         let slippage_bps = request.slippage_bps.unwrap_or(default_slippage_bps);
-
-        let dexes = request.dexes.as_ref().map(|d| {
-            d.split(',')
-                .map(|s| s.trim().to_string())
-                .collect::<Vec<_>>()
-        });
-
-        let exclude_dexes = request.exclude_dexes.as_ref().map(|d| {
-            d.split(',')
-                .map(|s| s.trim().to_string())
-                .collect::<Vec<_>>()
-        });
-
-        let price_request = SwapPriceRequest {
-            input_mint: request.input_mint.to_bytes().into(),
-            output_mint: request.output_mint.to_bytes().into(),
-            amount: request.amount,
-            dexes,
-            exclude_dexes,
+        let stream_request = SwapQuoteRequest {
+            swap: SwapParams {
+                input_mint: request.input_mint.to_bytes().into(),
+                output_mint: request.output_mint.to_bytes().into(),
+                amount: request.amount,
+                swap_mode: Some(match request.swap_mode {
+                    Some(SwapMode::ExactOut) => TitanSwapMode::ExactOut,
+                    Some(SwapMode::ExactIn) | None => TitanSwapMode::ExactIn,
+                }),
+                slippage_bps: Some(slippage_bps),
+                dexes: split_dexes(request.dexes.as_deref()),
+                exclude_dexes: split_dexes(request.exclude_dexes.as_deref()),
+                only_direct_routes: request.only_direct_routes,
+                ..SwapParams::default()
+            },
+            // A quote is not tied to a wallet; the taker, when known, only makes the routes'
+            // instructions usable as quoted. The referral fee shapes the quoted output.
+            transaction: transaction_params(
+                &request.taker.unwrap_or_default(),
+                request.fee_account,
+                request.platform_fee_bps,
+            ),
+            update: None,
         };
 
-        debug!("titan get_swap_price (quote)");
-        let price = client
-            .get_swap_price(price_request)
-            .await
-            .map_err(|e| SwapError::Titan(e.to_string()))?;
+        // Every stream opened here is stopped before returning, so none outlives the quote.
+        debug!("titan quote stream (quote)");
+        let started = Instant::now();
+        let quotes = latest_quotes_reconnecting(
+            || session.open_stream(stream_request.clone()),
+            self.quote_window,
+        )
+        .await?;
+        let (route_id, route) = select_best_route(&quotes).ok_or(SwapError::NoRouteFound)?;
 
-        if price.amount_out == 0 {
-            return Err(SwapError::NoRouteFound);
-        }
+        ensure_nonzero_route(route)?;
 
-        let provider_data = serde_json::to_value(&price)
-            .unwrap_or_else(|_| serde_json::json!({"error": "serialization_failed"}));
+        let provider_data = quote_provider_data(request, route_id, route, &candidates_of(&quotes));
+        // The client decodes the msgpack frames internally and never exposes their size, so no
+        // byte count is reported rather than one for a re-encoding of the route.
+        let meta = QuoteMeta::measured(started, 0);
 
         Ok(QuoteResponse {
             provider: Provider::Titan,
             input_mint: request.input_mint,
             output_mint: request.output_mint,
-            input_amount: request.amount,
-            output_amount: price.amount_out,
+            input_amount: route.in_amount,
+            output_amount: route.out_amount,
             price_impact_bps: None,
             slippage_bps,
+            slippage_adjusted: false,
             provider_data,
+            meta,
+            swap_id: Uuid::new_v4(),
         })
     }
 
+    // Titan routes are built for the wallet named in the stream request, so the swap reopens the
+    // stream for `user_pubkey` with the quote's params and swaps the best route it returns.
     pub async fn swap(
         &self,
-        _quote: &QuoteResponse,
-        _user_pubkey: &Pubkey,
-        _rpc_client: &RpcClient,
+        quote: &QuoteResponse,
+        user_pubkey: &Pubkey,
+        rpc_client: &RpcClient,
     ) -> Result<SwapResult, SwapError> {
-        Err(SwapError::Titan(
-            "Titan get_swap_price does not provide swap instructions".to_string(),
-        ))
+        quote.ensure_provider(Provider::Titan, &["route"])?;
+        let session = self.get_session().await?;
+
+        let fee_account = quote.provider_data["feeAccount"]
+            .as_str()
+            .and_then(|s| s.parse().ok());
+        let fee_bps = quote.provider_data["feeBps"]
+            .as_u64()
+            .and_then(|bps| u16::try_from(bps).ok());
+        let stream_request = SwapQuoteRequest {
+            swap: swap_params(quote),
+            transaction: transaction_params(user_pubkey, fee_account, fee_bps),
+            update: None,
+        };
+
+        debug!("titan quote stream (swap)");
+        let quotes = latest_quotes_reconnecting(
+            || session.open_stream(stream_request.clone()),
+            self.quote_window,
+        )
+        .await?;
+        let (route_id, route) = select_best_route(&quotes).ok_or(SwapError::NoRouteFound)?;
+        ensure_nonzero_route(route)?;
+        // RFQ providers may answer with a prebuilt transaction only; it is signed by the
+        // provider's flow rather than built from instructions, so it is not swapped here.
+        if route.instructions.is_empty() {
+            return Err(SwapError::Titan(format!(
+                "route {route_id} has no instructions"
+            )));
+        }
+
+        let alt_addresses: Vec<Pubkey> = route
+            .address_lookup_tables
+            .iter()
+            .map(|key| (*key).into())
+            .collect();
+        let address_lookup_tables =
+            fetch_address_lookup_tables(&alt_addresses, rpc_client, None).await?;
+
+        Ok(SwapResult::Instructions {
+            instructions: TitanInstructions::convert_instructions(&route.instructions),
+            address_lookup_tables,
+            compute_budget: ComputeBudget {
+                limit: route
+                    .compute_units_safe
+                    .and_then(|units| u32::try_from(units).ok()),
+                price_micro_lamports: None,
+            },
+            phases: None,
+            swap_id: quote.swap_id,
+            source_quote: Some(quote.provider_data.clone()),
+        })
+    }
+}
+
+// Drop cannot await the close handshake, so an open connection is only reported.
+impl Drop for TitanProvider {
+    fn drop(&mut self) {
+        if self.client.is_connected() && !self.client.is_closed() {
+            warn!("titan provider dropped without close(); the WebSocket may be left open");
+        }
+    }
+}
+
+// Referral fee fields sent with every quote stream; the quote stores them so its swap stream
+// charges the same fee.
+fn transaction_params(
+    user_pubkey: &Pubkey,
+    fee_account: Option<Pubkey>,
+    fee_bps: Option<u16>,
+) -> TransactionParams {
+    TransactionParams {
+        user_public_key: user_pubkey.to_bytes().into(),
+        fee_account: fee_account.map(|pk| pk.to_bytes().into()),
+        fee_bps,
+        ..TransactionParams::default()
+    }
+}
+
+// Swap-stream params for a Titan quote, keeping the quote's swap mode rather than assuming ExactIn
+// and the DEX filters it was quoted with.
+pub fn swap_params(quote: &QuoteResponse) -> SwapParams {
+    let exact_out = quote.provider_data["swapMode"] == "ExactOut";
+    let dexes = |key: &str| serde_json::from_value(quote.provider_data[key].clone()).ok();
+    SwapParams {
+        input_mint: quote.input_mint.to_bytes().into(),
+        output_mint: quote.output_mint.to_bytes().into(),
+        amount: if exact_out {
+            quote.output_amount
+        } else {
+            quote.input_amount
+        },
+        swap_mode: Some(if exact_out {
+            TitanSwapMode::ExactOut
+        } else {
+            TitanSwapMode::ExactIn
+        }),
+        slippage_bps: Some(quote.slippage_bps),
+        dexes: dexes("dexes"),
+        exclude_dexes: dexes("excludeDexes"),
+        only_direct_routes: quote.provider_data["onlyDirectRoutes"].as_bool(),
+        ..SwapParams::default()
+    }
+}
+
+// ExactOut routes all deliver the requested output, so the best one spends the least input.
+pub fn select_best_route(quotes: &SwapQuotes) -> Option<(&str, &SwapRoute)> {
+    let routes = quotes.quotes.iter().map(|(id, route)| (id.as_str(), route));
+    match quotes.swap_mode {
+        TitanSwapMode::ExactIn => routes.max_by_key(|(_, route)| route.out_amount),
+        TitanSwapMode::ExactOut => routes.min_by_key(|(_, route)| route.in_amount),
+    }
+}
+
+// One entry of a stream update's `quotes` map, kept for analytics even though only the best
+// route is swapped.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteCandidate {
+    pub route_id: String,
+    pub in_amount: u64,
+    pub out_amount: u64,
+    // Step labels in route order, e.g. ["Whirlpool", "Raydium CLMM"].
+    pub venues: Vec<String>,
+}
+
+// Every route of the update a Titan quote was picked from, best first; empty for other quotes.
+pub fn route_candidates(quote: &QuoteResponse) -> Vec<RouteCandidate> {
+    if quote.provider != Provider::Titan {
+        return Vec::new();
+    }
+    serde_json::from_value(quote.provider_data["candidates"].clone()).unwrap_or_default()
+}
+
+// Every route of an update, best first by the same rule as `select_best_route`.
+fn candidates_of(quotes: &SwapQuotes) -> Vec<RouteCandidate> {
+    let mut candidates: Vec<RouteCandidate> = quotes
+        .quotes
+        .iter()
+        .map(|(id, route)| RouteCandidate {
+            route_id: id.clone(),
+            in_amount: route.in_amount,
+            out_amount: route.out_amount,
+            venues: route.steps.iter().map(|step| step.label.clone()).collect(),
+        })
+        .collect();
+    match quotes.swap_mode {
+        TitanSwapMode::ExactIn => candidates.sort_by_key(|c| std::cmp::Reverse(c.out_amount)),
+        TitanSwapMode::ExactOut => candidates.sort_by_key(|c| c.in_amount),
+    }
+    candidates
+}
+
+// Hops of a `SwapRoute` picked off a quote stream. Titan reports allocation in parts per
+// billion; `percent` follows Jupiter's whole-percent convention.
+pub fn route_hops(route: &SwapRoute) -> Vec<RouteHop> {
+    route
+        .steps
+        .iter()
+        .map(|step| RouteHop {
+            amm_key: Some(step.amm_key.to_string()),
+            program_id: None,
+            label: Some(step.label.clone()),
+            input_mint: Some(step.input_mint.to_string()),
+            output_mint: Some(step.output_mint.to_string()),
+            in_amount: Some(step.in_amount),
+            out_amount: Some(step.out_amount),
+            percent: u8::try_from(step.alloc_ppb / 10_000_000).ok(),
+        })
+        .collect()
+}
+
+// The quote's `provider_data`: the chosen route and every candidate of its update, plus the
+// route's hops as a Jupiter-shaped `routePlan` so `QuoteResponse::route()` (and the `max_hops`
+// guard) read Titan quotes like Jupiter ones.
+fn quote_provider_data(
+    request: &QuoteRequest,
+    route_id: &str,
+    route: &SwapRoute,
+    candidates: &[RouteCandidate],
+) -> serde_json::Value {
+    let route_plan: Vec<serde_json::Value> = route_hops(route)
+        .into_iter()
+        .map(|hop| {
+            serde_json::json!({
+                "swapInfo": {
+                    "ammKey": hop.amm_key,
+                    "label": hop.label,
+                    "inputMint": hop.input_mint,
+                    "outputMint": hop.output_mint,
+                    "inAmount": hop.in_amount.map(|a| a.to_string()),
+                    "outAmount": hop.out_amount.map(|a| a.to_string()),
+                },
+                "percent": hop.percent,
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "inputMint": request.input_mint.to_string(),
+        "outputMint": request.output_mint.to_string(),
+        "swapMode": if request.swap_mode == Some(SwapMode::ExactOut) {
+            "ExactOut"
+        } else {
+            "ExactIn"
+        },
+        "routeId": route_id,
+        "route": route,
+        "routePlan": route_plan,
+        "candidates": candidates,
+        "feeAccount": request.fee_account.map(|pk| pk.to_string()),
+        "feeBps": request.platform_fee_bps,
+        "dexes": split_dexes(request.dexes.as_deref()),
+        "excludeDexes": split_dexes(request.exclude_dexes.as_deref()),
+        "onlyDirectRoutes": request.only_direct_routes,
+        "taker": request.taker.map(|pk| pk.to_string()),
+    })
+}
+
+fn split_dexes(dexes: Option<&str>) -> Option<Vec<String>> {
+    dexes.map(|d| d.split(',').map(|s| s.trim().to_string()).collect())
+}
+
+// A zero on either side is a degenerate route rather than a quote; rejecting it here keeps it
+// from surfacing later as a zero-output swap or a divide-by-zero in price helpers.
+fn ensure_nonzero_route(route: &SwapRoute) -> Result<(), SwapError> {
+    if route.in_amount == 0 || route.out_amount == 0 {
+        return Err(SwapError::NoRouteFound);
+    }
+    Ok(())
+}
+
+fn map_titan_error(error: TitanClientError) -> SwapError {
+    match error {
+        TitanClientError::AuthenticationFailed(_) => {
+            SwapError::ProviderNotConfigured(Provider::Titan)
+        }
+        TitanClientError::ConnectionFailed { .. } => SwapError::Timeout,
+        TitanClientError::ServerError { code, .. } if code == 401 || code == 403 => {
+            SwapError::ProviderNotConfigured(Provider::Titan)
+        }
+        TitanClientError::ServerError { ref message, .. }
+            if message.to_lowercase().contains("no route") =>
+        {
+            SwapError::NoRouteFound
+        }
+        other => SwapError::Titan(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::expect_used, reason = "test code")]
+mod tests {
+    use super::*;
+
+    fn make_request() -> QuoteRequest {
+        QuoteRequest {
+            input_mint: Pubkey::new_unique(),
+            output_mint: Pubkey::new_unique(),
+            amount: 1_000,
+            slippage_bps: Some(50),
+            only_direct_routes: None,
+            taker: None,
+            restrict_intermediate_tokens: None,
+            as_legacy_transaction: None,
+            swap_mode: None,
+            dexes: None,
+            exclude_dexes: None,
+            input_is_fee_on_transfer: false,
+            fee_account: None,
+            platform_fee_bps: None,
+            input_token_program: None,
+            output_token_program: None,
+            provider_params: None,
+            reference_price: None,
+            providers: None,
+            preferred_intermediate_mints: None,
+        }
+    }
+
+    #[test]
+    fn transaction_params_carry_referral_fee() {
+        let user = Pubkey::new_unique();
+        let fee_account = Pubkey::new_unique();
+        let request = QuoteRequest {
+            fee_account: Some(fee_account),
+            platform_fee_bps: Some(25),
+            ..make_request()
+        };
+
+        let params = transaction_params(&user, request.fee_account, request.platform_fee_bps);
+
+        assert_eq!(params.user_public_key, user.to_bytes().into());
+        assert_eq!(params.fee_account, Some(fee_account.to_bytes().into()));
+        assert_eq!(params.fee_bps, Some(25));
+    }
+
+    #[test]
+    fn quote_records_referral_fee_for_the_swap() {
+        let fee_account = Pubkey::new_unique();
+        let request = QuoteRequest {
+            fee_account: Some(fee_account),
+            platform_fee_bps: Some(25),
+            ..make_request()
+        };
+
+        let data = quote_provider_data(&request, "titan", &SwapRoute::default(), &[]);
+
+        assert_eq!(data["feeAccount"], fee_account.to_string());
+        assert_eq!(data["feeBps"], 25);
+        let data = quote_provider_data(&make_request(), "titan", &SwapRoute::default(), &[]);
+        assert!(data["feeAccount"].is_null() && data["feeBps"].is_null());
+    }
+
+    #[test]
+    fn transaction_params_omit_fee_when_unset() {
+        let params = transaction_params(&Pubkey::new_unique(), None, None);
+        assert_eq!(params.fee_account, None);
+        assert_eq!(params.fee_bps, None);
+    }
+
+    fn quotes(swap_mode: TitanSwapMode) -> SwapQuotes {
+        let route = |in_amount, out_amount| SwapRoute {
+            in_amount,
+            out_amount,
+            ..SwapRoute::default()
+        };
+        SwapQuotes {
+            swap_mode,
+            quotes: [
+                ("cheap".to_string(), route(900, 1_000)),
+                ("rich".to_string(), route(1_000, 1_050)),
+                ("middle".to_string(), route(950, 1_020)),
+            ]
+            .into_iter()
+            .collect(),
+            ..SwapQuotes::default()
+        }
+    }
+
+    #[test]
+    fn select_best_route_maximizes_output_for_exact_in() {
+        let quotes = quotes(TitanSwapMode::ExactIn);
+        let (id, _) = select_best_route(&quotes).expect("a route");
+        assert_eq!(id, "rich");
+    }
+
+    #[test]
+    fn select_best_route_minimizes_input_for_exact_out() {
+        let quotes = quotes(TitanSwapMode::ExactOut);
+        let (id, _) = select_best_route(&quotes).expect("a route");
+        assert_eq!(id, "cheap");
+        assert!(select_best_route(&SwapQuotes::default()).is_none());
+    }
+
+    #[test]
+    fn route_candidates_list_every_route_best_first() {
+        let candidates = candidates_of(&quotes(TitanSwapMode::ExactIn));
+        let ids: Vec<_> = candidates.iter().map(|c| c.route_id.as_str()).collect();
+        assert_eq!(ids, ["rich", "middle", "cheap"]);
+        assert_eq!(candidates[0].in_amount, 1_000);
+        assert_eq!(candidates[0].out_amount, 1_050);
+
+        let candidates = candidates_of(&quotes(TitanSwapMode::ExactOut));
+        assert_eq!(candidates[0].route_id, "cheap");
+    }
+
+    #[test]
+    fn quote_keeps_every_candidate_of_its_update() {
+        let update = quotes(TitanSwapMode::ExactIn);
+        let (route_id, route) = select_best_route(&update).expect("a route");
+        let request = make_request();
+        let quote = QuoteResponse {
+            provider: Provider::Titan,
+            input_mint: request.input_mint,
+            output_mint: request.output_mint,
+            input_amount: route.in_amount,
+            output_amount: route.out_amount,
+            price_impact_bps: None,
+            slippage_bps: 50,
+            slippage_adjusted: false,
+            provider_data: quote_provider_data(&request, route_id, route, &candidates_of(&update)),
+            meta: QuoteMeta::default(),
+            swap_id: Uuid::new_v4(),
+        };
+
+        let amounts: Vec<_> = route_candidates(&quote)
+            .iter()
+            .map(|c| (c.route_id.clone(), c.out_amount))
+            .collect();
+        assert_eq!(
+            amounts,
+            [
+                ("rich".to_string(), 1_050),
+                ("middle".to_string(), 1_020),
+                ("cheap".to_string(), 1_000),
+            ]
+        );
+        assert_eq!(quote.provider_data["routeId"], "rich");
+        assert!(route_candidates(&QuoteResponse {
+            provider: Provider::Jupiter,
+            ..quote
+        })
+        .is_empty());
+    }
+
+    #[test]
+    fn swap_params_follow_quote_swap_mode() {
+        let quote = QuoteResponse {
+            provider: Provider::Titan,
+            input_mint: Pubkey::new_unique(),
+            output_mint: Pubkey::new_unique(),
+            input_amount: 1_010,
+            output_amount: 1_000,
+            price_impact_bps: None,
+            slippage_bps: 50,
+            slippage_adjusted: false,
+            provider_data: quote_provider_data(
+                &QuoteRequest {
+                    swap_mode: Some(SwapMode::ExactOut),
+                    dexes: Some("Whirlpool, Raydium".to_string()),
+                    only_direct_routes: Some(true),
+                    ..make_request()
+                },
+                "titan",
+                &SwapRoute::default(),
+                &[],
+            ),
+            meta: QuoteMeta::default(),
+            swap_id: Uuid::new_v4(),
+        };
+
+        let params = swap_params(&quote);
+        assert_eq!(params.swap_mode, Some(TitanSwapMode::ExactOut));
+        assert_eq!(params.amount, 1_000);
+        assert_eq!(params.slippage_bps, Some(50));
+        assert_eq!(
+            params.dexes,
+            Some(vec!["Whirlpool".to_string(), "Raydium".to_string()])
+        );
+        assert_eq!(params.exclude_dexes, None);
+        assert_eq!(params.only_direct_routes, Some(true));
+
+        let params = swap_params(&QuoteResponse {
+            provider_data: serde_json::json!({}),
+            ..quote
+        });
+        assert_eq!(params.swap_mode, Some(TitanSwapMode::ExactIn));
+        assert_eq!(params.amount, 1_010);
+    }
+
+    #[test]
+    fn route_hops_map_steps_in_order() {
+        use titan_rust_client::types::RoutePlanStep;
+
+        let sol = Pubkey::new_unique();
+        let usdc = Pubkey::new_unique();
+        let bonk = Pubkey::new_unique();
+        let step = |label: &str, input: Pubkey, output: Pubkey, alloc_ppb| RoutePlanStep {
+            amm_key: Pubkey::new_unique().to_bytes().into(),
+            label: label.to_string(),
+            input_mint: input.to_bytes().into(),
+            output_mint: output.to_bytes().into(),
+            in_amount: 1_000,
+            out_amount: 900,
+            alloc_ppb,
+            ..RoutePlanStep::default()
+        };
+        let route = SwapRoute {
+            steps: vec![
+                step("Whirlpool", sol, usdc, 1_000_000_000),
+                step("Raydium CLMM", usdc, bonk, 600_000_000),
+            ],
+            ..SwapRoute::default()
+        };
+
+        let hops = route_hops(&route);
+
+        assert_eq!(hops.len(), 2);
+        assert_eq!(hops[0].label.as_deref(), Some("Whirlpool"));
+        assert_eq!(hops[0].input_mint, Some(sol.to_string()));
+        assert_eq!(hops[0].output_mint, Some(usdc.to_string()));
+        assert_eq!(hops[0].percent, Some(100));
+        assert_eq!(hops[1].label.as_deref(), Some("Raydium CLMM"));
+        assert_eq!(hops[1].output_mint, Some(bonk.to_string()));
+        assert_eq!(hops[1].in_amount, Some(1_000));
+        assert_eq!(hops[1].out_amount, Some(900));
+        assert_eq!(hops[1].percent, Some(60));
+        assert_eq!(hops[0].amm_key, Some(route.steps[0].amm_key.to_string()));
+
+        let request = make_request();
+        let quote = QuoteResponse {
+            provider: Provider::Titan,
+            input_mint: request.input_mint,
+            output_mint: request.output_mint,
+            input_amount: 1_000,
+            output_amount: 900,
+            price_impact_bps: None,
+            slippage_bps: 50,
+            slippage_adjusted: false,
+            provider_data: quote_provider_data(&request, "titan", &route, &[]),
+            meta: QuoteMeta::default(),
+            swap_id: Uuid::new_v4(),
+        };
+        assert_eq!(quote.route(), hops);
+    }
+
+    #[tokio::test]
+    async fn close_is_idempotent_without_a_connection() {
+        let provider = TitanProvider::new(None, Some("token".to_string()));
+
+        provider.close().await.expect("first close");
+        provider.close().await.expect("second close");
+        assert_eq!(provider.connection_count(), 0);
+
+        let err = provider
+            .quote(&make_request(), 50)
+            .await
+            .expect_err("closed provider");
+        assert!(matches!(err, SwapError::Titan(ref m) if m.contains("closed")));
+        assert_eq!(provider.connection_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn swap_rejects_quote_for_another_pair() {
+        let request = make_request();
+        let quote = QuoteResponse {
+            provider: Provider::Titan,
+            input_mint: request.input_mint,
+            output_mint: request.output_mint,
+            input_amount: request.amount,
+            output_amount: 900,
+            price_impact_bps: None,
+            slippage_bps: 50,
+            slippage_adjusted: false,
+            provider_data: serde_json::json!({
+                "inputMint": request.input_mint.to_string(),
+                "outputMint": Pubkey::new_unique().to_string(),
+                "route": {},
+            }),
+            meta: QuoteMeta::default(),
+            swap_id: Uuid::new_v4(),
+        };
+        let provider = TitanProvider::new(None, Some("token".to_string()));
+        let rpc = RpcClient::new("http://127.0.0.1:1".to_string());
+
+        let err = provider
+            .swap(&quote, &Pubkey::new_unique(), &rpc)
+            .await
+            .expect_err("stored output mint differs");
+        assert!(
+            matches!(err, SwapError::InconsistentResponse(ref m) if m.contains("outputMint")),
+            "got {err:?}"
+        );
+
+        let matching = QuoteResponse {
+            provider_data: serde_json::json!({
+                "inputMint": request.input_mint.to_string(),
+                "outputMint": request.output_mint.to_string(),
+            }),
+            ..quote
+        };
+        let err = provider
+            .swap(&matching, &Pubkey::new_unique(), &rpc)
+            .await
+            .expect_err("no route recorded");
+        assert!(
+            matches!(err, SwapError::InconsistentResponse(ref m) if m.contains("route")),
+            "got {err:?}"
+        );
+
+        // A well-formed quote gets as far as opening the swap stream.
+        provider.close().await.expect("close");
+        let routed = QuoteResponse {
+            provider_data: quote_provider_data(&request, "titan", &SwapRoute::default(), &[]),
+            ..matching
+        };
+        let err = provider
+            .swap(&routed, &Pubkey::new_unique(), &rpc)
+            .await
+            .expect_err("closed provider");
+        assert!(
+            matches!(err, SwapError::Titan(ref m) if m.contains("closed")),
+            "got {err:?}"
+        );
+    }
+
+    #[test]
+    fn zero_amount_route_is_no_route() {
+        let priced = SwapRoute {
+            in_amount: 1_000,
+            out_amount: 900,
+            ..SwapRoute::default()
+        };
+        assert!(ensure_nonzero_route(&priced).is_ok());
+
+        for degenerate in [
+            SwapRoute {
+                out_amount: 0,
+                ..priced.clone()
+            },
+            SwapRoute {
+                in_amount: 0,
+                ..priced.clone()
+            },
+        ] {
+            let err = ensure_nonzero_route(&degenerate).expect_err("zero amount");
+            assert!(matches!(err, SwapError::NoRouteFound), "got {err:?}");
+        }
+    }
+
+    #[test]
+    fn no_route_server_error_maps_to_no_route_found() {
+        let err = map_titan_error(TitanClientError::ServerError {
+            code: 404,
+            message: "No routes found for pair".to_string(),
+        });
+        assert!(matches!(err, SwapError::NoRouteFound), "got {err:?}");
+    }
+
+    #[test]
+    fn auth_failures_map_to_provider_not_configured() {
+        let err = map_titan_error(TitanClientError::AuthenticationFailed(
+            "bad token".to_string(),
+        ));
+        assert!(matches!(
+            err,
+            SwapError::ProviderNotConfigured(Provider::Titan)
+        ));
+
+        let err = map_titan_error(TitanClientError::ServerError {
+            code: 401,
+            message: "unauthorized".to_string(),
+        });
+        assert!(matches!(
+            err,
+            SwapError::ProviderNotConfigured(Provider::Titan)
+        ));
+    }
+
+    #[test]
+    fn connection_failure_maps_to_timeout() {
+        let err = map_titan_error(TitanClientError::ConnectionFailed {
+            attempts: 5,
+            reason: "refused".to_string(),
+        });
+        assert!(matches!(err, SwapError::Timeout));
+    }
+
+    #[test]
+    fn other_errors_fall_back_to_titan() {
+        let err = map_titan_error(TitanClientError::StreamLimitExceeded);
+        assert!(matches!(err, SwapError::Titan(ref m) if m.contains("Stream limit")));
     }
 }
@@ -0,0 +1,240 @@
+use std::{future::Future, time::Duration};
+
+use titan_rust_client::{types::SwapQuotes, QuoteStream, TitanClientError};
+
+use tracing::debug;
+
+use crate::error::SwapError;
+
+use super::map_titan_error;
+
+// A server-side Titan quote stream: updates until it ends, and open until stopped.
+pub(crate) trait QuoteSource {
+    fn recv(&mut self) -> impl Future<Output = Option<SwapQuotes>> + Send;
+    fn stop(&mut self) -> impl Future<Output = Result<(), TitanClientError>> + Send;
+}
+
+impl QuoteSource for QuoteStream {
+    fn recv(&mut self) -> impl Future<Output = Option<SwapQuotes>> + Send {
+        QuoteStream::recv(self)
+    }
+
+    fn stop(&mut self) -> impl Future<Output = Result<(), TitanClientError>> + Send {
+        QuoteStream::stop(self)
+    }
+}
+
+// Bounded-window quoting behind `TitanProvider::quote`: opens a stream with `open`, reads updates
+// for `window` and returns the newest one. Titan's updates supersede each other, so older sets
+// are not compared. A stream that closes before any
+// update is more likely a dropped connection than an empty market, so it is reopened once (with
+// a fresh window) before concluding `NoRouteFound`. A live stream that stays silent for the
+// whole window is not retried.
+pub(crate) async fn latest_quotes_reconnecting<S, F, Fut>(
+    mut open: F,
+    window: Duration,
+) -> Result<SwapQuotes, SwapError>
+where
+    S: QuoteSource,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<S, SwapError>>,
+{
+    let mut stream = open().await?;
+    let (mut latest, closed) = read_window(&mut stream, window).await?;
+    if latest.is_none() && closed {
+        debug!("titan quote stream closed without updates, reconnecting once");
+        let mut stream = open().await?;
+        (latest, _) = read_window(&mut stream, window).await?;
+    }
+    latest.ok_or(SwapError::NoRouteFound)
+}
+
+// Reads updates until `window` elapses or the stream ends, then stops it exactly once, so the
+// stream never outlives the read; `QuoteStream`'s own drop-time stop is only a fallback. Returns
+// the newest update and whether the stream ended on its own.
+async fn read_window<S: QuoteSource>(
+    stream: &mut S,
+    window: Duration,
+) -> Result<(Option<SwapQuotes>, bool), SwapError> {
+    let until = tokio::time::Instant::now() + window;
+    let mut latest = None;
+    let closed = loop {
+        match tokio::time::timeout_at(until, stream.recv()).await {
+            Ok(Some(quotes)) => latest = Some(quotes),
+            Ok(None) => break true,
+            Err(_) => break false,
+        }
+    };
+    stream.stop().await.map_err(map_titan_error)?;
+    Ok((latest, closed))
+}
+
+#[cfg(test)]
+#[expect(clippy::expect_used, reason = "test code")]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use titan_rust_client::types::{SwapMode as TitanSwapMode, SwapRoute};
+
+    use super::*;
+
+    struct MockStream {
+        updates: VecDeque<SwapQuotes>,
+        // Once drained, `recv` hangs like a live stream instead of ending.
+        stays_open: bool,
+        stops: Arc<AtomicUsize>,
+    }
+
+    impl QuoteSource for MockStream {
+        async fn recv(&mut self) -> Option<SwapQuotes> {
+            match self.updates.pop_front() {
+                Some(update) => Some(update),
+                None if self.stays_open => std::future::pending().await,
+                None => None,
+            }
+        }
+
+        async fn stop(&mut self) -> Result<(), TitanClientError> {
+            self.stops.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn update(routes: &[(&str, u64)]) -> SwapQuotes {
+        SwapQuotes {
+            swap_mode: TitanSwapMode::ExactIn,
+            quotes: routes
+                .iter()
+                .map(|(id, out_amount)| {
+                    (
+                        (*id).to_string(),
+                        SwapRoute {
+                            out_amount: *out_amount,
+                            ..SwapRoute::default()
+                        },
+                    )
+                })
+                .collect(),
+            ..SwapQuotes::default()
+        }
+    }
+
+    fn mock(updates: Vec<SwapQuotes>, stays_open: bool) -> (MockStream, Arc<AtomicUsize>) {
+        let stops = Arc::new(AtomicUsize::new(0));
+        let stream = MockStream {
+            updates: updates.into(),
+            stays_open,
+            stops: Arc::clone(&stops),
+        };
+        (stream, stops)
+    }
+
+    // An opener that hands out `stream` once, like a provider whose first stream is live.
+    fn open_once(
+        stream: MockStream,
+    ) -> impl FnMut() -> std::future::Ready<Result<MockStream, SwapError>> {
+        let mut stream = Some(stream);
+        move || std::future::ready(Ok(stream.take().expect("opened once")))
+    }
+
+    fn route_ids(quotes: &SwapQuotes) -> Vec<(&str, u64)> {
+        let mut routes: Vec<_> = quotes
+            .quotes
+            .iter()
+            .map(|(id, route)| (id.as_str(), route.out_amount))
+            .collect();
+        routes.sort_unstable();
+        routes
+    }
+
+    #[tokio::test]
+    async fn stream_is_stopped_once_when_window_elapses() {
+        let (stream, stops) = mock(
+            vec![
+                update(&[("old", 2_000)]),
+                update(&[("a", 1_000), ("b", 1_100), ("c", 1_050)]),
+            ],
+            true,
+        );
+
+        let quotes = latest_quotes_reconnecting(open_once(stream), Duration::from_millis(20))
+            .await
+            .expect("an update");
+
+        // The newest update wins whole, with every route kept for the quote's candidates.
+        assert_eq!(
+            route_ids(&quotes),
+            [("a", 1_000), ("b", 1_100), ("c", 1_050)]
+        );
+        assert_eq!(stops.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn stream_is_stopped_once_when_it_ends_early() {
+        let (stream, stops) = mock(vec![update(&[("a", 1_000)])], false);
+
+        let quotes = latest_quotes_reconnecting(open_once(stream), Duration::from_secs(5))
+            .await
+            .expect("an update");
+
+        assert_eq!(route_ids(&quotes), [("a", 1_000)]);
+        assert_eq!(stops.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn stream_closed_empty_is_reopened_once() {
+        let (dropped, dropped_stops) = mock(vec![], false);
+        let (live, live_stops) = mock(vec![update(&[("a", 1_000)])], true);
+        let mut attempts = VecDeque::from([dropped, live]);
+
+        let quotes = latest_quotes_reconnecting(
+            || {
+                let next = attempts.pop_front().expect("at most two attempts");
+                async move { Ok(next) }
+            },
+            Duration::from_millis(20),
+        )
+        .await
+        .expect("update on retry");
+
+        assert_eq!(route_ids(&quotes), [("a", 1_000)]);
+        assert_eq!(dropped_stops.load(Ordering::SeqCst), 1);
+        assert_eq!(live_stops.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn stream_closed_empty_twice_is_no_route() {
+        let mut opens = 0;
+
+        let err = latest_quotes_reconnecting(
+            || {
+                opens += 1;
+                async { Ok(mock(vec![], false).0) }
+            },
+            Duration::from_millis(20),
+        )
+        .await
+        .expect_err("no updates on either attempt");
+
+        assert!(matches!(err, SwapError::NoRouteFound), "got {err:?}");
+        assert_eq!(opens, 2);
+    }
+
+    #[tokio::test]
+    async fn silent_live_stream_is_not_reopened() {
+        let (stream, stops) = mock(vec![], true);
+
+        let err = latest_quotes_reconnecting(open_once(stream), Duration::from_millis(20))
+            .await
+            .expect_err("no update within the window");
+
+        assert!(matches!(err, SwapError::NoRouteFound), "got {err:?}");
+        assert_eq!(stops.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,276 @@
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey,
+    pubkey::Pubkey,
+};
+
+pub const NATIVE_MINT: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+pub const TOKEN_PROGRAM: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const TOKEN_2022_PROGRAM: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+pub const ASSOCIATED_TOKEN_PROGRAM: Pubkey =
+    pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+pub const SYSTEM_PROGRAM: Pubkey = pubkey!("11111111111111111111111111111111");
+pub const MEMO_PROGRAM: Pubkey = pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+const RECENT_BLOCKHASHES_SYSVAR: Pubkey = pubkey!("SysvarRecentB1ockHashes11111111111111111111");
+
+const CLOSE_ACCOUNT_TAG: u8 = 9;
+const TRANSFER_CHECKED_TAG: u8 = 12;
+const SYNC_NATIVE_TAG: u8 = 17;
+const CREATE_IDEMPOTENT_TAG: u8 = 1;
+const SYSTEM_TRANSFER_TAG: u32 = 2;
+const SYSTEM_ADVANCE_NONCE_TAG: u32 = 4;
+
+const TOKEN_ACCOUNT_AMOUNT_OFFSET: usize = 64;
+// Token-2022 mints pad the base mint to the token account length, then store the account type
+// byte followed by TLV extensions.
+const TOKEN_2022_EXTENSIONS_OFFSET: usize = 166;
+const TRANSFER_FEE_CONFIG_EXTENSION: u16 = 1;
+const TRANSFER_FEE_CONFIG_FEES_OFFSET: usize = 72;
+const MAX_FEE_BASIS_POINTS: u128 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferFee {
+    pub epoch: u64,
+    pub maximum_fee: u64,
+    pub basis_points: u16,
+}
+
+impl TransferFee {
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        Some(Self {
+            epoch: u64::from_le_bytes(bytes.get(0..8)?.try_into().ok()?),
+            maximum_fee: u64::from_le_bytes(bytes.get(8..16)?.try_into().ok()?),
+            basis_points: u16::from_le_bytes(bytes.get(16..18)?.try_into().ok()?),
+        })
+    }
+
+    // Token-2022 rounds the fee up and caps it at `maximum_fee`.
+    pub fn fee(&self, amount: u64) -> u64 {
+        let fee =
+            (u128::from(amount) * u128::from(self.basis_points)).div_ceil(MAX_FEE_BASIS_POINTS);
+        u64::try_from(fee).unwrap_or(u64::MAX).min(self.maximum_fee)
+    }
+
+    pub fn amount_after_fee(&self, amount: u64) -> u64 {
+        amount.saturating_sub(self.fee(amount))
+    }
+}
+
+pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM,
+    )
+    .0
+}
+
+// `TransferChecked` rather than plain `Transfer`, which Token-2022 rejects for mints with a
+// transfer-fee extension.
+pub fn transfer_checked_instruction(
+    token_program: &Pubkey,
+    source: &Pubkey,
+    mint: &Pubkey,
+    destination: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+    decimals: u8,
+) -> Instruction {
+    let mut data = vec![TRANSFER_CHECKED_TAG];
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.push(decimals);
+    Instruction {
+        program_id: *token_program,
+        accounts: vec![
+            AccountMeta::new(*source, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+        data,
+    }
+}
+
+// Whether `ix` is a token program `CloseAccount` of `account`.
+pub fn closes_account(ix: &Instruction, account: &Pubkey) -> bool {
+    is_token_program(&ix.program_id)
+        && ix.data.first() == Some(&CLOSE_ACCOUNT_TAG)
+        && ix
+            .accounts
+            .first()
+            .is_some_and(|meta| meta.pubkey == *account)
+}
+
+pub fn create_associated_token_account_idempotent(
+    payer: &Pubkey,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: ASSOCIATED_TOKEN_PROGRAM,
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(associated_token_address(owner, mint, token_program), false),
+            AccountMeta::new_readonly(*owner, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(SYSTEM_PROGRAM, false),
+            AccountMeta::new_readonly(*token_program, false),
+        ],
+        data: vec![CREATE_IDEMPOTENT_TAG],
+    }
+}
+
+pub fn close_account_instruction(
+    token_program: &Pubkey,
+    account: &Pubkey,
+    destination: &Pubkey,
+    owner: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *token_program,
+        accounts: vec![
+            AccountMeta::new(*account, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+        data: vec![CLOSE_ACCOUNT_TAG],
+    }
+}
+
+// Creates the owner's wSOL ATA if needed, moves `lamports` into it and syncs the token balance.
+pub fn wrap_sol_instructions(owner: &Pubkey, lamports: u64) -> Vec<Instruction> {
+    let ata = associated_token_address(owner, &NATIVE_MINT, &TOKEN_PROGRAM);
+    let mut transfer_data = SYSTEM_TRANSFER_TAG.to_le_bytes().to_vec();
+    transfer_data.extend_from_slice(&lamports.to_le_bytes());
+    vec![
+        create_associated_token_account_idempotent(owner, owner, &NATIVE_MINT, &TOKEN_PROGRAM),
+        Instruction {
+            program_id: SYSTEM_PROGRAM,
+            accounts: vec![AccountMeta::new(*owner, true), AccountMeta::new(ata, false)],
+            data: transfer_data,
+        },
+        Instruction {
+            program_id: TOKEN_PROGRAM,
+            accounts: vec![AccountMeta::new(ata, false)],
+            data: vec![SYNC_NATIVE_TAG],
+        },
+    ]
+}
+
+pub fn advance_nonce_instruction(nonce_account: &Pubkey, nonce_authority: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: SYSTEM_PROGRAM,
+        accounts: vec![
+            AccountMeta::new(*nonce_account, false),
+            AccountMeta::new_readonly(RECENT_BLOCKHASHES_SYSVAR, false),
+            AccountMeta::new_readonly(*nonce_authority, true),
+        ],
+        data: SYSTEM_ADVANCE_NONCE_TAG.to_le_bytes().to_vec(),
+    }
+}
+
+// With no accounts the memo program requires no signers; the text is logged as-is.
+pub fn memo_instruction(memo: &str) -> Instruction {
+    Instruction {
+        program_id: MEMO_PROGRAM,
+        accounts: vec![],
+        data: memo.as_bytes().to_vec(),
+    }
+}
+
+// Closing the wSOL ATA returns any unspent wrapped SOL, plus rent, to the owner.
+pub fn unwrap_sol_instruction(owner: &Pubkey) -> Instruction {
+    let ata = associated_token_address(owner, &NATIVE_MINT, &TOKEN_PROGRAM);
+    close_account_instruction(&TOKEN_PROGRAM, &ata, owner, owner)
+}
+
+pub fn token_account_amount(data: &[u8]) -> Option<u64> {
+    let bytes = data.get(TOKEN_ACCOUNT_AMOUNT_OFFSET..TOKEN_ACCOUNT_AMOUNT_OFFSET + 8)?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+pub fn transfer_fee(mint_data: &[u8], epoch: u64) -> Option<TransferFee> {
+    let mut offset = TOKEN_2022_EXTENSIONS_OFFSET;
+    while let Some(header) = mint_data.get(offset..offset + 4) {
+        let extension_type = u16::from_le_bytes([header[0], header[1]]);
+        let length = usize::from(u16::from_le_bytes([header[2], header[3]]));
+        let value = mint_data.get(offset + 4..offset + 4 + length)?;
+        if extension_type == TRANSFER_FEE_CONFIG_EXTENSION {
+            let fees = value.get(TRANSFER_FEE_CONFIG_FEES_OFFSET..)?;
+            let older = TransferFee::parse(fees.get(0..18)?)?;
+            let newer = TransferFee::parse(fees.get(18..36)?)?;
+            return Some(if epoch >= newer.epoch { newer } else { older });
+        }
+        offset += 4 + length;
+    }
+    None
+}
+
+pub fn is_token_program(program_id: &Pubkey) -> bool {
+    *program_id == TOKEN_PROGRAM || *program_id == TOKEN_2022_PROGRAM
+}
+
+#[cfg(test)]
+#[expect(clippy::expect_used, reason = "test code")]
+pub(crate) mod tests {
+    use super::*;
+
+    fn fee_bytes(fee: TransferFee) -> Vec<u8> {
+        let mut bytes = fee.epoch.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&fee.maximum_fee.to_le_bytes());
+        bytes.extend_from_slice(&fee.basis_points.to_le_bytes());
+        bytes
+    }
+
+    pub(crate) fn mint_with_transfer_fee(older: TransferFee, newer: TransferFee) -> Vec<u8> {
+        let mut data = vec![0_u8; TOKEN_2022_EXTENSIONS_OFFSET];
+        // an unrelated extension first, to exercise the TLV walk
+        data.extend_from_slice(&3_u16.to_le_bytes());
+        data.extend_from_slice(&2_u16.to_le_bytes());
+        data.extend_from_slice(&[0, 0]);
+
+        let mut value = vec![0_u8; TRANSFER_FEE_CONFIG_FEES_OFFSET];
+        value.extend(fee_bytes(older));
+        value.extend(fee_bytes(newer));
+        data.extend_from_slice(&TRANSFER_FEE_CONFIG_EXTENSION.to_le_bytes());
+        data.extend_from_slice(&u16::try_from(value.len()).expect("len").to_le_bytes());
+        data.extend(value);
+        data
+    }
+
+    #[test]
+    fn amount_after_fee_applies_bps_rounded_up_and_capped() {
+        let fee = TransferFee {
+            epoch: 0,
+            maximum_fee: u64::MAX,
+            basis_points: 100,
+        };
+        assert_eq!(fee.amount_after_fee(1_000_000), 990_000);
+        assert_eq!(fee.amount_after_fee(150), 148);
+
+        let capped = TransferFee {
+            maximum_fee: 5_000,
+            ..fee
+        };
+        assert_eq!(capped.amount_after_fee(1_000_000), 995_000);
+    }
+
+    #[test]
+    fn transfer_fee_picks_fee_for_epoch() {
+        let older = TransferFee {
+            epoch: 0,
+            maximum_fee: u64::MAX,
+            basis_points: 50,
+        };
+        let newer = TransferFee {
+            epoch: 10,
+            maximum_fee: u64::MAX,
+            basis_points: 200,
+        };
+        let data = mint_with_transfer_fee(older, newer);
+
+        assert_eq!(transfer_fee(&data, 9), Some(older));
+        assert_eq!(transfer_fee(&data, 10), Some(newer));
+        assert_eq!(transfer_fee(&data[..82], 10), None);
+    }
+}
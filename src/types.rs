@@ -1,20 +1,31 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use reqwest::header::HeaderName;
 use serde::Serialize;
+use solana_compute_budget_interface::{self as compute_budget, ComputeBudgetInstruction};
 use solana_sdk::{
     address_lookup_table::AddressLookupTableAccount,
     hash::Hash,
-    instruction::{AccountMeta, Instruction},
-    message::{v0, VersionedMessage},
+    instruction::{AccountMeta, CompiledInstruction, Instruction},
+    message::{v0, CompileError, Message, VersionedMessage},
     pubkey,
     pubkey::Pubkey,
     signature::Signature,
     transaction::VersionedTransaction,
 };
 
-use crate::error::SwapError;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{error::SwapError, token};
 
 pub const JUPITER_PROGRAM: Pubkey = pubkey!("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4");
 pub const TITAN_PROGRAM: Pubkey = pubkey!("T1TANpTeScyeqVzzgNViGDNrkQ6qHz9KrSBS4aNXvGT");
 
+// IPv6 MTU minus headers; validators drop anything larger.
+pub const MAX_TRANSACTION_BYTES: usize = 1280 - 40 - 8;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SwapMode {
     ExactIn,
@@ -28,6 +39,87 @@ pub enum Provider {
     Dflow,
 }
 
+impl Provider {
+    pub const ALL: &'static [Provider] = &[Self::Jupiter, Self::Titan, Self::Dflow];
+
+    // What this crate's integration supports, not everything the provider's API offers.
+    pub fn capabilities(&self) -> Capabilities {
+        match self {
+            Self::Jupiter => Capabilities {
+                exact_out: true,
+                swap: true,
+                instructions: true,
+                prebuilt_transaction: false,
+                address_lookup_tables: true,
+                streaming: false,
+            },
+            Self::Titan => Capabilities {
+                exact_out: true,
+                swap: true,
+                instructions: true,
+                prebuilt_transaction: false,
+                address_lookup_tables: true,
+                streaming: true,
+            },
+            Self::Dflow => Capabilities {
+                exact_out: false,
+                swap: true,
+                instructions: false,
+                prebuilt_transaction: true,
+                address_lookup_tables: false,
+                streaming: false,
+            },
+        }
+    }
+
+    // Checked before any request is sent: a provider without ExactOut would otherwise quote the
+    // amount as an input and return an answer to a different question.
+    pub(crate) fn ensure_swap_mode(&self, mode: Option<SwapMode>) -> Result<(), SwapError> {
+        match mode {
+            Some(SwapMode::ExactOut) if !self.capabilities().exact_out => {
+                Err(SwapError::UnsupportedSwapMode {
+                    provider: *self,
+                    mode: SwapMode::ExactOut,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    ExactOut,
+    Swap,
+    Instructions,
+    PrebuiltTransaction,
+    AddressLookupTables,
+    Streaming,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    pub exact_out: bool,
+    pub swap: bool,
+    pub instructions: bool,
+    pub prebuilt_transaction: bool,
+    pub address_lookup_tables: bool,
+    pub streaming: bool,
+}
+
+impl Capabilities {
+    pub fn supports(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::ExactOut => self.exact_out,
+            Capability::Swap => self.swap,
+            Capability::Instructions => self.instructions,
+            Capability::PrebuiltTransaction => self.prebuilt_transaction,
+            Capability::AddressLookupTables => self.address_lookup_tables,
+            Capability::Streaming => self.streaming,
+        }
+    }
+}
+
 impl std::fmt::Display for Provider {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -51,6 +143,78 @@ pub struct QuoteRequest {
     pub swap_mode: Option<SwapMode>,
     pub dexes: Option<String>,
     pub exclude_dexes: Option<String>,
+    pub input_is_fee_on_transfer: bool,
+    pub fee_account: Option<Pubkey>,
+    pub platform_fee_bps: Option<u16>,
+    pub input_token_program: Option<Pubkey>,
+    pub output_token_program: Option<Pubkey>,
+    pub provider_params: Option<ProviderParams>,
+    pub reference_price: Option<ReferencePrice>,
+    // Restricts multi-provider calls to these providers; `None` queries every configured one.
+    pub providers: Option<Vec<Provider>>,
+    // A routing hint providers may ignore; see `restrict_intermediate_tokens` for Jupiter.
+    pub preferred_intermediate_mints: Option<Vec<Pubkey>>,
+}
+
+impl QuoteRequest {
+    pub fn with_slippage(mut self, slippage: Slippage) -> Result<Self, SwapError> {
+        self.slippage_bps = Some(slippage.to_bps()?);
+        Ok(self)
+    }
+
+    // `provider_params` for `provider` as query pairs, minus the keys in `set`: a parameter the
+    // crate already sends from a typed field keeps the typed value.
+    #[cfg(any(feature = "jupiter", feature = "dflow"))]
+    pub(crate) fn provider_query(&self, provider: Provider, set: &[&str]) -> Vec<(String, String)> {
+        let Some(params) = self
+            .provider_params
+            .as_ref()
+            .and_then(|p| p.for_provider(provider))
+        else {
+            return Vec::new();
+        };
+        params
+            .iter()
+            .filter(|(key, value)| !value.is_null() && !set.contains(&key.as_str()))
+            .map(|(key, value)| {
+                let value = match value {
+                    serde_json::Value::String(text) => text.clone(),
+                    other => other.to_string(),
+                };
+                (key.clone(), value)
+            })
+            .collect()
+    }
+}
+
+// Provider-native quote parameters the crate does not model, passed through as-is. Titan's
+// typed WebSocket request has no room for extras, so it has no entry.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProviderParams {
+    pub jupiter: serde_json::Map<String, serde_json::Value>,
+    pub dflow: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ProviderParams {
+    #[cfg(any(feature = "jupiter", feature = "dflow"))]
+    fn for_provider(
+        &self,
+        provider: Provider,
+    ) -> Option<&serde_json::Map<String, serde_json::Value>> {
+        match provider {
+            Provider::Jupiter => Some(&self.jupiter),
+            Provider::Dflow => Some(&self.dflow),
+            Provider::Titan => None,
+        }
+    }
+}
+
+// Per-call overrides for multi-tenant callers; a set key replaces the provider's configured
+// key for that call only. Titan shares one authenticated socket, so its token cannot vary.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuoteContext {
+    pub jupiter_api_key: Option<String>,
+    pub dflow_api_key: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -62,7 +226,432 @@ pub struct QuoteResponse {
     pub output_amount: u64,
     pub price_impact_bps: Option<u16>,
     pub slippage_bps: u16,
+    pub slippage_adjusted: bool,
     pub provider_data: serde_json::Value,
+    pub meta: QuoteMeta,
+    pub swap_id: Uuid,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct QuoteMeta {
+    pub latency_ms: u64,
+    // Size of the provider's response body on the wire; 0 for Titan, whose WebSocket frames the
+    // client does not expose.
+    pub response_bytes: usize,
+}
+
+impl QuoteMeta {
+    pub(crate) fn measured(started: std::time::Instant, response_bytes: usize) -> Self {
+        Self {
+            latency_ms: started.elapsed().as_millis() as u64,
+            response_bytes,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NormalizedQuote {
+    pub provider: Provider,
+    pub input_mint: String,
+    pub output_mint: String,
+    pub input_amount: u64,
+    pub output_amount: u64,
+    pub price_impact_bps: Option<u16>,
+    pub slippage_bps: u16,
+    pub fee_amount: Option<u64>,
+    pub hop_count: Option<usize>,
+}
+
+impl QuoteResponse {
+    pub fn is_noop(&self) -> bool {
+        self.input_mint == self.output_mint || self.input_amount == 0 || self.output_amount == 0
+    }
+
+    // Providers call this before trusting `provider_data`, so a quote built for
+    // one provider (or hand-edited) fails cleanly instead of deep inside swap.
+    pub(crate) fn ensure_provider(
+        &self,
+        provider: Provider,
+        required_keys: &[&str],
+    ) -> Result<(), SwapError> {
+        if self.provider != provider {
+            return Err(SwapError::InconsistentResponse(format!(
+                "{provider} swap received a {} quote",
+                self.provider
+            )));
+        }
+        if let Some(key) = required_keys.iter().find(|key| {
+            self.provider_data
+                .get(**key)
+                .is_none_or(serde_json::Value::is_null)
+        }) {
+            return Err(SwapError::InconsistentResponse(format!(
+                "{provider} quote provider_data is missing `{key}`"
+            )));
+        }
+        // The swap is rebuilt from `provider_data`, so mints recorded there must be the ones the
+        // quote displays; otherwise an edited quote would swap a different pair.
+        for (key, mint) in [
+            ("inputMint", self.input_mint),
+            ("outputMint", self.output_mint),
+        ] {
+            let stored = &self.provider_data[key];
+            if !stored.is_null() && json_pubkey(stored) != Some(mint) {
+                return Err(SwapError::InconsistentResponse(format!(
+                    "{provider} quote provider_data `{key}` {stored} does not match {mint}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // Slot the provider quoted against; RPC reads for this swap should be at least this fresh.
+    pub fn context_slot(&self) -> Option<u64> {
+        json_u64(&self.provider_data["contextSlot"])
+    }
+
+    // Output per unit of input in raw token units; `None` for a zero input.
+    pub fn price(&self) -> Option<f64> {
+        (self.input_amount > 0).then(|| self.output_amount as f64 / self.input_amount as f64)
+    }
+
+    // Input per unit of output in raw token units; `None` for a zero output.
+    pub fn inverse_price(&self) -> Option<f64> {
+        (self.output_amount > 0).then(|| self.input_amount as f64 / self.output_amount as f64)
+    }
+
+    // `price` in whole tokens, so pairs with different decimals compare directly.
+    pub fn price_with_decimals(&self, input_decimals: u8, output_decimals: u8) -> Option<f64> {
+        let scale = 10f64.powi(i32::from(input_decimals) - i32::from(output_decimals));
+        self.price().map(|price| price * scale)
+    }
+
+    // ExactOut quotes fix the output, and their threshold bounds the input instead.
+    pub fn min_received(&self) -> u64 {
+        if self.provider_data["swapMode"] == "ExactOut" {
+            return self.output_amount;
+        }
+        json_u64(&self.provider_data["otherAmountThreshold"])
+            .unwrap_or_else(|| apply_slippage(self.output_amount, self.slippage_bps))
+    }
+
+    // A request for the same trade, for re-quoting. The taker, fees and routing filters come
+    // back from `provider_data`, where each provider records the request it quoted; DEX lists
+    // are stored comma-separated (Jupiter, Dflow) or as arrays (Titan).
+    pub fn to_request(&self) -> QuoteRequest {
+        let data = &self.provider_data;
+        let exact_out = data["swapMode"] == "ExactOut";
+        let dexes = |key: &str| match &data[key] {
+            serde_json::Value::String(dexes) => Some(dexes.clone()),
+            serde_json::Value::Array(dexes) => Some(
+                dexes
+                    .iter()
+                    .filter_map(serde_json::Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            _ => None,
+        };
+        QuoteRequest {
+            input_mint: self.input_mint,
+            output_mint: self.output_mint,
+            amount: if exact_out {
+                self.output_amount
+            } else {
+                self.input_amount
+            },
+            slippage_bps: Some(self.slippage_bps),
+            only_direct_routes: data["onlyDirectRoutes"].as_bool(),
+            taker: json_pubkey(&data["taker"]),
+            restrict_intermediate_tokens: None,
+            as_legacy_transaction: None,
+            swap_mode: exact_out.then_some(SwapMode::ExactOut),
+            dexes: dexes("dexes"),
+            exclude_dexes: dexes("excludeDexes"),
+            input_is_fee_on_transfer: false,
+            fee_account: json_pubkey(&data["feeAccount"]),
+            platform_fee_bps: json_u64(&data["platformFeeBps"])
+                .or_else(|| json_u64(&data["feeBps"]))
+                .and_then(|bps| u16::try_from(bps).ok()),
+            input_token_program: None,
+            output_token_program: None,
+            provider_params: None,
+            reference_price: None,
+            providers: None,
+            preferred_intermediate_mints: None,
+        }
+    }
+
+    // Upper bound on the input: for ExactOut the provider's threshold (or the quoted input
+    // plus slippage), for ExactIn the quoted input itself.
+    pub fn maximum_input(&self) -> Result<u64, SwapError> {
+        if self.provider_data["swapMode"] != "ExactOut" {
+            return Ok(self.input_amount);
+        }
+        match json_u64(&self.provider_data["otherAmountThreshold"]) {
+            Some(threshold) => Ok(threshold),
+            None => apply_slippage_up(self.input_amount, self.slippage_bps),
+        }
+    }
+
+    // The variable side's bound, named for what the user sees: the least output they accept
+    // (fixed at the requested output for ExactOut) and the most input they pay (fixed at the
+    // quoted input for ExactIn).
+    pub fn minimum_received(&self) -> u64 {
+        self.min_received()
+    }
+
+    pub fn maximum_spent(&self) -> Result<u64, SwapError> {
+        self.maximum_input()
+    }
+
+    // Hops from the provider's `routePlan`; Jupiter nests them under `swapInfo`.
+    pub fn route(&self) -> Vec<RouteHop> {
+        let Some(plan) = self.provider_data["routePlan"].as_array() else {
+            return Vec::new();
+        };
+        plan.iter()
+            .map(|step| {
+                let info = step.get("swapInfo").unwrap_or(step);
+                let text = |key: &str| info[key].as_str().map(str::to_string);
+                RouteHop {
+                    amm_key: text("ammKey"),
+                    program_id: text("programId"),
+                    label: text("label"),
+                    input_mint: text("inputMint"),
+                    output_mint: text("outputMint"),
+                    in_amount: json_u64(&info["inAmount"]),
+                    out_amount: json_u64(&info["outAmount"]),
+                    percent: step["percent"].as_u64().and_then(|p| u8::try_from(p).ok()),
+                }
+            })
+            .collect()
+    }
+
+    // Sequential hops in `route`: parallel legs of a split step (`percent` below 100) fill one
+    // step together, so a step ends once its legs' percents reach 100.
+    pub fn hop_count(&self) -> usize {
+        let mut hops = 0;
+        let mut filled = 0;
+        for hop in self.route() {
+            filled += u32::from(hop.percent.unwrap_or(100));
+            if filled >= 100 {
+                hops += 1;
+                filled = 0;
+            }
+        }
+        hops + usize::from(filled > 0)
+    }
+
+    // ExactIn quotes fix the input and compete on output; ExactOut quotes fix the output and
+    // compete on input. Quotes for different pairs or modes are not comparable, so neither is
+    // better than the other.
+    pub fn is_better_than(&self, other: &QuoteResponse) -> bool {
+        let exact_out = self.provider_data["swapMode"] == "ExactOut";
+        if self.input_mint != other.input_mint
+            || self.output_mint != other.output_mint
+            || exact_out != (other.provider_data["swapMode"] == "ExactOut")
+        {
+            return false;
+        }
+        if exact_out {
+            self.input_amount < other.input_amount
+        } else {
+            self.output_amount > other.output_amount
+        }
+    }
+
+    // Signed distance of `price()` from `reference_price` (same raw units) in bps: negative when
+    // the quote pays less than the reference. NaN when either price is missing or non-positive.
+    pub fn deviation_from(&self, reference_price: f64) -> f64 {
+        match self.price() {
+            Some(price) if reference_price > 0.0 => (price / reference_price - 1.0) * 10_000.0,
+            _ => f64::NAN,
+        }
+    }
+
+    // Output after the provider-reported platform fee, the only fee a quote states. Jupiter's
+    // `outAmount` already has its `platformFee` taken off (the route's last leg pays out the sum),
+    // so only Dflow's reported fee is subtracted here.
+    pub fn net_output(&self) -> u64 {
+        if self.provider == Provider::Jupiter {
+            return self.output_amount;
+        }
+        let fee = json_u64(&self.provider_data["platformFee"]["amount"]).unwrap_or(0);
+        self.output_amount.saturating_sub(fee)
+    }
+
+    // What reaches the user once a caller's own fee of `fee_bps` is taken from `net_output`, e.g.
+    // "you'll receive X after our 0.3% fee". Same u128 math as `apply_slippage`: the fee rounds
+    // up, so the figure shown is never more than what is delivered.
+    pub fn net_output_after_bps(&self, fee_bps: u16) -> u64 {
+        apply_slippage(self.net_output(), fee_bps)
+    }
+
+    pub fn normalized(&self) -> NormalizedQuote {
+        let fee_amount = json_u64(&self.provider_data["platformFee"]["amount"]);
+        let hop_count = self.provider_data["routePlan"]
+            .is_array()
+            .then(|| self.hop_count());
+
+        NormalizedQuote {
+            provider: self.provider,
+            input_mint: self.input_mint.to_string(),
+            output_mint: self.output_mint.to_string(),
+            input_amount: self.input_amount,
+            output_amount: self.output_amount,
+            price_impact_bps: self.price_impact_bps,
+            slippage_bps: self.slippage_bps,
+            fee_amount,
+            hop_count,
+        }
+    }
+}
+
+fn ensure_fits(
+    transaction: VersionedTransaction,
+    max_bytes: usize,
+) -> Result<VersionedTransaction, SwapError> {
+    let bytes = bincode::serialized_size(&transaction)
+        .map_err(|e| SwapError::Serialization(e.to_string()))? as usize;
+    if bytes > max_bytes {
+        return Err(SwapError::TransactionTooLarge {
+            bytes,
+            limit: max_bytes,
+        });
+    }
+    Ok(transaction)
+}
+
+// Appends an instruction whose accounts are all read-only non-signers. New keys go at the end
+// of the static keys (the read-only unsigned section), which shifts every index into the
+// lookup-table addresses that follow them.
+fn append_unsigned_instruction(
+    message: &mut VersionedMessage,
+    ix: Instruction,
+) -> Result<(), SwapError> {
+    let loaded = message.address_table_lookups().map_or(0, |lookups| {
+        lookups
+            .iter()
+            .map(|l| l.writable_indexes.len() + l.readonly_indexes.len())
+            .sum()
+    });
+    let (account_keys, header, compiled) = match message {
+        VersionedMessage::Legacy(m) => (&mut m.account_keys, &mut m.header, &mut m.instructions),
+        VersionedMessage::V0(m) => (&mut m.account_keys, &mut m.header, &mut m.instructions),
+    };
+    let static_len = account_keys.len();
+    let mut new_keys = Vec::new();
+    for key in std::iter::once(&ix.program_id).chain(ix.accounts.iter().map(|a| &a.pubkey)) {
+        if !account_keys.contains(key) && !new_keys.contains(key) {
+            new_keys.push(*key);
+        }
+    }
+    let account_count = static_len + loaded + new_keys.len();
+    if account_count > usize::from(u8::MAX) + 1 {
+        return Err(SwapError::TooManyAccounts { account_count });
+    }
+    let added =
+        u8::try_from(new_keys.len()).map_err(|_| SwapError::TooManyAccounts { account_count })?;
+    if added > 0 {
+        for existing in compiled.iter_mut() {
+            for index in &mut existing.accounts {
+                if usize::from(*index) >= static_len {
+                    *index += added;
+                }
+            }
+        }
+    }
+    account_keys.extend(new_keys);
+    header.num_readonly_unsigned_accounts += added;
+
+    let index_of = |key: &Pubkey| {
+        account_keys
+            .iter()
+            .position(|k| k == key)
+            .and_then(|i| u8::try_from(i).ok())
+            .ok_or_else(|| SwapError::Solana(format!("account {key} missing from message")))
+    };
+    compiled.push(CompiledInstruction {
+        program_id_index: index_of(&ix.program_id)?,
+        accounts: ix
+            .accounts
+            .iter()
+            .map(|a| index_of(&a.pubkey))
+            .collect::<Result<_, _>>()?,
+        data: ix.data,
+    });
+    Ok(())
+}
+
+fn unique_account_count(payer: &Pubkey, instructions: &[Instruction]) -> usize {
+    let mut keys: Vec<&Pubkey> = std::iter::once(payer)
+        .chain(instructions.iter().flat_map(|ix| {
+            std::iter::once(&ix.program_id).chain(ix.accounts.iter().map(|a| &a.pubkey))
+        }))
+        .collect();
+    keys.sort_unstable();
+    keys.dedup();
+    keys.len()
+}
+
+// See `to_request`.
+impl From<&QuoteResponse> for QuoteRequest {
+    fn from(quote: &QuoteResponse) -> Self {
+        quote.to_request()
+    }
+}
+
+pub fn apply_slippage(amount: u64, slippage_bps: u16) -> u64 {
+    let kept = 10_000 - u128::from(slippage_bps.min(10_000));
+    u64::try_from(u128::from(amount) * kept / 10_000).unwrap_or(u64::MAX)
+}
+
+// The max-in counterpart of `apply_slippage`: `amount` raised by the slippage, rounded down.
+// Can exceed `u64::MAX`, which is an error rather than a silently clamped bound.
+pub fn apply_slippage_up(amount: u64, slippage_bps: u16) -> Result<u64, SwapError> {
+    let raised = 10_000 + u128::from(slippage_bps.min(10_000));
+    u64::try_from(u128::from(amount) * raised / 10_000).map_err(|_| {
+        SwapError::InvalidRequest(format!(
+            "amount {amount} plus {slippage_bps} bps slippage overflows u64"
+        ))
+    })
+}
+
+// Splits `amount` into `parts` slices for split swaps; the last slice absorbs the remainder so
+// the slices always sum to `amount`. More parts than units yields `amount` one-unit slices.
+// `parts` is a `u8` so the slice list stays small whatever the caller passes.
+pub fn split_amount(amount: u64, parts: u8) -> Vec<u64> {
+    let parts = u64::from(parts).min(amount);
+    if parts == 0 {
+        return Vec::new();
+    }
+    let slice = amount / parts;
+    let mut slices = vec![slice; parts as usize];
+    if let Some(last) = slices.last_mut() {
+        *last += amount % parts;
+    }
+    slices
+}
+
+// Providers encode amounts as either JSON numbers or decimal strings.
+fn json_u64(value: &serde_json::Value) -> Option<u64> {
+    value
+        .as_u64()
+        .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+}
+
+// Jupiter and Dflow store base58 strings; Titan's serialized prices hold the raw 32 bytes.
+fn json_pubkey(value: &serde_json::Value) -> Option<Pubkey> {
+    if let Some(s) = value.as_str() {
+        return s.parse().ok();
+    }
+    let bytes: Vec<u8> = value
+        .as_array()?
+        .iter()
+        .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+        .collect::<Option<_>>()?;
+    Pubkey::try_from(bytes.as_slice()).ok()
 }
 
 impl Serialize for Provider {
@@ -74,19 +663,162 @@ impl Serialize for Provider {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum SwapResult {
     Instructions {
         instructions: Vec<Instruction>,
         address_lookup_tables: Vec<AddressLookupTableAccount>,
-        compute_units: Option<u32>,
+        compute_budget: ComputeBudget,
+        phases: Option<InstructionPhases>,
+        swap_id: Uuid,
+        // The quote's `provider_data` the swap was built from, to tie a transaction back to it.
+        source_quote: Option<serde_json::Value>,
     },
     Transaction {
         transaction: VersionedTransaction,
         last_valid_block_height: u64,
+        // The provider's recommended limit, already set inside `transaction`.
+        compute_units: Option<u32>,
+        swap_id: Uuid,
+        source_quote: Option<serde_json::Value>,
     },
 }
 
+// The provider's own grouping of `instructions`, when it reports one (Jupiter). Concatenating
+// the phases in field order yields the flat list, so instructions can be spliced in between.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstructionPhases {
+    pub compute_budget: Vec<Instruction>,
+    pub setup: Vec<Instruction>,
+    pub swap: Vec<Instruction>,
+    pub cleanup: Vec<Instruction>,
+    pub other: Vec<Instruction>,
+}
+
+impl InstructionPhases {
+    pub fn flatten(&self) -> Vec<Instruction> {
+        [
+            &self.compute_budget,
+            &self.setup,
+            &self.swap,
+            &self.cleanup,
+            &self.other,
+        ]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ComputeBudget {
+    pub limit: Option<u32>,
+    pub price_micro_lamports: Option<u64>,
+}
+
+impl ComputeBudget {
+    const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+    const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+
+    fn missing_instructions(&self, instructions: &[Instruction]) -> Vec<Instruction> {
+        let has = |discriminant: u8| {
+            instructions.iter().any(|ix| {
+                ix.program_id == compute_budget::id() && ix.data.first() == Some(&discriminant)
+            })
+        };
+
+        let mut missing = Vec::new();
+        if let Some(limit) = self.limit {
+            if !has(Self::SET_COMPUTE_UNIT_LIMIT) {
+                missing.push(ComputeBudgetInstruction::set_compute_unit_limit(limit));
+            }
+        }
+        if let Some(price) = self.price_micro_lamports {
+            if !has(Self::SET_COMPUTE_UNIT_PRICE) {
+                missing.push(ComputeBudgetInstruction::set_compute_unit_price(price));
+            }
+        }
+        missing
+    }
+
+    fn configured(&self, discriminant: u8) -> Option<Instruction> {
+        if discriminant == Self::SET_COMPUTE_UNIT_LIMIT {
+            self.limit
+                .map(ComputeBudgetInstruction::set_compute_unit_limit)
+        } else {
+            self.price_micro_lamports
+                .map(ComputeBudgetInstruction::set_compute_unit_price)
+        }
+    }
+
+    fn conflict(instructions: &[Instruction]) -> Option<SwapError> {
+        COMPUTE_BUDGET_KINDS
+            .iter()
+            .find_map(|&(discriminant, instruction)| {
+                let mut values: Vec<u64> = Vec::new();
+                for value in budget_positions(instructions, discriminant)
+                    .into_iter()
+                    .map(|i| budget_value(&instructions[i]))
+                {
+                    if !values.contains(&value) {
+                        values.push(value);
+                    }
+                }
+                (values.len() > 1).then(|| SwapError::ConflictingComputeBudget {
+                    instruction: instruction.to_string(),
+                    values,
+                })
+            })
+    }
+
+    // Several instructions of one kind (e.g. the caller's plus Jupiter's) collapse into one at the
+    // first one's position. A value set here, where `with_compute_budget` overrides land, wins;
+    // otherwise the first instruction is kept, matching what the runtime would have used.
+    fn dedup_instructions(&self, instructions: &mut Vec<Instruction>) {
+        for (discriminant, _) in COMPUTE_BUDGET_KINDS {
+            let positions = budget_positions(instructions, discriminant);
+            let Some((&first, rest)) = positions.split_first() else {
+                continue;
+            };
+            if rest.is_empty() {
+                continue;
+            }
+            if let Some(configured) = self.configured(discriminant) {
+                instructions[first] = configured;
+            }
+            for &i in rest.iter().rev() {
+                instructions.remove(i);
+            }
+        }
+    }
+}
+
+const COMPUTE_BUDGET_KINDS: [(u8, &str); 2] = [
+    (ComputeBudget::SET_COMPUTE_UNIT_LIMIT, "SetComputeUnitLimit"),
+    (ComputeBudget::SET_COMPUTE_UNIT_PRICE, "SetComputeUnitPrice"),
+];
+
+fn budget_positions(instructions: &[Instruction], discriminant: u8) -> Vec<usize> {
+    instructions
+        .iter()
+        .enumerate()
+        .filter(|(_, ix)| {
+            ix.program_id == compute_budget::id() && ix.data.first() == Some(&discriminant)
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+// The limit is a little-endian u32 and the price a little-endian u64 after the discriminant.
+fn budget_value(ix: &Instruction) -> u64 {
+    let mut bytes = [0u8; 8];
+    let tail = ix.data.get(1..).unwrap_or_default();
+    let len = tail.len().min(bytes.len());
+    bytes[..len].copy_from_slice(&tail[..len]);
+    u64::from_le_bytes(bytes)
+}
+
 #[derive(Debug)]
 pub struct CpiSwapResult {
     pub swap_data: Vec<u8>,
@@ -97,6 +829,191 @@ pub struct CpiSwapResult {
 }
 
 impl SwapResult {
+    pub fn swap_id(&self) -> Uuid {
+        match self {
+            Self::Instructions { swap_id, .. } | Self::Transaction { swap_id, .. } => *swap_id,
+        }
+    }
+
+    pub fn source_quote(&self) -> Option<&serde_json::Value> {
+        match self {
+            Self::Instructions { source_quote, .. } | Self::Transaction { source_quote, .. } => {
+                source_quote.as_ref()
+            }
+        }
+    }
+
+    // The blockhash a prebuilt transaction is valid against; instructions get one only when
+    // compiled, so they report `None`.
+    pub fn blockhash(&self) -> Option<Hash> {
+        match self {
+            Self::Instructions { .. } => None,
+            Self::Transaction { transaction, .. } => Some(*transaction.message.recent_blockhash()),
+        }
+    }
+
+    // A height of 0 means the provider did not report one.
+    pub fn last_valid_block_height(&self) -> Option<u64> {
+        match self {
+            Self::Instructions { .. } => None,
+            Self::Transaction {
+                last_valid_block_height,
+                ..
+            } => Some(*last_valid_block_height).filter(|height| *height > 0),
+        }
+    }
+
+    pub(crate) fn with_swap_id(mut self, id: Uuid) -> Self {
+        match &mut self {
+            Self::Instructions { swap_id, .. } | Self::Transaction { swap_id, .. } => *swap_id = id,
+        }
+        self
+    }
+
+    pub fn with_compute_budget(self, budget: ComputeBudget) -> Self {
+        match self {
+            Self::Instructions {
+                instructions,
+                address_lookup_tables,
+                compute_budget,
+                phases,
+                swap_id,
+                source_quote,
+            } => Self::Instructions {
+                instructions,
+                address_lookup_tables,
+                compute_budget: ComputeBudget {
+                    limit: budget.limit.or(compute_budget.limit),
+                    price_micro_lamports: budget
+                        .price_micro_lamports
+                        .or(compute_budget.price_micro_lamports),
+                },
+                phases,
+                swap_id,
+                source_quote,
+            },
+            transaction @ Self::Transaction { .. } => transaction,
+        }
+    }
+
+    // Sends the quote's `min_received` of the output token from `user`'s ATA to `transfer.destination`
+    // right after the swap, so the transfer cannot exceed what slippage protection guarantees. It
+    // goes before any instruction closing that ATA (the wSOL unwrap for SOL output).
+    pub fn with_output_transfer(
+        self,
+        quote: &QuoteResponse,
+        user: &Pubkey,
+        transfer: &PostSwapTransfer,
+    ) -> Result<Self, SwapError> {
+        let Self::Instructions {
+            mut instructions,
+            address_lookup_tables,
+            compute_budget,
+            mut phases,
+            swap_id,
+            source_quote,
+        } = self
+        else {
+            return Err(SwapError::InvalidRequest(
+                "cannot append a transfer to a prebuilt transaction".to_string(),
+            ));
+        };
+
+        let program = &transfer.token_program;
+        let source_ata = token::associated_token_address(user, &quote.output_mint, program);
+        let destination_ata =
+            token::associated_token_address(&transfer.destination, &quote.output_mint, program);
+        let mut added = Vec::new();
+        if transfer.create_destination_ata {
+            added.push(token::create_associated_token_account_idempotent(
+                user,
+                &transfer.destination,
+                &quote.output_mint,
+                program,
+            ));
+        }
+        added.push(token::transfer_checked_instruction(
+            program,
+            &source_ata,
+            &quote.output_mint,
+            &destination_ata,
+            user,
+            quote.min_received(),
+            transfer.decimals,
+        ));
+
+        let closes_source = |ix: &Instruction| token::closes_account(ix, &source_ata);
+        if let Some(phases) = &mut phases {
+            match phases.cleanup.iter().position(closes_source) {
+                Some(at) => {
+                    phases.cleanup.splice(at..at, added.iter().cloned());
+                }
+                None => phases.other.extend(added.iter().cloned()),
+            }
+        }
+        let at = instructions
+            .iter()
+            .position(closes_source)
+            .unwrap_or(instructions.len());
+        instructions.splice(at..at, added);
+
+        Ok(Self::Instructions {
+            instructions,
+            address_lookup_tables,
+            compute_budget,
+            phases,
+            swap_id,
+            source_quote,
+        })
+    }
+
+    // Tags the swap with an SPL Memo for accounting. A prebuilt transaction gets the memo
+    // compiled into its message; its signatures are reset since the message changed.
+    pub fn with_memo(self, memo: &str) -> Result<Self, SwapError> {
+        let memo_ix = token::memo_instruction(memo);
+        match self {
+            Self::Instructions {
+                mut instructions,
+                address_lookup_tables,
+                compute_budget,
+                mut phases,
+                swap_id,
+                source_quote,
+            } => {
+                if let Some(phases) = &mut phases {
+                    phases.other.push(memo_ix.clone());
+                }
+                instructions.push(memo_ix);
+                Ok(Self::Instructions {
+                    instructions,
+                    address_lookup_tables,
+                    compute_budget,
+                    phases,
+                    swap_id,
+                    source_quote,
+                })
+            }
+            Self::Transaction {
+                mut transaction,
+                last_valid_block_height,
+                compute_units,
+                swap_id,
+                source_quote,
+            } => {
+                append_unsigned_instruction(&mut transaction.message, memo_ix)?;
+                let num_signers = usize::from(transaction.message.header().num_required_signatures);
+                transaction.signatures = vec![Signature::default(); num_signers];
+                Ok(Self::Transaction {
+                    transaction,
+                    last_valid_block_height,
+                    compute_units,
+                    swap_id,
+                    source_quote,
+                })
+            }
+        }
+    }
+
     pub fn into_cpi(self, executor_program: Pubkey) -> Result<CpiSwapResult, SwapError> {
         match self {
             Self::Instructions {
@@ -133,35 +1050,232 @@ impl SwapResult {
         }
     }
 
-    pub fn into_unsigned_transaction(
-        self,
-        payer: &Pubkey,
-        blockhash: Hash,
-    ) -> Result<VersionedTransaction, SwapError> {
+    // For the Instructions variant the fee payer is only known once the message is
+    // compiled, so this lists the instruction-level signers in first-seen order.
+    pub fn required_signers(&self) -> Vec<Pubkey> {
         match self {
-            Self::Transaction {
-                mut transaction, ..
-            } => {
-                match &mut transaction.message {
-                    VersionedMessage::Legacy(m) => m.recent_blockhash = blockhash,
-                    VersionedMessage::V0(m) => m.recent_blockhash = blockhash,
-                }
+            Self::Transaction { transaction, .. } => {
                 let num_signers = transaction.message.header().num_required_signatures as usize;
-                transaction.signatures = vec![Signature::default(); num_signers];
-                Ok(transaction)
+                transaction
+                    .message
+                    .static_account_keys()
+                    .iter()
+                    .take(num_signers)
+                    .copied()
+                    .collect()
             }
-            Self::Instructions {
+            Self::Instructions { instructions, .. } => {
+                let mut signers: Vec<Pubkey> = Vec::new();
+                for meta in instructions.iter().flat_map(|ix| &ix.accounts) {
+                    if meta.is_signer && !signers.contains(&meta.pubkey) {
+                        signers.push(meta.pubkey);
+                    }
+                }
+                signers
+            }
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        let units = |value: Option<String>| value.unwrap_or_else(|| "n/a".to_string());
+        match self {
+            Self::Instructions {
                 instructions,
                 address_lookup_tables,
+                compute_budget,
+                ..
+            } => format!(
+                "{} instructions · {} ALTs · CU limit: {} · CU price: {}",
+                instructions.len(),
+                address_lookup_tables.len(),
+                units(compute_budget.limit.map(|l| l.to_string())),
+                units(compute_budget.price_micro_lamports.map(|p| p.to_string())),
+            ),
+            Self::Transaction { transaction, .. } => {
+                let message = &transaction.message;
+                let keys = message.static_account_keys();
+                // The limit lives in the message's SetComputeUnitLimit instruction, if any.
+                let limit = message.instructions().iter().find_map(|ix| {
+                    let is_budget =
+                        keys.get(usize::from(ix.program_id_index)) == Some(&compute_budget::id());
+                    match ix.data.as_slice() {
+                        [ComputeBudget::SET_COMPUTE_UNIT_LIMIT, a, b, c, d] if is_budget => {
+                            Some(u32::from_le_bytes([*a, *b, *c, *d]))
+                        }
+                        _ => None,
+                    }
+                });
+                let bytes = bincode::serialized_size(transaction)
+                    .map_or("n/a".to_string(), |b| b.to_string());
+                format!(
+                    "transaction · {} instructions · {} ALTs · CU limit: {} · {} signers · {bytes} bytes",
+                    message.instructions().len(),
+                    message.address_table_lookups().map_or(0, <[_]>::len),
+                    units(limit.map(|l| l.to_string())),
+                    message.header().num_required_signatures,
+                )
+            }
+        }
+    }
+
+    // Strict callers run this before compiling: duplicate compute budget instructions that
+    // disagree are an error here, while compiling only logs them and keeps one.
+    pub fn check_compute_budget(&self) -> Result<(), SwapError> {
+        match self {
+            Self::Instructions { instructions, .. } => {
+                ComputeBudget::conflict(instructions).map_or(Ok(()), Err)
+            }
+            Self::Transaction { .. } => Ok(()),
+        }
+    }
+
+    pub fn into_unsigned_transaction(
+        self,
+        payer: &Pubkey,
+        blockhash: Hash,
+    ) -> Result<VersionedTransaction, SwapError> {
+        self.into_unsigned_transaction_within(payer, blockhash, MAX_TRANSACTION_BYTES)
+    }
+
+    // Size is measured with placeholder signatures, which serialize at full length.
+    pub fn into_unsigned_transaction_within(
+        self,
+        payer: &Pubkey,
+        blockhash: Hash,
+        max_bytes: usize,
+    ) -> Result<VersionedTransaction, SwapError> {
+        let transaction = self.compile_unsigned(payer, blockhash, None, false)?;
+        ensure_fits(transaction, max_bytes)
+    }
+
+    // The unsigned transaction as base64 wire bytes, e.g. for a wallet to sign; size-checked like
+    // `into_unsigned_transaction`.
+    pub fn to_base64_transaction(
+        &self,
+        payer: &Pubkey,
+        blockhash: Hash,
+    ) -> Result<String, SwapError> {
+        let transaction = self.clone().into_unsigned_transaction(payer, blockhash)?;
+        let bytes = bincode::serialize(&transaction)
+            .map_err(|e| SwapError::Serialization(e.to_string()))?;
+        Ok(BASE64.encode(bytes))
+    }
+
+    // For multisig flows (e.g. a Squads vault): the vault PDA is the payer, but a PDA cannot sign
+    // a transaction, so there is nothing to put in signature slots. The multisig program takes
+    // the compiled message and signs for the vault with its seeds when executing it, so only the
+    // message is returned. Its size is not checked against a packet: the multisig program stores
+    // it across its own transactions.
+    pub fn into_unsigned_message(
+        self,
+        payer: &Pubkey,
+        blockhash: Hash,
+    ) -> Result<VersionedMessage, SwapError> {
+        Ok(self
+            .compile_unsigned(payer, blockhash, None, false)?
+            .message)
+    }
+
+    // For programs or wallets that reject v0: a legacy message has no lookup tables, so every
+    // account the tables would have resolved is listed inline, making the transaction larger.
+    // A prebuilt v0 transaction cannot be converted without resolving its tables and is rejected.
+    pub fn into_legacy_transaction(
+        self,
+        payer: &Pubkey,
+        blockhash: Hash,
+    ) -> Result<VersionedTransaction, SwapError> {
+        let transaction = self.compile_unsigned(payer, blockhash, None, true)?;
+        ensure_fits(transaction, MAX_TRANSACTION_BYTES)
+    }
+
+    // For offline signing or delayed submission: the transaction stays valid until the nonce
+    // advances, not for ~150 blocks. The runtime requires the advance to be the very first
+    // instruction, ahead of compute budget instructions. Prebuilt transactions are rejected
+    // since their instructions cannot be reordered.
+    pub fn into_unsigned_transaction_with_nonce(
+        self,
+        payer: &Pubkey,
+        nonce_account: &Pubkey,
+        nonce_authority: &Pubkey,
+        nonce_value: Hash,
+    ) -> Result<VersionedTransaction, SwapError> {
+        let advance = token::advance_nonce_instruction(nonce_account, nonce_authority);
+        let transaction = self.compile_unsigned(payer, nonce_value, Some(advance), false)?;
+        ensure_fits(transaction, MAX_TRANSACTION_BYTES)
+    }
+
+    fn compile_unsigned(
+        self,
+        payer: &Pubkey,
+        blockhash: Hash,
+        first_instruction: Option<Instruction>,
+        legacy: bool,
+    ) -> Result<VersionedTransaction, SwapError> {
+        match self {
+            Self::Transaction { .. } if first_instruction.is_some() => {
+                Err(SwapError::InvalidRequest(
+                    "cannot prepend a nonce advance to a prebuilt transaction".to_string(),
+                ))
+            }
+            Self::Transaction { transaction, .. }
+                if legacy && !matches!(transaction.message, VersionedMessage::Legacy(_)) =>
+            {
+                Err(SwapError::InvalidRequest(
+                    "cannot convert a prebuilt v0 transaction to legacy".to_string(),
+                ))
+            }
+            Self::Transaction {
+                mut transaction, ..
+            } => {
+                match &mut transaction.message {
+                    VersionedMessage::Legacy(m) => m.recent_blockhash = blockhash,
+                    VersionedMessage::V0(m) => m.recent_blockhash = blockhash,
+                }
+                let num_signers = transaction.message.header().num_required_signatures as usize;
+                transaction.signatures = vec![Signature::default(); num_signers];
+                Ok(transaction)
+            }
+            Self::Instructions {
+                mut instructions,
+                address_lookup_tables,
+                compute_budget,
                 ..
             } => {
+                if let Some(conflict) = ComputeBudget::conflict(&instructions) {
+                    warn!("{conflict}; keeping a single instruction");
+                }
+                compute_budget.dedup_instructions(&mut instructions);
+                let missing = compute_budget.missing_instructions(&instructions);
+                instructions.splice(0..0, first_instruction.into_iter().chain(missing));
+
+                if legacy {
+                    // Legacy compilation indexes accounts with a u8 and panics past that.
+                    let account_count = unique_account_count(payer, &instructions);
+                    if account_count > usize::from(u8::MAX) + 1 {
+                        return Err(SwapError::TooManyAccounts { account_count });
+                    }
+                    let message =
+                        Message::new_with_blockhash(&instructions, Some(payer), &blockhash);
+                    let num_signers = usize::from(message.header.num_required_signatures);
+                    return Ok(VersionedTransaction {
+                        signatures: vec![Signature::default(); num_signers],
+                        message: VersionedMessage::Legacy(message),
+                    });
+                }
+
                 let message = v0::Message::try_compile(
                     payer,
                     &instructions,
                     &address_lookup_tables,
                     blockhash,
                 )
-                .map_err(|e| SwapError::Solana(e.to_string()))?;
+                .map_err(|e| match e {
+                    CompileError::AccountIndexOverflow
+                    | CompileError::AddressTableLookupIndexOverflow => SwapError::TooManyAccounts {
+                        account_count: unique_account_count(payer, &instructions),
+                    },
+                    CompileError::UnknownInstructionKey(_) => SwapError::Solana(e.to_string()),
+                })?;
                 let num_signers = message.header.num_required_signatures as usize;
                 let message = VersionedMessage::V0(message);
                 Ok(VersionedTransaction {
@@ -173,15 +1287,207 @@ impl SwapResult {
     }
 }
 
+impl std::fmt::Display for SwapResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.summary())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JupiterTier {
+    Lite,
+    Pro,
+    Custom(String),
+}
+
+impl JupiterTier {
+    pub fn base_url(&self) -> &str {
+        match self {
+            Self::Lite => "https://lite-api.jup.ag/swap/v1",
+            Self::Pro => "https://api.jup.ag/swap/v1",
+            Self::Custom(url) => url,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteHop {
+    pub amm_key: Option<String>,
+    pub program_id: Option<String>,
+    pub label: Option<String>,
+    pub input_mint: Option<String>,
+    pub output_mint: Option<String>,
+    pub in_amount: Option<u64>,
+    pub out_amount: Option<u64>,
+    pub percent: Option<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PostSwapTransfer {
+    pub destination: Pubkey,
+    pub token_program: Pubkey,
+    // The output mint's decimals, checked by `TransferChecked`.
+    pub decimals: u8,
+    pub create_destination_ata: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcceptableOutput {
+    AtLeast(u64),
+    BpsOf { reference: u64, bps: u16 },
+}
+
+impl AcceptableOutput {
+    pub fn min_output(&self) -> u64 {
+        match *self {
+            Self::AtLeast(min) => min,
+            Self::BpsOf { reference, bps } => {
+                let min = u128::from(reference) * u128::from(bps) / 10_000;
+                u64::try_from(min).unwrap_or(u64::MAX)
+            }
+        }
+    }
+}
+
+// Slippage in whichever unit the caller thinks in; providers always receive bps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Slippage {
+    Bps(u16),
+    Percent(f64),
+}
+
+impl Slippage {
+    pub fn to_bps(self) -> Result<u16, SwapError> {
+        match self {
+            Self::Bps(bps) if bps <= 10_000 => Ok(bps),
+            Self::Percent(percent) if (0.0..=100.0).contains(&percent) => {
+                Ok((percent * 100.0).round() as u16)
+            }
+            Self::Bps(bps) => Err(SwapError::InvalidRequest(format!(
+                "slippage {bps} bps exceeds 10000"
+            ))),
+            Self::Percent(percent) => Err(SwapError::InvalidRequest(format!(
+                "slippage {percent}% is outside 0..=100"
+            ))),
+        }
+    }
+}
+
+// Opt-in re-quoting after a slippage failure: each retry raises slippage by `step_bps`
+// until `max_bps` has been tried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlippageEscalation {
+    pub step_bps: u16,
+    pub max_bps: u16,
+}
+
+impl SlippageEscalation {
+    pub fn next(&self, current_bps: u16) -> Option<u16> {
+        (current_bps < self.max_bps)
+            .then(|| current_bps.saturating_add(self.step_bps).min(self.max_bps))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuoteGuard {
+    pub max_impact_bps: Option<u16>,
+    pub min_output: Option<u64>,
+    pub max_input: Option<u64>,
+    // Counted as `hop_count`; quotes without a route plan (Titan) are not limited.
+    pub max_hops: Option<usize>,
+}
+
+impl QuoteGuard {
+    pub fn check(&self, quote: &QuoteResponse) -> Result<(), SwapError> {
+        if let (Some(max_bps), Some(impact_bps)) = (self.max_impact_bps, quote.price_impact_bps) {
+            if impact_bps > max_bps {
+                return Err(SwapError::PriceImpactTooHigh {
+                    impact_bps,
+                    max_bps,
+                });
+            }
+        }
+        if let Some(min) = self.min_output {
+            if quote.output_amount < min {
+                return Err(SwapError::OutputBelowMinimum {
+                    output: quote.output_amount,
+                    min,
+                });
+            }
+        }
+        if let Some(max) = self.max_input {
+            if quote.input_amount > max {
+                return Err(SwapError::InputAboveMaximum {
+                    input: quote.input_amount,
+                    max,
+                });
+            }
+        }
+        if let Some(max) = self.max_hops {
+            let hops = quote.hop_count();
+            if hops > max {
+                return Err(SwapError::RouteTooLong { hops, max });
+            }
+        }
+        Ok(())
+    }
+}
+
+// An oracle or reference price to judge quotes against: a quote whose `deviation_from` the
+// reference exceeds `max_deviation_bps` in either direction is rejected. A quote far above an
+// oracle is as suspect as one far below it (a stale oracle or a bad route).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReferencePrice {
+    // Output per unit of input in raw token units, as `QuoteResponse::price`.
+    pub price: f64,
+    pub max_deviation_bps: u16,
+}
+
+impl ReferencePrice {
+    pub fn check(&self, quote: &QuoteResponse) -> Result<(), SwapError> {
+        let deviation_bps = quote.deviation_from(self.price);
+        // A NaN deviation means there was no comparable price, which is no pass either.
+        if deviation_bps.is_nan() || deviation_bps.abs() > f64::from(self.max_deviation_bps) {
+            return Err(SwapError::PriceDeviationTooHigh {
+                deviation_bps,
+                max_bps: self.max_deviation_bps,
+            });
+        }
+        Ok(())
+    }
+}
+
+// Computes auth headers (HMAC, Ed25519, ...) for a provider request right before it is sent.
+// `path` includes the query string; `body` is empty for GETs.
+pub trait RequestSigner: Send + Sync {
+    fn sign(&self, method: &reqwest::Method, path: &str, body: &[u8]) -> Vec<(HeaderName, String)>;
+}
+
 pub struct SwapConfig {
     pub default_slippage_bps: u16,
     pub jupiter_api_url: Option<String>,
     pub jupiter_api_key: Option<String>,
+    pub jupiter_tier: Option<JupiterTier>,
+    pub jupiter_use_token_ledger: Option<bool>,
+    pub jupiter_dynamic_cu_limit: Option<bool>,
     pub titan_ws_url: Option<String>,
     pub titan_token: Option<String>,
     pub dflow_api_url: Option<String>,
     pub dflow_api_key: Option<String>,
     pub dflow_max_route_length: Option<u32>,
+    pub dflow_priority_fee_lamports: Option<u64>,
+    pub dflow_jito_tip_lamports: Option<u64>,
+    pub quote_guard: Option<QuoteGuard>,
+    pub swap_cache_ttl: Option<Duration>,
+    pub reserve_sol_for_fees: Option<u64>,
+    pub blockhash_refresh_retries: Option<u32>,
+    pub blockhash_cache_ttl: Option<Duration>,
+    pub slippage_escalation: Option<SlippageEscalation>,
+    pub record_dir: Option<PathBuf>,
+    pub rank_by_net_output: Option<bool>,
+    pub max_response_bytes: Option<usize>,
+    pub jupiter_request_signer: Option<Arc<dyn RequestSigner>>,
+    pub dflow_request_signer: Option<Arc<dyn RequestSigner>>,
 }
 
 #[cfg(test)]
@@ -201,10 +1507,58 @@ mod tests {
         SwapResult::Instructions {
             instructions,
             address_lookup_tables: vec![],
-            compute_units: Some(200_000),
+            compute_budget: ComputeBudget {
+                limit: Some(200_000),
+                price_micro_lamports: None,
+            },
+            phases: None,
+            swap_id: Uuid::new_v4(),
+            source_quote: None,
         }
     }
 
+    #[test]
+    fn summary_reports_instruction_counts() {
+        let ix = Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: vec![AccountMeta::new(Pubkey::new_unique(), true)],
+            data: vec![1],
+        };
+        let result = make_swap_result(vec![ix.clone(), ix]);
+
+        assert_eq!(
+            result.summary(),
+            "2 instructions · 0 ALTs · CU limit: 200000 · CU price: n/a"
+        );
+        assert_eq!(result.to_string(), result.summary());
+    }
+
+    #[test]
+    fn summary_reports_transaction_signers_and_size() {
+        let ix = Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: vec![AccountMeta::new(Pubkey::new_unique(), true)],
+            data: vec![1],
+        };
+        let transaction = make_swap_result(vec![ix])
+            .into_unsigned_transaction(&Pubkey::new_unique(), Hash::default())
+            .expect("transaction");
+        let bytes = bincode::serialized_size(&transaction).expect("size");
+        let result = SwapResult::Transaction {
+            transaction,
+            last_valid_block_height: 0,
+            compute_units: None,
+            swap_id: Uuid::new_v4(),
+            source_quote: None,
+        };
+
+        let summary = result.summary();
+        assert!(summary.contains("2 instructions"), "{summary}");
+        assert!(summary.contains("CU limit: 200000"), "{summary}");
+        assert!(summary.contains("2 signers"), "{summary}");
+        assert!(summary.contains(&format!("{bytes} bytes")), "{summary}");
+    }
+
     #[test]
     fn into_cpi_splits_pre_swap_post() {
         let pre_program = Pubkey::new_unique();
@@ -307,7 +1661,10 @@ mod tests {
                 key: alt_key,
                 addresses: vec![alt_address],
             }],
-            compute_units: None,
+            compute_budget: ComputeBudget::default(),
+            phases: None,
+            swap_id: Uuid::new_v4(),
+            source_quote: None,
         };
 
         let cpi = result.into_cpi(executor).expect("into_cpi should succeed");
@@ -336,8 +1693,8 @@ mod tests {
         let payer = Pubkey::new_unique();
         let ix = make_instruction(payer, &[1]);
 
-        let msg = v0::Message::try_compile(&payer, &[ix], &[], Hash::default())
-            .expect("should compile");
+        let msg =
+            v0::Message::try_compile(&payer, &[ix], &[], Hash::default()).expect("should compile");
         let versioned_msg = VersionedMessage::V0(msg);
         let tx = VersionedTransaction {
             signatures: vec![Signature::default()],
@@ -346,6 +1703,9 @@ mod tests {
         let result = SwapResult::Transaction {
             transaction: tx,
             last_valid_block_height: 100,
+            compute_units: None,
+            swap_id: Uuid::new_v4(),
+            source_quote: None,
         };
 
         let err = result.into_cpi(payer).expect_err("should fail");
@@ -402,37 +1762,1255 @@ mod tests {
     }
 
     #[test]
-    fn quote_response_serializes_to_json() {
-        let quote = QuoteResponse {
-            provider: Provider::Jupiter,
-            input_mint: Pubkey::new_unique(),
-            output_mint: Pubkey::new_unique(),
-            input_amount: 1_000_000,
-            output_amount: 500_000,
-            price_impact_bps: Some(15),
-            slippage_bps: 100,
-            provider_data: serde_json::json!({"route_plan": []}),
+    fn required_signers_lists_transaction_header_signers() {
+        let payer = Pubkey::new_unique();
+        let fee_authority = Pubkey::new_unique();
+        let program = Pubkey::new_unique();
+        let ix = Instruction {
+            program_id: program,
+            accounts: vec![
+                AccountMeta::new(fee_authority, true),
+                AccountMeta::new(Pubkey::new_unique(), false),
+            ],
+            data: vec![1],
         };
 
-        let json = serde_json::to_value(&quote).expect("should serialize");
+        let msg =
+            v0::Message::try_compile(&payer, &[ix], &[], Hash::default()).expect("should compile");
+        let num_signers = msg.header.num_required_signatures as usize;
+        let tx = VersionedTransaction {
+            signatures: vec![Signature::default(); num_signers],
+            message: VersionedMessage::V0(msg),
+        };
+        let result = SwapResult::Transaction {
+            transaction: tx,
+            last_valid_block_height: 100,
+            compute_units: None,
+            swap_id: Uuid::new_v4(),
+            source_quote: None,
+        };
 
-        assert_eq!(json["provider"], "Jupiter");
-        assert_eq!(json["input_amount"], 1_000_000);
-        assert_eq!(json["output_amount"], 500_000);
-        assert_eq!(json["price_impact_bps"], 15);
-        assert_eq!(json["slippage_bps"], 100);
+        let signers = result.required_signers();
+
+        assert_eq!(signers.len(), num_signers);
+        assert_eq!(signers, vec![payer, fee_authority]);
     }
 
     #[test]
-    fn provider_display_and_serialize_match() {
-        for (provider, expected) in [
-            (Provider::Jupiter, "Jupiter"),
-            (Provider::Titan, "Titan"),
-            (Provider::Dflow, "Dflow"),
-        ] {
-            assert_eq!(provider.to_string(), expected);
-            let json = serde_json::to_value(provider).expect("should serialize");
-            assert_eq!(json, expected);
+    fn required_signers_dedupes_instruction_signers() {
+        let user = Pubkey::new_unique();
+        let co_signer = Pubkey::new_unique();
+        let result = make_swap_result(vec![
+            Instruction {
+                program_id: Pubkey::new_unique(),
+                accounts: vec![
+                    AccountMeta::new(user, true),
+                    AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                ],
+                data: vec![],
+            },
+            Instruction {
+                program_id: Pubkey::new_unique(),
+                accounts: vec![
+                    AccountMeta::new_readonly(co_signer, true),
+                    AccountMeta::new(user, true),
+                ],
+                data: vec![],
+            },
+        ]);
+
+        assert_eq!(result.required_signers(), vec![user, co_signer]);
+    }
+
+    #[test]
+    fn required_signers_include_accounts_signing_in_any_instruction() {
+        let user = Pubkey::new_unique();
+        let pda = Pubkey::new_unique();
+        let readonly = Pubkey::new_unique();
+        let result = make_swap_result(vec![
+            Instruction {
+                program_id: Pubkey::new_unique(),
+                accounts: vec![
+                    AccountMeta::new(pda, false),
+                    AccountMeta::new_readonly(readonly, false),
+                ],
+                data: vec![],
+            },
+            Instruction {
+                program_id: Pubkey::new_unique(),
+                accounts: vec![
+                    AccountMeta::new_readonly(readonly, false),
+                    AccountMeta::new(pda, true),
+                    AccountMeta::new_readonly(user, true),
+                ],
+                data: vec![],
+            },
+        ]);
+
+        assert_eq!(result.required_signers(), vec![pda, user]);
+    }
+
+    fn compute_budget_ixs(tx: &VersionedTransaction) -> Vec<(u8, Vec<u8>)> {
+        let keys = tx.message.static_account_keys();
+        tx.message
+            .instructions()
+            .iter()
+            .filter(|ix| keys[ix.program_id_index as usize] == compute_budget::id())
+            .map(|ix| (ix.data[0], ix.data[1..].to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn into_unsigned_transaction_emits_compute_budget_instructions() {
+        let payer = Pubkey::new_unique();
+        let result = make_swap_result(vec![make_instruction(Pubkey::new_unique(), &[1])])
+            .with_compute_budget(ComputeBudget {
+                limit: None,
+                price_micro_lamports: Some(10_000),
+            });
+
+        let tx = result
+            .into_unsigned_transaction(&payer, Hash::default())
+            .expect("should compile");
+
+        let budget = compute_budget_ixs(&tx);
+        assert_eq!(
+            budget,
+            vec![
+                (2, 200_000u32.to_le_bytes().to_vec()),
+                (3, 10_000u64.to_le_bytes().to_vec()),
+            ]
+        );
+        assert_eq!(tx.message.instructions().len(), 3);
+    }
+
+    #[test]
+    fn nonce_transaction_advances_nonce_first_and_uses_nonce_as_blockhash() {
+        let payer = Pubkey::new_unique();
+        let nonce_account = Pubkey::new_unique();
+        let nonce_value = Hash::new_unique();
+        let result = make_swap_result(vec![make_instruction(Pubkey::new_unique(), &[1])])
+            .with_compute_budget(ComputeBudget {
+                limit: Some(300_000),
+                price_micro_lamports: None,
+            });
+
+        let tx = result
+            .into_unsigned_transaction_with_nonce(&payer, &nonce_account, &payer, nonce_value)
+            .expect("should compile");
+
+        assert_eq!(*tx.message.recent_blockhash(), nonce_value);
+        let keys = tx.message.static_account_keys();
+        let first = &tx.message.instructions()[0];
+        assert_eq!(keys[first.program_id_index as usize], token::SYSTEM_PROGRAM);
+        assert_eq!(first.data, 4u32.to_le_bytes().to_vec());
+        assert_eq!(keys[first.accounts[0] as usize], nonce_account);
+        assert_eq!(
+            keys[tx.message.instructions()[1].program_id_index as usize],
+            compute_budget::id()
+        );
+    }
+
+    #[test]
+    fn legacy_transaction_inlines_lookup_table_accounts() {
+        let payer = Pubkey::new_unique();
+        let table_account = Pubkey::new_unique();
+        let program = Pubkey::new_unique();
+        let result = SwapResult::Instructions {
+            instructions: vec![Instruction {
+                program_id: program,
+                accounts: vec![AccountMeta::new(table_account, false)],
+                data: vec![1],
+            }],
+            address_lookup_tables: vec![AddressLookupTableAccount {
+                key: Pubkey::new_unique(),
+                addresses: vec![table_account],
+            }],
+            compute_budget: ComputeBudget::default(),
+            phases: None,
+            swap_id: Uuid::new_v4(),
+            source_quote: None,
+        };
+
+        let tx = result
+            .clone()
+            .into_legacy_transaction(&payer, Hash::default())
+            .expect("should compile");
+
+        assert!(matches!(tx.message, VersionedMessage::Legacy(_)));
+        let keys = tx.message.static_account_keys();
+        assert_eq!(keys[0], payer);
+        assert!(keys.contains(&table_account));
+        assert!(keys.contains(&program));
+        assert!(tx.message.address_table_lookups().is_none());
+
+        let v0 = result
+            .into_unsigned_transaction(&payer, Hash::default())
+            .expect("should compile");
+        assert!(!v0.message.static_account_keys().contains(&table_account));
+    }
+
+    #[test]
+    fn unsigned_message_has_pda_payer_without_signature_slots() {
+        let (vault, _) = Pubkey::find_program_address(&[b"vault", &[0]], &Pubkey::new_unique());
+        let swap_ix = Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: vec![
+                AccountMeta::new(vault, true),
+                AccountMeta::new(Pubkey::new_unique(), false),
+            ],
+            data: vec![1],
+        };
+        let blockhash = Hash::new_unique();
+
+        let message = make_swap_result(vec![swap_ix])
+            .into_unsigned_message(&vault, blockhash)
+            .expect("should compile");
+
+        assert_eq!(message.static_account_keys()[0], vault);
+        assert_eq!(message.header().num_required_signatures, 1);
+        assert_eq!(*message.recent_blockhash(), blockhash);
+        assert!(matches!(message, VersionedMessage::V0(_)));
+    }
+
+    #[test]
+    fn nonce_is_rejected_for_prebuilt_transaction() {
+        let result = SwapResult::Transaction {
+            transaction: VersionedTransaction::default(),
+            last_valid_block_height: 0,
+            compute_units: None,
+            swap_id: Uuid::new_v4(),
+            source_quote: None,
+        };
+
+        let err = result
+            .into_unsigned_transaction_with_nonce(
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                Hash::new_unique(),
+            )
+            .expect_err("prebuilt");
+        assert!(matches!(err, SwapError::InvalidRequest(_)), "got {err:?}");
+    }
+
+    #[test]
+    fn into_unsigned_transaction_keeps_existing_compute_budget_instructions() {
+        let payer = Pubkey::new_unique();
+        let result = make_swap_result(vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(300_000),
+            make_instruction(Pubkey::new_unique(), &[1]),
+        ]);
+
+        let tx = result
+            .into_unsigned_transaction(&payer, Hash::default())
+            .expect("should compile");
+
+        assert_eq!(
+            compute_budget_ixs(&tx),
+            vec![(2, 300_000u32.to_le_bytes().to_vec())]
+        );
+    }
+
+    #[test]
+    fn into_unsigned_transaction_dedups_compute_budget_instructions() {
+        let payer = Pubkey::new_unique();
+        let duplicated = vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(500_000),
+            ComputeBudgetInstruction::set_compute_unit_price(7),
+            ComputeBudgetInstruction::set_compute_unit_limit(300_000),
+            ComputeBudgetInstruction::set_compute_unit_price(7),
+            make_instruction(Pubkey::new_unique(), &[1]),
+        ];
+
+        let strict = make_swap_result(duplicated.clone())
+            .check_compute_budget()
+            .expect_err("limits disagree");
+        assert!(
+            matches!(
+                &strict,
+                SwapError::ConflictingComputeBudget { instruction, values }
+                    if instruction == "SetComputeUnitLimit" && *values == vec![500_000, 300_000]
+            ),
+            "got {strict:?}"
+        );
+
+        // make_swap_result's 200k limit stands in for the caller's override.
+        let tx = make_swap_result(duplicated.clone())
+            .into_unsigned_transaction(&payer, Hash::default())
+            .expect("should compile");
+        assert_eq!(
+            compute_budget_ixs(&tx),
+            vec![
+                (2, 200_000u32.to_le_bytes().to_vec()),
+                (3, 7u64.to_le_bytes().to_vec()),
+            ]
+        );
+        assert_eq!(tx.message.instructions().len(), 3);
+
+        let SwapResult::Instructions {
+            instructions,
+            address_lookup_tables,
+            swap_id,
+            ..
+        } = make_swap_result(duplicated)
+        else {
+            unreachable!("built as instructions");
+        };
+        let tx = SwapResult::Instructions {
+            instructions,
+            address_lookup_tables,
+            compute_budget: ComputeBudget::default(),
+            phases: None,
+            swap_id,
+            source_quote: None,
+        }
+        .into_unsigned_transaction(&payer, Hash::default())
+        .expect("should compile");
+        assert_eq!(
+            compute_budget_ixs(&tx)[0],
+            (2, 500_000u32.to_le_bytes().to_vec())
+        );
+        assert_eq!(tx.message.instructions().len(), 3);
+    }
+
+    #[test]
+    fn single_compute_budget_instructions_pass_strict_check() {
+        let result = make_swap_result(vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(300_000),
+            make_instruction(Pubkey::new_unique(), &[1]),
+        ]);
+
+        assert!(result.check_compute_budget().is_ok());
+    }
+
+    #[test]
+    fn with_compute_budget_caller_values_win() {
+        let result = make_swap_result(vec![]).with_compute_budget(ComputeBudget {
+            limit: Some(400_000),
+            price_micro_lamports: Some(1),
+        });
+
+        let SwapResult::Instructions { compute_budget, .. } = result else {
+            unreachable!("built as instructions");
+        };
+        assert_eq!(compute_budget.limit, Some(400_000));
+        assert_eq!(compute_budget.price_micro_lamports, Some(1));
+    }
+
+    #[test]
+    fn quote_response_serializes_to_json() {
+        let quote = QuoteResponse {
+            provider: Provider::Jupiter,
+            input_mint: Pubkey::new_unique(),
+            output_mint: Pubkey::new_unique(),
+            input_amount: 1_000_000,
+            output_amount: 500_000,
+            price_impact_bps: Some(15),
+            slippage_bps: 100,
+            slippage_adjusted: false,
+            provider_data: serde_json::json!({"route_plan": []}),
+            meta: QuoteMeta::default(),
+            swap_id: Uuid::new_v4(),
+        };
+
+        let json = serde_json::to_value(&quote).expect("should serialize");
+
+        assert_eq!(json["provider"], "Jupiter");
+        assert_eq!(json["input_amount"], 1_000_000);
+        assert_eq!(json["output_amount"], 500_000);
+        assert_eq!(json["price_impact_bps"], 15);
+        assert_eq!(json["slippage_bps"], 100);
+    }
+
+    fn make_quote(provider: Provider, provider_data: serde_json::Value) -> QuoteResponse {
+        QuoteResponse {
+            provider,
+            input_mint: Pubkey::new_unique(),
+            output_mint: Pubkey::new_unique(),
+            input_amount: 1_000_000,
+            output_amount: 500_000,
+            price_impact_bps: Some(15),
+            slippage_bps: 100,
+            slippage_adjusted: false,
+            provider_data,
+            meta: QuoteMeta::default(),
+            swap_id: Uuid::new_v4(),
+        }
+    }
+
+    #[test]
+    fn output_transfer_is_appended_with_min_received() {
+        let quote = make_quote(
+            Provider::Jupiter,
+            serde_json::json!({"otherAmountThreshold": "497000"}),
+        );
+        let user = Pubkey::new_unique();
+        let transfer = PostSwapTransfer {
+            destination: Pubkey::new_unique(),
+            token_program: token::TOKEN_PROGRAM,
+            decimals: 6,
+            create_destination_ata: true,
+        };
+        let swap_ix = make_instruction(Pubkey::new_unique(), &[9]);
+
+        let result = make_swap_result(vec![swap_ix.clone()])
+            .with_output_transfer(&quote, &user, &transfer)
+            .expect("instructions variant");
+
+        let SwapResult::Instructions { instructions, .. } = result else {
+            unreachable!("built as instructions");
+        };
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(instructions[0], swap_ix);
+        assert_eq!(instructions[1].program_id, token::ASSOCIATED_TOKEN_PROGRAM);
+
+        let transfer_ix = &instructions[2];
+        let destination_ata = token::associated_token_address(
+            &transfer.destination,
+            &quote.output_mint,
+            &token::TOKEN_PROGRAM,
+        );
+        assert_eq!(transfer_ix.program_id, token::TOKEN_PROGRAM);
+        assert_eq!(transfer_ix.accounts[1].pubkey, quote.output_mint);
+        assert_eq!(transfer_ix.accounts[2].pubkey, destination_ata);
+        assert_eq!(transfer_ix.accounts[3].pubkey, user);
+        assert_eq!(transfer_ix.data[0], 12);
+        assert_eq!(transfer_ix.data[1..9], 497_000_u64.to_le_bytes());
+        assert_eq!(transfer_ix.data[9], 6);
+    }
+
+    #[test]
+    fn output_transfer_goes_before_the_wsol_unwrap() {
+        let mut quote = make_quote(Provider::Jupiter, serde_json::json!({}));
+        quote.output_mint = token::NATIVE_MINT;
+        let user = Pubkey::new_unique();
+        let transfer = PostSwapTransfer {
+            destination: Pubkey::new_unique(),
+            token_program: token::TOKEN_PROGRAM,
+            decimals: 9,
+            create_destination_ata: false,
+        };
+        let swap_ix = make_instruction(Pubkey::new_unique(), &[9]);
+        let unwrap = token::unwrap_sol_instruction(&user);
+        let result = SwapResult::Instructions {
+            instructions: vec![swap_ix.clone(), unwrap.clone()],
+            address_lookup_tables: vec![],
+            compute_budget: ComputeBudget::default(),
+            phases: Some(InstructionPhases {
+                swap: vec![swap_ix.clone()],
+                cleanup: vec![unwrap.clone()],
+                ..InstructionPhases::default()
+            }),
+            swap_id: Uuid::new_v4(),
+            source_quote: None,
+        };
+
+        let result = result
+            .with_output_transfer(&quote, &user, &transfer)
+            .expect("instructions variant");
+
+        let SwapResult::Instructions {
+            instructions,
+            phases: Some(phases),
+            ..
+        } = result
+        else {
+            unreachable!("built as instructions with phases");
+        };
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(instructions[0], swap_ix);
+        assert_eq!(instructions[1].data[0], 12);
+        assert_eq!(instructions[2], unwrap);
+        assert_eq!(phases.flatten(), instructions);
+    }
+
+    #[test]
+    fn memo_is_appended_to_instructions() {
+        let swap_ix = make_instruction(Pubkey::new_unique(), &[9]);
+
+        let result = make_swap_result(vec![swap_ix.clone()])
+            .with_memo("invoice 42 ✓")
+            .expect("instructions variant");
+
+        let SwapResult::Instructions { instructions, .. } = result else {
+            unreachable!("built as instructions");
+        };
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[0], swap_ix);
+        assert_eq!(instructions[1].program_id, token::MEMO_PROGRAM);
+        assert!(instructions[1].accounts.is_empty());
+        assert_eq!(instructions[1].data, "invoice 42 ✓".as_bytes());
+    }
+
+    #[test]
+    fn memo_is_compiled_into_prebuilt_transaction() {
+        let payer = Pubkey::new_unique();
+        let table_account = Pubkey::new_unique();
+        let swap_ix = Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: vec![
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(table_account, false),
+            ],
+            data: vec![1],
+        };
+        let table = AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: vec![table_account],
+        };
+        let message = v0::Message::try_compile(&payer, &[swap_ix], &[table], Hash::default())
+            .expect("should compile");
+        let result = SwapResult::Transaction {
+            transaction: VersionedTransaction {
+                signatures: vec![Signature::new_unique()],
+                message: VersionedMessage::V0(message),
+            },
+            last_valid_block_height: 100,
+            compute_units: None,
+            swap_id: Uuid::new_v4(),
+            source_quote: None,
+        };
+
+        let result = result.with_memo("swap-7").expect("transaction variant");
+
+        let SwapResult::Transaction { transaction, .. } = result else {
+            unreachable!("built as transaction");
+        };
+        assert_eq!(transaction.signatures, vec![Signature::default()]);
+        let keys = transaction.message.static_account_keys();
+        let instructions = transaction.message.instructions();
+        assert_eq!(instructions.len(), 2);
+        let memo = &instructions[1];
+        assert_eq!(
+            keys[usize::from(memo.program_id_index)],
+            token::MEMO_PROGRAM
+        );
+        assert_eq!(memo.data, b"swap-7");
+        // the lookup-table account still resolves to the first loaded address
+        assert_eq!(usize::from(instructions[0].accounts[1]), keys.len());
+        assert_eq!(
+            transaction.message.header().num_readonly_unsigned_accounts,
+            2
+        );
+    }
+
+    #[test]
+    fn output_transfer_skips_ata_creation_when_not_requested() {
+        let quote = make_quote(Provider::Titan, serde_json::json!({}));
+        let transfer = PostSwapTransfer {
+            destination: Pubkey::new_unique(),
+            token_program: token::TOKEN_2022_PROGRAM,
+            decimals: 6,
+            create_destination_ata: false,
+        };
+
+        let result = make_swap_result(vec![])
+            .with_output_transfer(&quote, &Pubkey::new_unique(), &transfer)
+            .expect("instructions variant");
+
+        let SwapResult::Instructions { instructions, .. } = result else {
+            unreachable!("built as instructions");
+        };
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(instructions[0].program_id, token::TOKEN_2022_PROGRAM);
+    }
+
+    #[test]
+    fn oversized_instructions_report_too_many_accounts() {
+        let accounts = (0..300)
+            .map(|_| AccountMeta::new_readonly(Pubkey::new_unique(), false))
+            .collect();
+        let ix = Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts,
+            data: vec![],
+        };
+        let result = SwapResult::Instructions {
+            instructions: vec![ix],
+            address_lookup_tables: vec![],
+            compute_budget: ComputeBudget::default(),
+            phases: None,
+            swap_id: Uuid::new_v4(),
+            source_quote: None,
+        };
+
+        let err = result
+            .into_unsigned_transaction(&Pubkey::new_unique(), Hash::default())
+            .expect_err("too many accounts");
+
+        assert!(
+            matches!(err, SwapError::TooManyAccounts { account_count: 302 }),
+            "got {err:?}"
+        );
+        assert!(err.to_string().contains("maxAccounts"));
+    }
+
+    #[test]
+    fn oversized_transaction_reports_byte_count() {
+        let ix = Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: vec![],
+            data: vec![0; 1_300],
+        };
+        let result = SwapResult::Instructions {
+            instructions: vec![ix],
+            address_lookup_tables: vec![],
+            compute_budget: ComputeBudget::default(),
+            phases: None,
+            swap_id: Uuid::new_v4(),
+            source_quote: None,
+        };
+
+        let err = result
+            .clone()
+            .into_unsigned_transaction(&Pubkey::new_unique(), Hash::default())
+            .expect_err("over the packet limit");
+        assert!(matches!(
+            result.to_base64_transaction(&Pubkey::new_unique(), Hash::default()),
+            Err(SwapError::TransactionTooLarge { .. })
+        ));
+
+        let SwapError::TransactionTooLarge { bytes, limit } = err else {
+            unreachable!("expected TransactionTooLarge, got {err:?}");
+        };
+        assert_eq!(limit, MAX_TRANSACTION_BYTES);
+        assert!(bytes > 1_300, "got {bytes}");
+
+        let tx = result
+            .into_unsigned_transaction_within(&Pubkey::new_unique(), Hash::default(), bytes)
+            .expect("fits a raised limit");
+        assert_eq!(bincode::serialized_size(&tx).expect("size") as usize, bytes);
+    }
+
+    #[test]
+    fn base64_transaction_decodes_to_the_unsigned_transaction() {
+        let result = make_swap_result(vec![make_instruction(Pubkey::new_unique(), &[1])]);
+        let payer = Pubkey::new_unique();
+
+        let encoded = result
+            .to_base64_transaction(&payer, Hash::default())
+            .expect("fits");
+
+        let bytes = BASE64.decode(encoded).expect("base64");
+        let decoded: VersionedTransaction = bincode::deserialize(&bytes).expect("transaction");
+        let expected = result
+            .into_unsigned_transaction(&payer, Hash::default())
+            .expect("fits");
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn provider_capabilities_are_declared() {
+        let jupiter = Provider::Jupiter.capabilities();
+        assert!(jupiter.exact_out && jupiter.swap && jupiter.instructions);
+        assert!(jupiter.address_lookup_tables);
+        assert!(!jupiter.prebuilt_transaction && !jupiter.streaming);
+
+        let titan = Provider::Titan.capabilities();
+        assert!(titan.exact_out && titan.swap && titan.instructions);
+        assert!(titan.address_lookup_tables && titan.streaming);
+        assert!(!titan.prebuilt_transaction);
+
+        let dflow = Provider::Dflow.capabilities();
+        assert!(dflow.swap && dflow.prebuilt_transaction);
+        assert!(!dflow.exact_out && !dflow.instructions && !dflow.streaming);
+
+        assert!(jupiter.supports(Capability::ExactOut));
+        assert!(!dflow.supports(Capability::ExactOut));
+        assert!(titan.supports(Capability::Streaming));
+    }
+
+    #[test]
+    fn split_amount_assigns_remainder_to_last_slice() {
+        let slices = split_amount(1_000, 3);
+        assert_eq!(slices, vec![333, 333, 334]);
+        assert_eq!(slices.iter().sum::<u64>(), 1_000);
+
+        assert_eq!(split_amount(2, 5), vec![1, 1]);
+        assert!(split_amount(0, 3).is_empty());
+        assert!(split_amount(1_000, 0).is_empty());
+        assert_eq!(split_amount(u64::MAX, u8::MAX).len(), 255);
+    }
+
+    #[test]
+    fn slippage_escalation_steps_up_to_cap() {
+        let escalation = SlippageEscalation {
+            step_bps: 100,
+            max_bps: 250,
+        };
+        assert_eq!(escalation.next(50), Some(150));
+        assert_eq!(escalation.next(150), Some(250));
+        assert_eq!(escalation.next(250), None);
+    }
+
+    #[test]
+    fn min_received_prefers_provider_threshold() {
+        let quote = make_quote(
+            Provider::Jupiter,
+            serde_json::json!({"otherAmountThreshold": "497000"}),
+        );
+        assert_eq!(quote.min_received(), 497_000);
+
+        let numeric = make_quote(
+            Provider::Dflow,
+            serde_json::json!({"otherAmountThreshold": 496_000}),
+        );
+        assert_eq!(numeric.min_received(), 496_000);
+    }
+
+    #[test]
+    fn min_received_computes_from_slippage_without_threshold() {
+        let quote = make_quote(Provider::Titan, serde_json::json!({}));
+        assert_eq!(quote.min_received(), apply_slippage(500_000, 100));
+        assert_eq!(quote.min_received(), 495_000);
+
+        let exact_out = make_quote(
+            Provider::Jupiter,
+            serde_json::json!({"swapMode": "ExactOut", "otherAmountThreshold": "1010000"}),
+        );
+        assert_eq!(exact_out.min_received(), 500_000);
+    }
+
+    #[test]
+    fn maximum_input_is_the_input_for_exact_in_and_the_bound_for_exact_out() {
+        let exact_in = make_quote(
+            Provider::Jupiter,
+            serde_json::json!({"otherAmountThreshold": "497000"}),
+        );
+        assert_eq!(
+            exact_in.maximum_input().expect("fits"),
+            exact_in.input_amount
+        );
+
+        let exact_out = make_quote(
+            Provider::Jupiter,
+            serde_json::json!({"swapMode": "ExactOut", "otherAmountThreshold": "1010000"}),
+        );
+        assert_eq!(exact_out.min_received(), exact_out.output_amount);
+        assert_eq!(exact_out.maximum_input().expect("fits"), 1_010_000);
+
+        let computed = make_quote(
+            Provider::Jupiter,
+            serde_json::json!({"swapMode": "ExactOut"}),
+        );
+        assert_eq!(computed.maximum_input().expect("fits"), 1_010_000);
+    }
+
+    #[test]
+    fn to_request_reads_titan_shaped_hints() {
+        let taker = Pubkey::new_unique();
+        let quote = make_quote(
+            Provider::Titan,
+            serde_json::json!({
+                "dexes": ["Whirlpool", "Raydium"],
+                "feeBps": 15,
+                "onlyDirectRoutes": true,
+                "taker": taker.to_string(),
+            }),
+        );
+
+        let request = QuoteRequest::from(&quote);
+        assert_eq!(request.dexes.as_deref(), Some("Whirlpool,Raydium"));
+        assert_eq!(request.exclude_dexes, None);
+        assert_eq!(request.platform_fee_bps, Some(15));
+        assert_eq!(request.only_direct_routes, Some(true));
+        assert_eq!(request.taker, Some(taker));
+    }
+
+    #[test]
+    fn minimum_received_and_maximum_spent_follow_swap_mode() {
+        let exact_in = make_quote(
+            Provider::Jupiter,
+            serde_json::json!({"otherAmountThreshold": "497000"}),
+        );
+        assert_eq!(
+            exact_in.maximum_spent().expect("fits"),
+            exact_in.input_amount
+        );
+        assert_eq!(exact_in.minimum_received(), 497_000);
+
+        let exact_out = make_quote(
+            Provider::Jupiter,
+            serde_json::json!({"swapMode": "ExactOut", "otherAmountThreshold": "1010000"}),
+        );
+        assert_eq!(exact_out.minimum_received(), exact_out.output_amount);
+        assert_eq!(exact_out.maximum_spent().expect("fits"), 1_010_000);
+    }
+
+    #[test]
+    fn slippage_percent_normalizes_to_bps() {
+        assert_eq!(Slippage::Percent(0.5).to_bps().expect("valid"), 50);
+        assert_eq!(Slippage::Percent(100.0).to_bps().expect("valid"), 10_000);
+        assert_eq!(Slippage::Bps(75).to_bps().expect("valid"), 75);
+
+        let request = make_quote(Provider::Jupiter, serde_json::Value::Null)
+            .to_request()
+            .with_slippage(Slippage::Percent(1.25));
+        assert_eq!(request.expect("valid").slippage_bps, Some(125));
+    }
+
+    #[test]
+    fn slippage_out_of_range_is_rejected() {
+        for slippage in [
+            Slippage::Percent(150.0),
+            Slippage::Percent(-0.1),
+            Slippage::Percent(f64::NAN),
+            Slippage::Bps(10_001),
+        ] {
+            assert!(
+                matches!(slippage.to_bps(), Err(SwapError::InvalidRequest(_))),
+                "{slippage:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn apply_slippage_rounds_down_and_clamps() {
+        assert_eq!(apply_slippage(999, 50), 994);
+        assert_eq!(apply_slippage(1_000, 0), 1_000);
+        assert_eq!(apply_slippage(1_000, u16::MAX), 0);
+        assert_eq!(apply_slippage(u64::MAX, 0), u64::MAX);
+    }
+
+    #[test]
+    fn apply_slippage_up_rejects_overflow() {
+        assert_eq!(apply_slippage_up(1_000, 50).expect("fits"), 1_005);
+        assert_eq!(apply_slippage_up(u64::MAX, 0).expect("fits"), u64::MAX);
+        assert!(matches!(
+            apply_slippage_up(u64::MAX, 5_000),
+            Err(SwapError::InvalidRequest(_))
+        ));
+
+        let quote = QuoteResponse {
+            input_amount: u64::MAX,
+            slippage_bps: 5_000,
+            ..make_quote(Provider::Titan, serde_json::json!({"swapMode": "ExactOut"}))
+        };
+        assert!(matches!(
+            quote.maximum_input(),
+            Err(SwapError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn route_parses_jupiter_and_flat_route_plans() {
+        let jupiter = make_quote(
+            Provider::Jupiter,
+            serde_json::json!({"routePlan": [{
+                "swapInfo": {"ammKey": "amm1", "label": "Whirlpool", "inAmount": "10", "outAmount": "9"},
+                "percent": 100,
+            }]}),
+        );
+        let hops = jupiter.route();
+        assert_eq!(hops.len(), 1);
+        assert_eq!(hops[0].amm_key.as_deref(), Some("amm1"));
+        assert_eq!(hops[0].label.as_deref(), Some("Whirlpool"));
+        assert_eq!((hops[0].in_amount, hops[0].out_amount), (Some(10), Some(9)));
+        assert_eq!(hops[0].percent, Some(100));
+
+        let flat = make_quote(
+            Provider::Dflow,
+            serde_json::json!({"routePlan": [{"programId": "prog", "inAmount": 5}]}),
+        );
+        assert_eq!(flat.route()[0].program_id.as_deref(), Some("prog"));
+        assert!(make_quote(Provider::Titan, serde_json::json!({}))
+            .route()
+            .is_empty());
+    }
+
+    #[test]
+    fn is_noop_detects_equal_mints_and_zero_amounts() {
+        let quote = make_quote(Provider::Jupiter, serde_json::json!({}));
+        assert!(!quote.is_noop());
+
+        let same_mint = QuoteResponse {
+            output_mint: quote.input_mint,
+            ..quote.clone()
+        };
+        assert!(same_mint.is_noop());
+
+        let zero_output = QuoteResponse {
+            output_amount: 0,
+            ..quote
+        };
+        assert!(zero_output.is_noop());
+    }
+
+    #[test]
+    fn price_is_output_per_input() {
+        let quote = QuoteResponse {
+            input_amount: 1_000_000_000,
+            output_amount: 150_000_000,
+            ..make_quote(Provider::Jupiter, serde_json::Value::Null)
+        };
+
+        assert_eq!(quote.price(), Some(0.15));
+        assert_eq!(quote.inverse_price(), Some(1_000_000_000.0 / 150_000_000.0));
+        // 1 SOL (9 decimals) for 150 USDC (6 decimals)
+        let decimal_price = quote.price_with_decimals(9, 6).expect("non-zero input");
+        assert!((decimal_price - 150.0).abs() < 1e-9, "got {decimal_price}");
+    }
+
+    #[test]
+    fn price_is_none_for_zero_amounts() {
+        let zero_input = QuoteResponse {
+            input_amount: 0,
+            output_amount: 10,
+            ..make_quote(Provider::Jupiter, serde_json::Value::Null)
+        };
+        assert_eq!(zero_input.price(), None);
+        assert_eq!(zero_input.price_with_decimals(9, 6), None);
+        assert_eq!(zero_input.inverse_price(), Some(0.0));
+
+        let zero_output = QuoteResponse {
+            output_amount: 0,
+            ..zero_input
+        };
+        assert_eq!(zero_output.inverse_price(), None);
+    }
+
+    #[test]
+    fn normalized_quote_has_field_parity_across_providers() {
+        let jupiter = make_quote(
+            Provider::Jupiter,
+            serde_json::json!({
+                // a 60/40 split step, then a single leg
+                "routePlan": [
+                    {"swapInfo": {}, "percent": 60},
+                    {"swapInfo": {}, "percent": 40},
+                    {"swapInfo": {}, "percent": 100},
+                ],
+                "platformFee": {"amount": "250", "feeBps": 5},
+            }),
+        );
+        let dflow = make_quote(
+            Provider::Dflow,
+            serde_json::json!({
+                "amount": 1_000_000,
+                "routePlan": [{"venue": "Whirlpool"}, {"venue": "Raydium"}],
+                "platformFee": {"amount": 250},
+            }),
+        );
+        let titan = make_quote(Provider::Titan, serde_json::json!({"amount_out": 500_000}));
+
+        let normalized: Vec<NormalizedQuote> = [&jupiter, &dflow, &titan]
+            .iter()
+            .map(|q| q.normalized())
+            .collect();
+
+        for (quote, n) in [&jupiter, &dflow, &titan].iter().zip(&normalized) {
+            assert_eq!(n.provider, quote.provider);
+            assert_eq!(n.input_mint, quote.input_mint.to_string());
+            assert_eq!(n.output_mint, quote.output_mint.to_string());
+            assert_eq!(n.input_amount, 1_000_000);
+            assert_eq!(n.output_amount, 500_000);
+            assert_eq!(n.price_impact_bps, Some(15));
+            assert_eq!(n.slippage_bps, 100);
+        }
+
+        assert_eq!(normalized[0].fee_amount, Some(250));
+        assert_eq!(normalized[0].hop_count, Some(2));
+        assert_eq!(normalized[1].fee_amount, Some(250));
+        assert_eq!(normalized[1].hop_count, Some(2));
+        assert_eq!(normalized[2].fee_amount, None);
+        assert_eq!(normalized[2].hop_count, None);
+
+        let keys = |n: &NormalizedQuote| {
+            serde_json::to_value(n)
+                .expect("should serialize")
+                .as_object()
+                .expect("should be an object")
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(keys(&normalized[0]), keys(&normalized[1]));
+        assert_eq!(keys(&normalized[1]), keys(&normalized[2]));
+    }
+
+    #[test]
+    fn quote_guard_rejects_high_price_impact() {
+        let guard = QuoteGuard {
+            max_impact_bps: Some(10),
+            ..QuoteGuard::default()
+        };
+        let quote = make_quote(Provider::Jupiter, serde_json::Value::Null);
+
+        let err = guard.check(&quote).expect_err("impact 15 > 10");
+        assert!(matches!(
+            err,
+            SwapError::PriceImpactTooHigh {
+                impact_bps: 15,
+                max_bps: 10
+            }
+        ));
+
+        let lenient = QuoteGuard {
+            max_impact_bps: Some(15),
+            ..QuoteGuard::default()
+        };
+        assert!(lenient.check(&quote).is_ok());
+    }
+
+    #[test]
+    fn quote_guard_ignores_missing_price_impact() {
+        let guard = QuoteGuard {
+            max_impact_bps: Some(1),
+            ..QuoteGuard::default()
+        };
+        let mut quote = make_quote(Provider::Titan, serde_json::Value::Null);
+        quote.price_impact_bps = None;
+
+        assert!(guard.check(&quote).is_ok());
+    }
+
+    #[test]
+    fn quote_guard_rejects_low_output() {
+        let guard = QuoteGuard {
+            min_output: Some(500_001),
+            ..QuoteGuard::default()
+        };
+        let quote = make_quote(Provider::Jupiter, serde_json::Value::Null);
+
+        let err = guard.check(&quote).expect_err("output 500_000 < 500_001");
+        assert!(matches!(
+            err,
+            SwapError::OutputBelowMinimum {
+                output: 500_000,
+                min: 500_001
+            }
+        ));
+    }
+
+    #[test]
+    fn quote_guard_rejects_high_input() {
+        let guard = QuoteGuard {
+            max_input: Some(999_999),
+            ..QuoteGuard::default()
+        };
+        let quote = make_quote(Provider::Jupiter, serde_json::Value::Null);
+
+        let err = guard.check(&quote).expect_err("input 1_000_000 > 999_999");
+        assert!(matches!(
+            err,
+            SwapError::InputAboveMaximum {
+                input: 1_000_000,
+                max: 999_999
+            }
+        ));
+    }
+
+    #[test]
+    fn net_output_after_bps_rounds_fee_up() {
+        let quote = QuoteResponse {
+            output_amount: 1_001,
+            ..make_quote(Provider::Jupiter, serde_json::Value::Null)
+        };
+
+        assert_eq!(quote.net_output_after_bps(0), 1_001);
+        assert_eq!(quote.net_output_after_bps(30), 997);
+        assert_eq!(quote.net_output_after_bps(10_000), 0);
+        assert_eq!(quote.net_output_after_bps(u16::MAX), 0);
+    }
+
+    #[test]
+    fn net_output_after_bps_handles_large_amounts_net_of_platform_fee() {
+        let quote = QuoteResponse {
+            output_amount: u64::MAX,
+            ..make_quote(Provider::Jupiter, serde_json::Value::Null)
+        };
+        assert_eq!(quote.net_output_after_bps(0), u64::MAX);
+        assert_eq!(
+            quote.net_output_after_bps(30),
+            u64::try_from(u128::from(u64::MAX) * 9_970 / 10_000).expect("fits")
+        );
+
+        let with_fee = make_quote(
+            Provider::Dflow,
+            serde_json::json!({"platformFee": {"amount": "100000"}}),
+        );
+        assert_eq!(with_fee.net_output_after_bps(100), 396_000);
+    }
+
+    #[test]
+    fn exact_in_quote_is_better_with_more_output() {
+        let base = make_quote(Provider::Jupiter, serde_json::Value::Null);
+        let more = QuoteResponse {
+            output_amount: base.output_amount + 1,
+            input_amount: base.input_amount + 1,
+            ..base.clone()
+        };
+
+        assert!(more.is_better_than(&base));
+        assert!(!base.is_better_than(&more));
+        assert!(!base.is_better_than(&base));
+    }
+
+    #[test]
+    fn exact_out_quote_is_better_with_less_input() {
+        let base = make_quote(
+            Provider::Jupiter,
+            serde_json::json!({"swapMode": "ExactOut"}),
+        );
+        let cheaper = QuoteResponse {
+            input_amount: base.input_amount - 1,
+            output_amount: base.output_amount - 1,
+            ..base.clone()
+        };
+
+        assert!(cheaper.is_better_than(&base));
+        assert!(!base.is_better_than(&cheaper));
+    }
+
+    #[test]
+    fn quotes_for_different_modes_or_pairs_are_not_comparable() {
+        let exact_in = make_quote(Provider::Jupiter, serde_json::Value::Null);
+        let exact_out = QuoteResponse {
+            input_amount: 1,
+            provider_data: serde_json::json!({"swapMode": "ExactOut"}),
+            ..exact_in.clone()
+        };
+        let other_pair = QuoteResponse {
+            output_amount: exact_in.output_amount * 2,
+            output_mint: Pubkey::new_unique(),
+            ..exact_in.clone()
+        };
+
+        assert!(!exact_out.is_better_than(&exact_in));
+        assert!(!exact_in.is_better_than(&exact_out));
+        assert!(!other_pair.is_better_than(&exact_in));
+    }
+
+    #[test]
+    fn deviation_from_is_signed_bps_of_reference() {
+        // make_quote prices at 500_000 / 1_000_000 = 0.5
+        let quote = make_quote(Provider::Jupiter, serde_json::Value::Null);
+
+        assert!(quote.deviation_from(0.5).abs() < 1e-9);
+        assert!((quote.deviation_from(0.4) - 2_500.0).abs() < 1e-9);
+        assert!((quote.deviation_from(0.625) + 2_000.0).abs() < 1e-9);
+        assert!(quote.deviation_from(0.0).is_nan());
+
+        let empty = QuoteResponse {
+            input_amount: 0,
+            ..quote
+        };
+        assert!(empty.deviation_from(0.5).is_nan());
+    }
+
+    #[test]
+    fn reference_price_rejects_deviation_either_way() {
+        let quote = make_quote(Provider::Jupiter, serde_json::Value::Null);
+        let reference = |price| ReferencePrice {
+            price,
+            max_deviation_bps: 100,
+        };
+
+        assert!(reference(0.5).check(&quote).is_ok());
+        assert!(reference(0.496).check(&quote).is_ok());
+        for price in [0.52, 0.48, 0.0] {
+            let err = reference(price).check(&quote).expect_err("beyond 100 bps");
+            assert!(
+                matches!(err, SwapError::PriceDeviationTooHigh { max_bps: 100, .. }),
+                "got {err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn quote_guard_rejects_long_route() {
+        let guard = QuoteGuard {
+            max_hops: Some(2),
+            ..QuoteGuard::default()
+        };
+        let step = serde_json::json!({"swapInfo": {"label": "Whirlpool"}, "percent": 100});
+        let quote = make_quote(
+            Provider::Jupiter,
+            serde_json::json!({"routePlan": [step.clone(), step.clone(), step.clone(), step]}),
+        );
+
+        let err = guard.check(&quote).expect_err("4 hops > 2");
+        assert!(matches!(err, SwapError::RouteTooLong { hops: 4, max: 2 }));
+
+        let direct = make_quote(Provider::Jupiter, serde_json::Value::Null);
+        assert!(guard.check(&direct).is_ok());
+    }
+
+    #[test]
+    fn quote_guard_counts_split_legs_as_one_hop() {
+        let guard = QuoteGuard {
+            max_hops: Some(2),
+            ..QuoteGuard::default()
+        };
+        let leg = |percent: u8| serde_json::json!({"swapInfo": {"label": "Whirlpool"}, "percent": percent});
+        // SOL -> USDC split 60/40 across two pools, then USDC -> BONK through one
+        let split = make_quote(
+            Provider::Jupiter,
+            serde_json::json!({"routePlan": [leg(60), leg(40), leg(100)]}),
+        );
+        assert_eq!(split.hop_count(), 2);
+        assert!(guard.check(&split).is_ok());
+
+        let longer = make_quote(
+            Provider::Jupiter,
+            serde_json::json!({"routePlan": [leg(60), leg(40), leg(100), leg(50), leg(50)]}),
+        );
+        let err = guard.check(&longer).expect_err("3 hops > 2");
+        assert!(matches!(err, SwapError::RouteTooLong { hops: 3, max: 2 }));
+    }
+
+    #[test]
+    fn quote_guard_combined_bounds() {
+        let quote = make_quote(Provider::Jupiter, serde_json::Value::Null);
+
+        let passing = QuoteGuard {
+            max_impact_bps: Some(300),
+            min_output: Some(400_000),
+            max_input: Some(1_000_000),
+            max_hops: Some(3),
+        };
+        assert!(passing.check(&quote).is_ok());
+
+        let failing_output = QuoteGuard {
+            min_output: Some(600_000),
+            ..passing
+        };
+        assert!(matches!(
+            failing_output.check(&quote),
+            Err(SwapError::OutputBelowMinimum { .. })
+        ));
+
+        assert!(QuoteGuard::default().check(&quote).is_ok());
+    }
+
+    #[test]
+    fn provider_display_and_serialize_match() {
+        for (provider, expected) in [
+            (Provider::Jupiter, "Jupiter"),
+            (Provider::Titan, "Titan"),
+            (Provider::Dflow, "Dflow"),
+        ] {
+            assert_eq!(provider.to_string(), expected);
+            let json = serde_json::to_value(provider).expect("should serialize");
+            assert_eq!(json, expected);
+        }
+    }
+
+    #[test]
+    fn provider_all_lists_every_variant_once() {
+        let count_variants = |p: &Provider| match p {
+            Provider::Jupiter | Provider::Titan | Provider::Dflow => 1,
+        };
+        assert_eq!(Provider::ALL.iter().map(count_variants).sum::<usize>(), 3);
+        assert_eq!(Provider::ALL.len(), 3);
+
+        for (i, provider) in Provider::ALL.iter().enumerate() {
+            assert!(!provider.to_string().is_empty());
+            assert!(
+                !Provider::ALL[i + 1..].contains(provider),
+                "{provider} listed twice"
+            );
         }
     }
 
@@ -0,0 +1,3 @@
+mod quote_and_simulate_all;
+mod simulate_output_delta;
+mod token_programs;
@@ -0,0 +1,38 @@
+use crate::common::{build_quote_request, build_swap_config, load_test_env};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::signer::Signer;
+use solana_swap_routers::SwapAggregator;
+
+#[tokio::test]
+#[ignore = "requires env vars and real API access"]
+async fn test_quote_and_simulate_all() {
+    let env = load_test_env();
+    let aggregator = SwapAggregator::new(build_swap_config(&env, None));
+    let request = build_quote_request(&env, None);
+    let rpc_client = RpcClient::new(env.rpc_url.clone());
+
+    let results = aggregator
+        .quote_and_simulate_all(&request, &env.keypair.pubkey(), &rpc_client)
+        .await;
+
+    assert!(
+        !results.is_empty(),
+        "expected at least one configured provider"
+    );
+    for (provider, outcome) in &results {
+        match outcome {
+            Ok((quote, summary)) => println!(
+                "  {provider}: out {} · delta {:?} · CU consumed: {:?} · {} log lines",
+                quote.output_amount,
+                summary.output_delta,
+                summary.consumed_cu,
+                summary.logs.len()
+            ),
+            Err(e) => println!("  {provider}: failed: {e}"),
+        }
+    }
+    assert!(
+        results.iter().any(|(_, outcome)| outcome.is_ok()),
+        "expected at least one provider to simulate successfully"
+    );
+}
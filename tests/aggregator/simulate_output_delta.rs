@@ -0,0 +1,38 @@
+use crate::common::{build_quote_request, build_swap_config, load_test_env, print_quote};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::signer::Signer;
+use solana_swap_routers::{simulate::simulate, Provider, SwapAggregator};
+
+#[tokio::test]
+#[ignore = "requires env vars and real API access"]
+async fn test_simulated_output_delta_meets_min_received() {
+    let env = load_test_env();
+    let aggregator = SwapAggregator::new(build_swap_config(&env, None));
+    let request = build_quote_request(&env, None);
+    let rpc_client = RpcClient::new(env.rpc_url.clone());
+    let pubkey = env.keypair.pubkey();
+
+    let quote = aggregator
+        .quote(Provider::Jupiter, &request)
+        .await
+        .expect("jupiter quote should succeed");
+    print_quote("aggregator::simulate_output_delta", &quote);
+
+    let result = aggregator
+        .swap(&quote, &pubkey, &rpc_client)
+        .await
+        .expect("jupiter swap should succeed");
+    let summary = simulate(&result, &quote, &pubkey, &rpc_client)
+        .await
+        .expect("simulation should deliver at least min_received");
+
+    println!(
+        "  output delta: {:?} · min received: {} · CU consumed: {:?}",
+        summary.output_delta,
+        quote.min_received(),
+        summary.consumed_cu
+    );
+    if let Some(delta) = summary.output_delta {
+        assert!(delta >= quote.min_received());
+    }
+}
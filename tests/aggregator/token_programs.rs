@@ -0,0 +1,35 @@
+use crate::common::{build_quote_request, build_swap_config, load_test_env};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_swap_routers::SwapAggregator;
+
+#[tokio::test]
+#[ignore = "requires env vars and real API access"]
+async fn test_detect_token_programs() {
+    let env = load_test_env();
+    let aggregator = SwapAggregator::new(build_swap_config(&env, None));
+    let request = build_quote_request(&env, None);
+    let rpc_client = RpcClient::new(env.rpc_url.clone());
+
+    let detected = aggregator
+        .detect_token_programs(&request, &rpc_client)
+        .await
+        .expect("token program detection should succeed");
+
+    let input_owner = rpc_client
+        .get_account(&env.input_mint)
+        .await
+        .expect("input mint should exist")
+        .owner;
+    let output_owner = rpc_client
+        .get_account(&env.output_mint)
+        .await
+        .expect("output mint should exist")
+        .owner;
+
+    println!(
+        "  token programs: input {:?} · output {:?}",
+        detected.input_token_program, detected.output_token_program
+    );
+    assert_eq!(detected.input_token_program, Some(input_owner));
+    assert_eq!(detected.output_token_program, Some(output_owner));
+}
@@ -6,7 +6,9 @@ use solana_sdk::{
     transaction::VersionedTransaction,
 };
 
-use solana_swap_routers::{QuoteRequest, QuoteResponse, SwapConfig, SwapResult};
+use solana_swap_routers::{
+    signer::load_keypair_from_file, QuoteRequest, QuoteResponse, SwapConfig, SwapResult,
+};
 
 pub struct TestEnv {
     pub input_mint: Pubkey,
@@ -51,12 +53,7 @@ pub fn load_test_env() -> TestEnv {
         .unwrap_or(300);
     let send_tx = optional_env("TEST_SEND_TX").is_some_and(|v| v == "1" || v == "true");
 
-    let keypair_bytes = std::fs::read_to_string(&keypair_path)
-        .unwrap_or_else(|e| panic!("failed to read keypair at {keypair_path}: {e}"));
-    let keypair_vec: Vec<u8> = serde_json::from_str(&keypair_bytes)
-        .unwrap_or_else(|e| panic!("failed to parse keypair JSON at {keypair_path}: {e}"));
-    let keypair = Keypair::try_from(keypair_vec.as_slice())
-        .unwrap_or_else(|e| panic!("invalid keypair bytes at {keypair_path}: {e}"));
+    let keypair = load_keypair_from_file(&keypair_path).unwrap_or_else(|e| panic!("{e}"));
 
     TestEnv {
         input_mint,
@@ -78,11 +75,27 @@ pub fn build_swap_config(env: &TestEnv, dflow_max_route_length: Option<u32>) ->
         default_slippage_bps: env.slippage_bps,
         jupiter_api_url: None,
         jupiter_api_key: env.jupiter_api_key.clone(),
+        jupiter_tier: None,
+        jupiter_use_token_ledger: None,
+        jupiter_dynamic_cu_limit: None,
         titan_ws_url: env.titan_ws_url.clone(),
         titan_token: env.titan_token.clone(),
         dflow_api_url: None,
         dflow_api_key: env.dflow_api_key.clone(),
         dflow_max_route_length,
+        dflow_priority_fee_lamports: None,
+        dflow_jito_tip_lamports: None,
+        quote_guard: None,
+        swap_cache_ttl: None,
+        reserve_sol_for_fees: None,
+        blockhash_refresh_retries: None,
+        blockhash_cache_ttl: None,
+        slippage_escalation: None,
+        record_dir: None,
+        rank_by_net_output: None,
+        max_response_bytes: None,
+        jupiter_request_signer: None,
+        dflow_request_signer: None,
     }
 }
 
@@ -99,6 +112,15 @@ pub fn build_quote_request(env: &TestEnv, only_direct_routes: Option<bool>) -> Q
         swap_mode: None,
         dexes: None,
         exclude_dexes: None,
+        input_is_fee_on_transfer: false,
+        fee_account: None,
+        platform_fee_bps: None,
+        input_token_program: None,
+        output_token_program: None,
+        provider_params: None,
+        reference_price: None,
+        providers: None,
+        preferred_intermediate_mints: None,
     }
 }
 
@@ -114,7 +136,7 @@ fn short_pubkey(pk: &Pubkey) -> String {
 pub fn print_quote(test_name: &str, quote: &QuoteResponse) {
     let bar = "━".repeat(56);
     println!("\n{bar}");
-    println!("  {test_name}  [{provider}]", provider = quote.provider,);
+    println!("  {test_name}  [{provider}]", provider = quote.provider);
     println!("{bar}");
     println!(
         "  {in_amount} {in_mint}  →  {out_amount} {out_mint}",
@@ -140,29 +162,20 @@ pub async fn finalize_swap(
     let payer = keypair.pubkey();
 
     match &result {
-        SwapResult::Instructions {
-            instructions,
-            compute_units,
-            ..
-        } => {
+        SwapResult::Instructions { instructions, .. } => {
             assert!(
                 !instructions.is_empty(),
                 "expected at least one instruction"
             );
-            let cu = compute_units.map_or("n/a".to_string(), |c| c.to_string());
-            println!("  swap: {} instructions · CU: {cu}", instructions.len());
         }
         SwapResult::Transaction { transaction, .. } => {
             assert!(
                 !transaction.message.instructions().is_empty(),
                 "expected at least one instruction in transaction"
             );
-            println!(
-                "  swap: transaction ({} instructions)",
-                transaction.message.instructions().len()
-            );
         }
     }
+    println!("  swap: {result}");
 
     if send {
         let blockhash = rpc_client
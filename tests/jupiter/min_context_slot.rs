@@ -0,0 +1,43 @@
+use crate::common::{build_quote_request, build_swap_config, load_test_env, print_quote};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::signer::Signer;
+use solana_swap_routers::{Provider, SwapAggregator, SwapError};
+
+#[tokio::test]
+#[ignore = "requires env vars and real API access"]
+async fn test_jupiter_swap_rejects_lagging_rpc() {
+    let env = load_test_env();
+    let aggregator = SwapAggregator::new(build_swap_config(&env, None));
+    let request = build_quote_request(&env, None);
+    let rpc_client = RpcClient::new(env.rpc_url.clone());
+    let pubkey = env.keypair.pubkey();
+
+    let quote = aggregator
+        .quote(Provider::Jupiter, &request)
+        .await
+        .expect("jupiter quote should succeed");
+
+    print_quote("jupiter::min_context_slot", &quote);
+    println!("  quote context slot: {:?}", quote.context_slot());
+
+    let current_slot = rpc_client.get_slot().await.expect("slot should load");
+    // Far ahead of the tip, so the node cannot have reached it yet.
+    let unreachable_slot = current_slot + 1_000_000;
+
+    match aggregator
+        .swap_at_slot(&quote, &pubkey, &rpc_client, Some(unreachable_slot))
+        .await
+    {
+        Err(SwapError::MinContextSlotNotReached { min_context_slot }) => {
+            assert_eq!(min_context_slot, unreachable_slot);
+        }
+        Ok(_) => println!("  route used no lookup tables; nothing read at the slot"),
+        Err(e) => panic!("unexpected error: {e}"),
+    }
+
+    aggregator
+        .swap_at_slot(&quote, &pubkey, &rpc_client, Some(current_slot))
+        .await
+        .expect("swap at a reached slot should succeed");
+    println!("  jupiter::min_context_slot: OK ✓\n");
+}
@@ -1,2 +1,6 @@
+mod min_context_slot;
+mod preflight;
+#[cfg(feature = "jupiter")]
+mod program_labels;
 mod quote_and_swap;
 mod quote_and_swap_direct;
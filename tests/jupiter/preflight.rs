@@ -0,0 +1,30 @@
+use crate::common::{build_quote_request, build_swap_config, load_test_env, print_quote};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::signer::Signer;
+use solana_swap_routers::{Provider, SwapAggregator};
+
+#[tokio::test]
+#[ignore = "requires env vars and real API access"]
+async fn test_jupiter_preflight() {
+    let env = load_test_env();
+    let aggregator = SwapAggregator::new(build_swap_config(&env, None));
+    let request = build_quote_request(&env, None);
+    let rpc_client = RpcClient::new(env.rpc_url.clone());
+    let pubkey = env.keypair.pubkey();
+
+    let quote = aggregator
+        .quote(Provider::Jupiter, &request)
+        .await
+        .expect("jupiter quote should succeed");
+
+    print_quote("jupiter::preflight", &quote);
+
+    let report = aggregator
+        .preflight(&quote, &pubkey, &rpc_client)
+        .await
+        .expect("preflight should succeed");
+
+    assert_eq!(report.required_input, quote.input_amount);
+    println!("  preflight: {report:?}");
+    println!("  jupiter::preflight: OK ✓\n");
+}
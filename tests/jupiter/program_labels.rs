@@ -0,0 +1,37 @@
+use crate::common::{build_quote_request, build_swap_config, load_test_env, print_quote};
+use solana_swap_routers::{Provider, SwapAggregator};
+
+#[tokio::test]
+#[ignore = "requires env vars and real API access"]
+async fn test_jupiter_program_labels() {
+    let env = load_test_env();
+    let aggregator = SwapAggregator::new(build_swap_config(&env, None));
+    let request = build_quote_request(&env, None);
+    let jupiter = aggregator
+        .jupiter
+        .as_ref()
+        .expect("jupiter should be configured");
+
+    let labels = jupiter
+        .program_labels()
+        .await
+        .expect("program labels should load");
+    assert!(!labels.is_empty(), "expected at least one program label");
+
+    let quote = aggregator
+        .quote(Provider::Jupiter, &request)
+        .await
+        .expect("jupiter quote should succeed");
+
+    print_quote("jupiter::program_labels", &quote);
+
+    let route = jupiter
+        .labeled_route(&quote)
+        .await
+        .expect("route should be labeled");
+    for hop in &route {
+        println!("  hop: {:?} via {:?}", hop.label, hop.amm_key);
+    }
+    assert!(!route.is_empty(), "expected at least one route hop");
+    println!("  jupiter::program_labels: OK ✓\n");
+}
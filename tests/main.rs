@@ -12,6 +12,7 @@
 
 pub mod common;
 
+mod aggregator;
 mod dflow;
 mod jupiter;
 mod titan;